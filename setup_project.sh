@@ -1,271 +0,0 @@
-#!/bin/bash
-
-# Create project structure
-mkdir -p src/wasm_bindings src/adapters src/utils examples tests www
-
-# Create Cargo.toml
-cat > Cargo.toml << EOL
-[package]
-name = "rig-wasm-compiler"
-version = "0.1.0"
-edition = "2021"
-
-[lib]
-crate-type = ["cdylib", "rlib"]
-
-[dependencies]
-rig-core = { git = "https://github.com/0xPlaygrounds/rig.git", branch = "main" }
-wasm-bindgen = "0.2"
-js-sys = "0.3"
-wasm-bindgen-futures = "0.4"
-console_error_panic_hook = "0.1"
-tokio_with_wasm = "0.7"
-serde = { version = "1.0", features = ["derive"] }
-serde-wasm-bindgen = "0.5"
-getrandom = { version = "0.2", features = ["js"] }
-reqwest = { version = "0.11", features = ["json"] }
-gloo-net = "0.3"
-async-trait = "0.1"
-
-[dev-dependencies]
-wasm-bindgen-test = "0.3"
-
-[build-dependencies]
-wasm-pack = "0.12"
-
-[features]
-default = ["console_error_panic_hook"]
-EOL
-
-# Create src/main.rs
-cat > src/main.rs << EOL
-use rig_wasm_compiler::compile_to_wasm;
-
-fn main() {
-    let input_file = std::env::args().nth(1).expect("Please provide an input file");
-    let output_dir = std::env::args().nth(2).expect("Please provide an output directory");
-
-    match compile_to_wasm(&input_file, &output_dir) {
-        Ok(_) => println!("Successfully compiled to WASM"),
-        Err(e) => eprintln!("Compilation failed: {}", e),
-    }
-}
-EOL
-
-# Create src/lib.rs
-cat > src/lib.rs << EOL
-mod config;
-mod wasm_bindings;
-mod adapters;
-mod utils;
-
-use wasm_bindgen::prelude::*;
-use config::WasmConfig;
-
-#[wasm_bindgen]
-pub fn initialize_rig_wasm(config: JsValue) -> Result<(), JsValue> {
-    let config: WasmConfig = serde_wasm_bindgen::from_value(config)?;
-    // Initialize Rig with WASM-specific configuration
-    Ok(())
-}
-
-pub fn compile_to_wasm(input_file: &str, output_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
-    // Implementation of the WASM compilation process
-    Ok(())
-}
-EOL
-
-# Create src/config.rs
-cat > src/config.rs << EOL
-use serde::{Deserialize, Serialize};
-
-#[derive(Serialize, Deserialize)]
-pub struct WasmConfig {
-    pub api_key: String,
-    pub model: String,
-    // Add other configuration options
-}
-EOL
-
-# Create src/wasm_bindings/mod.rs
-cat > src/wasm_bindings/mod.rs << EOL
-pub mod agent;
-pub mod rag;
-EOL
-
-# Create src/wasm_bindings/agent.rs
-cat > src/wasm_bindings/agent.rs << EOL
-use wasm_bindgen::prelude::*;
-use rig_core::agent::Agent;
-use crate::adapters::http_client::WasmHttpClient;
-
-#[wasm_bindgen]
-pub struct WasmAgent {
-    inner: Agent<WasmHttpClient>,
-}
-
-#[wasm_bindgen]
-impl WasmAgent {
-    #[wasm_bindgen(constructor)]
-    pub fn new(config: JsValue) -> Result<WasmAgent, JsValue> {
-        // Initialize Agent with WASM-specific configuration
-        unimplemented!()
-    }
-
-    pub async fn process(&self, input: &str) -> Result<String, JsValue> {
-        // Process input using the Agent
-        unimplemented!()
-    }
-}
-EOL
-
-# Create src/wasm_bindings/rag.rs
-cat > src/wasm_bindings/rag.rs << EOL
-// Implement RAG-specific WASM bindings
-EOL
-
-# Create src/adapters/mod.rs
-cat > src/adapters/mod.rs << EOL
-pub mod http_client;
-pub mod vector_store;
-EOL
-
-# Create src/adapters/http_client.rs
-cat > src/adapters/http_client.rs << EOL
-use async_trait::async_trait;
-use rig_core::http_client::HttpClient;
-use gloo_net::http::Request;
-
-pub struct WasmHttpClient;
-
-#[async_trait(?Send)]
-impl HttpClient for WasmHttpClient {
-    async fn get(&self, url: &str) -> Result<String, Box<dyn std::error::Error>> {
-        // Implement GET request using gloo-net
-        unimplemented!()
-    }
-
-    async fn post(&self, url: &str, body: &str) -> Result<String, Box<dyn std::error::Error>> {
-        // Implement POST request using gloo-net
-        unimplemented!()
-    }
-}
-EOL
-
-# Create src/adapters/vector_store.rs
-cat > src/adapters/vector_store.rs << EOL
-// Implement WASM-compatible vector store
-EOL
-
-# Create src/utils/mod.rs
-cat > src/utils/mod.rs << EOL
-pub mod wasm_utils;
-EOL
-
-# Create src/utils/wasm_utils.rs
-cat > src/utils/wasm_utils.rs << EOL
-use wasm_bindgen::prelude::*;
-
-pub fn set_panic_hook() {
-    #[cfg(feature = "console_error_panic_hook")]
-    console_error_panic_hook::set_once();
-}
-
-#[wasm_bindgen]
-pub fn init_wasm() {
-    set_panic_hook();
-}
-EOL
-
-# Create examples/simple_agent.rs
-cat > examples/simple_agent.rs << EOL
-// Example of a simple Rig agent compiled to WASM
-EOL
-
-# Create examples/rag_agent.rs
-cat > examples/rag_agent.rs << EOL
-// Example of a RAG agent compiled to WASM
-EOL
-
-# Create tests/integration_tests.rs
-cat > tests/integration_tests.rs << EOL
-// Integration tests for rig-wasm-compiler
-EOL
-
-# Create tests/wasm_tests.rs
-cat > tests/wasm_tests.rs << EOL
-// WASM-specific tests
-EOL
-
-# Create build.rs
-cat > build.rs << EOL
-fn main() {
-    println!("cargo:rerun-if-changed=build.rs");
-    // Add custom build steps for WASM compilation
-}
-EOL
-
-# Create www/index.html
-cat > www/index.html << EOL
-<!DOCTYPE html>
-<html>
-  <head>
-    <meta charset="utf-8">
-    <title>Rig WASM Demo</title>
-  </head>
-  <body>
-    <script src="./index.js"></script>
-  </body>
-</html>
-EOL
-
-# Create www/index.js
-cat > www/index.js << EOL
-// JavaScript code to interact with Rig WASM modules
-EOL
-
-# Create www/webpack.config.js
-cat > www/webpack.config.js << EOL
-const path = require('path');
-
-module.exports = {
-  entry: "./index.js",
-  output: {
-    path: path.resolve(__dirname, "dist"),
-    filename: "index.js",
-  },
-  mode: "development"
-};
-EOL
-
-# Create .gitignore
-cat > .gitignore << EOL
-/target
-**/*.rs.bk
-Cargo.lock
-bin/
-pkg/
-wasm-pack.log
-www/dist/
-EOL
-
-# Create README.md
-cat > README.md << EOL
-# Rig WASM Compiler
-
-A tool to compile Rig applications to WebAssembly (WASM), enabling Rig-based LLM applications to run in web browsers.
-
-## Usage
-
-[Add usage instructions here]
-
-## Development
-
-[Add development instructions here]
-
-## License
-
-[Add license information here]
-EOL
-
-echo "Project structure and starter files created successfully!"
\ No newline at end of file
@@ -1,2 +1,13 @@
+#[cfg(not(target_os = "wasi"))]
 pub mod http_client;
+#[cfg(target_os = "wasi")]
+pub mod wasi_executor;
+#[cfg(target_os = "wasi")]
+pub mod wasi_http_client;
 pub mod vector_store;
+
+// The HttpClient agents are built with: fetch in the browser, wasi:http on WASI
+#[cfg(not(target_os = "wasi"))]
+pub type DefaultHttpClient = http_client::WasmHttpClient;
+#[cfg(target_os = "wasi")]
+pub type DefaultHttpClient = wasi_http_client::WasiHttpClient;
@@ -0,0 +1,73 @@
+// A single-threaded executor for WASI components. Futures wait on wasi:io
+// pollables with `wait_for`, and `block_on` sleeps in wasi:io/poll until one
+// of them is ready.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::{pin, Pin};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use wasi::io::poll::{poll, Pollable};
+
+thread_local! {
+    // Pollables the running future is waiting on
+    static PENDING: RefCell<Vec<Rc<Pollable>>> = RefCell::new(Vec::new());
+}
+
+struct WaitFor {
+    pollable: Rc<Pollable>,
+}
+
+impl Future for WaitFor {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _context: &mut Context<'_>) -> Poll<()> {
+        if self.pollable.ready() {
+            return Poll::Ready(());
+        }
+        PENDING.with(|pending| pending.borrow_mut().push(self.pollable.clone()));
+        Poll::Pending
+    }
+}
+
+// Resolves once `pollable` is ready
+pub fn wait_for(pollable: Pollable) -> impl Future<Output = ()> {
+    WaitFor {
+        pollable: Rc::new(pollable),
+    }
+}
+
+// Set by futures that yield and ask to be polled again straight away
+struct Woken(AtomicBool);
+
+impl Wake for Woken {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+// Component exports are synchronous, so each call runs its future to completion
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let woken = Arc::new(Woken(AtomicBool::new(false)));
+    let waker = Waker::from(woken.clone());
+    let mut context = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+        // Taken before polling again, so no pollable outlives the resource
+        // it was subscribed to
+        let pending = PENDING.with(|pending| pending.take());
+        if woken.0.swap(false, Ordering::Relaxed) {
+            continue;
+        }
+        if pending.is_empty() {
+            panic!("A future is waiting on something other than a WASI pollable");
+        }
+        let pollables: Vec<&Pollable> = pending.iter().map(|pollable| pollable.as_ref()).collect();
+        poll(&pollables);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::adapters::wasi_executor::wait_for;
+use async_trait::async_trait;
+use rig_core::http_client::HttpClient;
+use std::collections::HashMap;
+use std::error::Error;
+use wasi::http::outgoing_handler;
+use wasi::http::types::{Fields, IncomingBody, Method, OutgoingBody, OutgoingRequest, Scheme};
+use wasi::io::streams::StreamError;
+
+// WASI streams accept at most this many bytes per blocking write
+const WRITE_CHUNK: usize = 4096;
+const READ_CHUNK: u64 = 64 * 1024;
+
+// HttpClient for WASI components, sending requests through the host's
+// wasi:http/outgoing-handler instead of the browser's fetch
+pub struct WasiHttpClient;
+
+impl WasiHttpClient {
+    pub fn new() -> Self {
+        WasiHttpClient
+    }
+}
+
+fn http_error(e: impl std::fmt::Debug) -> Box<dyn Error> {
+    format!("WASI HTTP request failed: {:?}", e).into()
+}
+
+// Splits "https://host:port/path?query" into scheme, authority and path
+fn split_url(url: &str) -> Result<(Scheme, String, String), Box<dyn Error>> {
+    let (scheme, rest) = url
+        .split_once("://")
+        .ok_or_else(|| format!("Not an absolute URL: {}", url))?;
+    let scheme = match scheme {
+        "http" => Scheme::Http,
+        "https" => Scheme::Https,
+        other => Scheme::Other(other.to_string()),
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    Ok((scheme, authority.to_string(), path.to_string()))
+}
+
+async fn send(method: Method, url: &str, content_type: Option<&str>, body: &[u8]) -> Result<String, Box<dyn Error>> {
+    let (scheme, authority, path) = split_url(url)?;
+
+    let headers = Fields::new();
+    if let Some(content_type) = content_type {
+        headers
+            .append(&"content-type".to_string(), &content_type.as_bytes().to_vec())
+            .map_err(http_error)?;
+    }
+
+    let request = OutgoingRequest::new(headers);
+    request.set_method(&method).map_err(http_error)?;
+    request.set_scheme(Some(&scheme)).map_err(http_error)?;
+    request.set_authority(Some(&authority)).map_err(http_error)?;
+    request.set_path_with_query(Some(&path)).map_err(http_error)?;
+
+    let outgoing_body = request.body().map_err(http_error)?;
+    let future_response = outgoing_handler::handle(request, None).map_err(http_error)?;
+    {
+        let stream = outgoing_body.write().map_err(http_error)?;
+        for chunk in body.chunks(WRITE_CHUNK) {
+            stream.blocking_write_and_flush(chunk).map_err(http_error)?;
+        }
+    }
+    OutgoingBody::finish(outgoing_body, None).map_err(http_error)?;
+
+    wait_for(future_response.subscribe()).await;
+    let response = future_response
+        .get()
+        .ok_or("WASI HTTP response was not ready")?
+        .map_err(http_error)?
+        .map_err(http_error)?;
+
+    let status = response.status();
+    let incoming_body = response.consume().map_err(http_error)?;
+    let mut bytes = Vec::new();
+    {
+        let stream = incoming_body.stream().map_err(http_error)?;
+        loop {
+            match stream.read(READ_CHUNK) {
+                Ok(chunk) if chunk.is_empty() => wait_for(stream.subscribe()).await,
+                Ok(chunk) => bytes.extend(chunk),
+                Err(StreamError::Closed) => break,
+                Err(e) => return Err(http_error(e)),
+            }
+        }
+    }
+    IncomingBody::finish(incoming_body);
+
+    let text = String::from_utf8(bytes)?;
+    if !(200..300).contains(&status) {
+        return Err(format!("HTTP {} from {}: {}", status, url, text).into());
+    }
+    Ok(text)
+}
+
+fn encode_form(form: &HashMap<String, String>) -> String {
+    fn encode(value: &str) -> String {
+        value
+            .bytes()
+            .map(|byte| match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+                b' ' => "+".to_string(),
+                _ => format!("%{:02X}", byte),
+            })
+            .collect()
+    }
+    form.iter()
+        .map(|(key, value)| format!("{}={}", encode(key), encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[async_trait(?Send)]
+impl HttpClient for WasiHttpClient {
+    async fn get(&self, url: &str) -> Result<String, Box<dyn std::error::Error>> {
+        send(Method::Get, url, None, &[]).await
+    }
+
+    async fn post(&self, url: &str, body: &str) -> Result<String, Box<dyn std::error::Error>> {
+        send(Method::Post, url, Some("text/plain;charset=UTF-8"), body.as_bytes()).await
+    }
+
+    async fn post_json<T: serde::Serialize + ?Sized>(
+        &self,
+        url: &str,
+        json: &T,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let body = serde_json::to_vec(json)?;
+        send(Method::Post, url, Some("application/json"), &body).await
+    }
+
+    async fn post_form(
+        &self,
+        url: &str,
+        form: &HashMap<String, String>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let body = encode_form(form);
+        send(Method::Post, url, Some("application/x-www-form-urlencoded"), body.as_bytes()).await
+    }
+}
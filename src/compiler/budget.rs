@@ -0,0 +1,317 @@
+use crate::compiler::error::CompileError;
+use crate::compiler::report::format_size;
+use crate::compiler::wasm_module::function_sizes;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// Limits for one group of files. Unset limits are not checked.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SizeLimit {
+    #[serde(deserialize_with = "deserialize_size")]
+    pub raw: Option<u64>,
+    #[serde(deserialize_with = "deserialize_size")]
+    pub gzip: Option<u64>,
+    #[serde(deserialize_with = "deserialize_size")]
+    pub brotli: Option<u64>,
+}
+
+impl SizeLimit {
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_none() && self.gzip.is_none() && self.brotli.is_none()
+    }
+}
+
+// How much the module may weigh, for the .wasm, the JS glue and both together.
+// Sizes accept plain byte counts or strings such as "150 KiB".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SizeBudget {
+    pub wasm: SizeLimit,
+    pub js: SizeLimit,
+    pub total: SizeLimit,
+    // How many of the largest functions to list when the budget is exceeded
+    pub top: usize,
+}
+
+impl Default for SizeBudget {
+    fn default() -> Self {
+        Self {
+            wasm: SizeLimit::default(),
+            js: SizeLimit::default(),
+            total: SizeLimit::default(),
+            top: 10,
+        }
+    }
+}
+
+impl SizeBudget {
+    pub fn is_empty(&self) -> bool {
+        self.wasm.is_empty() && self.js.is_empty() && self.total.is_empty()
+    }
+
+    // Reads a budget from a TOML file
+    pub fn from_file(path: &Path) -> Result<Self, CompileError> {
+        let contents = fs::read_to_string(path)?;
+        toml::from_str(&contents).map_err(|e| {
+            CompileError::InvalidInput(format!("Invalid size budget in {}: {}", path.display(), e))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct MeasuredSize {
+    pub raw: u64,
+    pub gzip: u64,
+    pub brotli: u64,
+}
+
+impl MeasuredSize {
+    pub fn of(bytes: &[u8]) -> io::Result<Self> {
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::best());
+        gzip.write_all(bytes)?;
+        let gzip = gzip.finish()?.len() as u64;
+
+        let mut brotli = Vec::new();
+        {
+            let mut writer = brotli::CompressorWriter::new(&mut brotli, 4096, 11, 22);
+            writer.write_all(bytes)?;
+        }
+
+        Ok(MeasuredSize {
+            raw: bytes.len() as u64,
+            gzip,
+            brotli: brotli.len() as u64,
+        })
+    }
+
+    fn add(self, other: MeasuredSize) -> MeasuredSize {
+        MeasuredSize {
+            raw: self.raw + other.raw,
+            gzip: self.gzip + other.gzip,
+            brotli: self.brotli + other.brotli,
+        }
+    }
+}
+
+// Raw and compressed sizes of the files a page downloads
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetReport {
+    pub wasm: MeasuredSize,
+    pub js: MeasuredSize,
+    pub total: MeasuredSize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetViolation {
+    // "wasm", "js" or "total"
+    pub group: String,
+    // "raw", "gzip" or "brotli"
+    pub encoding: String,
+    pub limit: u64,
+    pub actual: u64,
+}
+
+impl fmt::Display for BudgetViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}) is {}, over the {} budget by {}",
+            self.group,
+            self.encoding,
+            format_size(self.actual),
+            format_size(self.limit),
+            format_size(self.actual - self.limit)
+        )
+    }
+}
+
+fn check_limit(group: &str, limit: &SizeLimit, size: MeasuredSize, violations: &mut Vec<BudgetViolation>) {
+    let checks = [
+        ("raw", limit.raw, size.raw),
+        ("gzip", limit.gzip, size.gzip),
+        ("brotli", limit.brotli, size.brotli),
+    ];
+    for (encoding, limit, actual) in checks {
+        if let Some(limit) = limit {
+            if actual > limit {
+                violations.push(BudgetViolation {
+                    group: group.to_string(),
+                    encoding: encoding.to_string(),
+                    limit,
+                    actual,
+                });
+            }
+        }
+    }
+}
+
+// Measures the module and its glue, failing with `BudgetExceeded` when any limit is crossed
+pub fn check_budget(
+    budget: &SizeBudget,
+    wasm_file: &Path,
+    js_files: &[PathBuf],
+) -> Result<BudgetReport, CompileError> {
+    let wasm_bytes = fs::read(wasm_file)?;
+    let wasm = MeasuredSize::of(&wasm_bytes)?;
+
+    let mut js = MeasuredSize::default();
+    for file in js_files {
+        js = js.add(MeasuredSize::of(&fs::read(file)?)?);
+    }
+    let total = wasm.add(js);
+
+    let mut violations = Vec::new();
+    check_limit("wasm", &budget.wasm, wasm, &mut violations);
+    check_limit("js", &budget.js, js, &mut violations);
+    check_limit("total", &budget.total, total, &mut violations);
+
+    if !violations.is_empty() {
+        // The breakdown is best effort; a module we can't parse still fails the budget
+        let mut largest_functions = function_sizes(&wasm_bytes).unwrap_or_default();
+        largest_functions.truncate(budget.top);
+        return Err(CompileError::BudgetExceeded { violations, largest_functions });
+    }
+
+    Ok(BudgetReport { wasm, js, total })
+}
+
+// Parses "204800", "200KiB", "1.5 MB", ...
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("Invalid size: {}", s))?;
+
+    let multiplier = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1.0,
+        "kb" => 1000.0,
+        "k" | "kib" => 1024.0,
+        "mb" => 1000.0 * 1000.0,
+        "m" | "mib" => 1024.0 * 1024.0,
+        _ => return Err(format!("Unknown size unit in {} (use B, KB, KiB, MB or MiB)", s)),
+    };
+
+    Ok((number * multiplier).round() as u64)
+}
+
+fn deserialize_size<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(u64),
+        Text(String),
+    }
+
+    match Option::<Size>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Size::Bytes(bytes)) => Ok(Some(bytes)),
+        Some(Size::Text(text)) => parse_size(&text).map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::optimize::{optimize, OptimizeOptions};
+    use crate::compiler::wasm_module::sections;
+    use crate::compiler::wasm_module::tests::sample_module;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("2048").unwrap(), 2048);
+        assert_eq!(parse_size("150 KiB").unwrap(), 150 * 1024);
+        assert_eq!(parse_size("1.5MB").unwrap(), 1_500_000);
+        assert!(parse_size("ten").is_err());
+        assert!(parse_size("10 GB").is_err());
+    }
+
+    #[test]
+    fn test_budget_from_toml() {
+        let budget: SizeBudget = toml::from_str(
+            r#"
+            top = 5
+            [wasm]
+            gzip = "150 KiB"
+            [total]
+            raw = 1048576
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(budget.top, 5);
+        assert_eq!(budget.wasm.gzip, Some(150 * 1024));
+        assert_eq!(budget.total.raw, Some(1024 * 1024));
+        assert!(budget.js.is_empty());
+    }
+
+    #[test]
+    fn test_budget_exceeded_lists_functions() {
+        let dir = std::env::temp_dir().join("rig-wasm-compiler-budget-test");
+        fs::create_dir_all(&dir).unwrap();
+        let wasm_file = dir.join("rig_wasm_bg.wasm");
+        fs::write(&wasm_file, sample_module()).unwrap();
+
+        let mut budget = SizeBudget::default();
+        budget.wasm.raw = Some(10);
+        budget.top = 1;
+
+        match check_budget(&budget, &wasm_file, &[]) {
+            Err(CompileError::BudgetExceeded { violations, largest_functions }) => {
+                assert_eq!(violations[0].group, "wasm");
+                assert_eq!(violations[0].encoding, "raw");
+                assert_eq!(largest_functions, vec![("large".to_string(), 6)]);
+            }
+            other => panic!("expected BudgetExceeded, got {:?}", other),
+        }
+
+        budget.wasm.raw = Some(1024);
+        assert!(check_budget(&budget, &wasm_file, &[]).is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_budget_exceeded_after_optimization_keeps_names() {
+        let dir = std::env::temp_dir().join("rig-wasm-compiler-budget-optimized-test");
+        fs::create_dir_all(&dir).unwrap();
+        let wasm_file = dir.join("rig_wasm_bg.wasm");
+
+        // Export `large` so wasm-opt keeps it, inserting the export section before the code section
+        let mut module = sample_module();
+        let code_start = sections(&module).unwrap()[3].range.start;
+        let rest = module.split_off(code_start);
+        module.extend_from_slice(&[7, 9, 1, 5]);
+        module.extend_from_slice(b"large");
+        module.extend_from_slice(&[0, 2]);
+        module.extend(rest);
+        fs::write(&wasm_file, module).unwrap();
+
+        // Runs wasm-opt when it is installed, and only the strip stage otherwise
+        optimize(&wasm_file, &OptimizeOptions::default(), &mut |_| {}).unwrap();
+
+        let mut budget = SizeBudget::default();
+        budget.wasm.raw = Some(10);
+        match check_budget(&budget, &wasm_file, &[]) {
+            Err(CompileError::BudgetExceeded { largest_functions, .. }) => {
+                assert!(largest_functions.iter().any(|(name, _)| name == "large"), "{:?}", largest_functions);
+                assert!(largest_functions.iter().all(|(name, _)| !name.starts_with("func[")));
+            }
+            other => panic!("expected BudgetExceeded, got {:?}", other),
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,562 @@
+// Content-addressed build cache. A build is keyed by everything that goes into
+// it: the sources (including path dependencies), Cargo.lock, the toolchain
+// versions and the compile options. A hit copies the stored output files back
+// instead of running cargo, wasm-pack and wasm-opt.
+use crate::compiler::error::CompileError;
+use crate::compiler::input::ResolvedInput;
+use crate::compiler::integrity;
+use crate::compiler::optimize::{OptLevel, OptimizeReport};
+use crate::compiler::report::format_size;
+use crate::compiler::signing::SIGNATURE_FILE;
+use crate::compiler::target::Target;
+use crate::compiler::toolchain;
+use crate::compiler::CompileOptions;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Overrides the cache location
+pub const CACHE_DIR_ENV: &str = "RIG_WASM_CACHE_DIR";
+
+const ENTRY_FILE: &str = "entry.json";
+const FILES_DIR: &str = "files";
+
+// Directories that never hold build inputs
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules"];
+
+// Environment variables that change what rustc is given
+const RUSTFLAGS_ENV: &[&str] = &["CARGO_ENCODED_RUSTFLAGS", "RUSTFLAGS", "CARGO_BUILD_RUSTFLAGS"];
+
+// One cached build
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub key: String,
+    pub package_name: String,
+    pub target: Target,
+    pub out_name: String,
+    // Seconds since the Unix epoch
+    pub created: u64,
+    // Output files, relative to the output directory
+    pub files: Vec<String>,
+    pub size: u64,
+    // The optimization stage's report, with `file` relative to the output directory
+    pub optimization: Option<OptimizeReport>,
+}
+
+impl CacheEntry {
+    pub fn short_key(&self) -> &str {
+        &self.key[..12.min(self.key.len())]
+    }
+}
+
+// `cache ls`
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheListing {
+    pub dir: PathBuf,
+    pub entries: Vec<CacheEntry>,
+}
+
+impl CacheListing {
+    pub fn total_size(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.size).sum()
+    }
+}
+
+// `cache clean`
+#[derive(Debug, Clone, Serialize)]
+pub struct CleanReport {
+    pub dir: PathBuf,
+    pub removed: usize,
+    pub freed: u64,
+}
+
+// $RIG_WASM_CACHE_DIR, or rig-wasm-compiler under the user's cache directory
+pub fn cache_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os(CACHE_DIR_ENV) {
+        return PathBuf::from(dir);
+    }
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(std::env::temp_dir);
+    base.join("rig-wasm-compiler")
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Output directories wasm-pack created mark themselves with a `*` .gitignore
+fn is_output_dir(dir: &Path) -> bool {
+    fs::read_to_string(dir.join(".gitignore")).is_ok_and(|ignore| ignore.trim() == "*")
+}
+
+// Hashes every file under `dir` by path relative to `root` and contents, in a
+// stable order. Build output, dot-directories other than .cargo and `skip`
+// (the output directory) are left out.
+fn hash_tree(hasher: &mut Sha256, root: &Path, dir: &Path, skip: &Path) -> Result<(), CompileError> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for path in entries {
+        let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        if (name.starts_with('.') && name != ".cargo") || path == skip {
+            continue;
+        }
+        if path.is_dir() {
+            if SKIPPED_DIRS.contains(&name.as_str()) || is_output_dir(&path) {
+                continue;
+            }
+            hash_tree(hasher, root, &path, skip)?;
+        } else if path.is_file() {
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            hasher.update(relative.to_string_lossy().as_bytes());
+            hasher.update([0]);
+            hasher.update(fs::read(&path)?);
+            hasher.update([0]);
+        }
+    }
+    Ok(())
+}
+
+// Directories of the `path = ...` dependencies in a Cargo.toml, including
+// target-specific and build dependencies
+fn path_dependencies(manifest_path: &Path) -> Vec<PathBuf> {
+    let manifest = fs::read_to_string(manifest_path).ok().and_then(|s| toml::from_str::<toml::Value>(&s).ok());
+    let manifest = match manifest {
+        Some(manifest) => manifest,
+        None => return Vec::new(),
+    };
+    let base = manifest_path.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut tables = vec![&manifest];
+    if let Some(targets) = manifest.get("target").and_then(|t| t.as_table()) {
+        tables.extend(targets.values());
+    }
+    let mut dirs = Vec::new();
+    for table in tables {
+        for section in ["dependencies", "dev-dependencies", "build-dependencies"] {
+            let deps = match table.get(section).and_then(|d| d.as_table()) {
+                Some(deps) => deps,
+                None => continue,
+            };
+            for dep in deps.values() {
+                if let Some(path) = dep.get("path").and_then(|p| p.as_str()) {
+                    dirs.push(base.join(path));
+                }
+            }
+        }
+    }
+    dirs
+}
+
+// The cache key for building `resolved` with `options`: a SHA-256 over the
+// sources, the lockfile, the toolchain and the options
+pub fn cache_key(
+    options: &CompileOptions,
+    resolved: &ResolvedInput,
+    output_dir: &Path,
+) -> Result<String, CompileError> {
+    let mut hasher = Sha256::new();
+    hasher.update(concat!("rig-wasm-compiler ", env!("CARGO_PKG_VERSION"), "\n"));
+
+    // The output directory does not change what is built, and the input is
+    // covered by the sources below
+    let mut normalized = options.clone();
+    normalized.input = String::new();
+    normalized.output_dir = PathBuf::new();
+    normalized.cache = true;
+    // Signing happens after the cache and does not change the stored files
+    normalized.sign_key = None;
+    hasher.update(serde_json::to_vec(&normalized).map_err(|e| CompileError::PostProcess(e.to_string()))?);
+    hasher.update(resolved.package_name.as_bytes());
+
+    let optimize = options.post_process && options.optimize.level != OptLevel::O0;
+    hasher.update(toolchain::fingerprint(options.target, optimize));
+
+    if let Some(config) = &options.config {
+        hasher.update(fs::read(config)?);
+    }
+
+    // The crate, a wrapped .rs file and every path dependency reachable from it
+    let mut seen = BTreeSet::new();
+    let mut pending = vec![resolved.crate_dir.clone()];
+    while let Some(dir) = pending.pop() {
+        let dir = fs::canonicalize(&dir)?;
+        if !seen.insert(dir.clone()) {
+            continue;
+        }
+        hash_tree(&mut hasher, &dir, &dir, output_dir)?;
+        pending.extend(path_dependencies(&dir.join("Cargo.toml")));
+    }
+    if let Some(source_file) = &resolved.source_file {
+        hasher.update(fs::read(source_file)?);
+    }
+
+    // rustflags and other settings from the cargo config files and the environment
+    hash_rustflags(&mut hasher, &resolved.crate_dir, options.target)?;
+
+    // Cargo.lock and, for workspace members, the root manifest with shared
+    // profiles and dependency versions
+    if let Some(lock_file) = toolchain::find_lock_file(&resolved.crate_dir) {
+        hasher.update(fs::read(&lock_file)?);
+        let root_manifest = lock_file.with_file_name("Cargo.toml");
+        if root_manifest.is_file() {
+            hasher.update(fs::read(root_manifest)?);
+        }
+    }
+
+    Ok(hex(&hasher.finalize()))
+}
+
+// The cargo config files cargo reads when building in `crate_dir`, and the
+// rustflags variables, including the `target.<triple>` one
+fn hash_rustflags(hasher: &mut Sha256, crate_dir: &Path, target: Target) -> Result<(), CompileError> {
+    for file in integrity::cargo_config_files(crate_dir) {
+        hasher.update(file.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(fs::read(&file)?);
+        hasher.update([0]);
+    }
+
+    let target_var = format!(
+        "CARGO_TARGET_{}_RUSTFLAGS",
+        target.rust_target().to_ascii_uppercase().replace('-', "_")
+    );
+    for name in RUSTFLAGS_ENV.iter().copied().chain([target_var.as_str()]) {
+        hasher.update(name.as_bytes());
+        match std::env::var_os(name) {
+            Some(value) => {
+                hasher.update([1]);
+                hasher.update(value.to_string_lossy().as_bytes());
+            }
+            None => hasher.update([0]),
+        }
+        hasher.update([0]);
+    }
+    Ok(())
+}
+
+// Files under `base`/`dir`, relative to `base`, with `/` separators
+fn files_under(base: &Path, dir: &Path) -> Result<Vec<String>, CompileError> {
+    let mut files = Vec::new();
+    if !base.join(dir).is_dir() {
+        return Ok(files);
+    }
+    for entry in fs::read_dir(base.join(dir))? {
+        let relative = dir.join(entry?.file_name());
+        if base.join(&relative).is_dir() {
+            files.extend(files_under(base, &relative)?);
+        } else {
+            files.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn copy_file(from: &Path, to: &Path) -> Result<u64, CompileError> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(fs::copy(from, to)?)
+}
+
+fn read_entry(entry_dir: &Path) -> Option<CacheEntry> {
+    let json = fs::read(entry_dir.join(ENTRY_FILE)).ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+// Copies the files stored under `key` into `output_dir`. Returns None on a miss.
+pub fn restore(cache_dir: &Path, key: &str, output_dir: &Path) -> Result<Option<CacheEntry>, CompileError> {
+    let entry_dir = cache_dir.join(key);
+    let mut entry = match read_entry(&entry_dir) {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+    // A partially deleted entry is a miss, not an error
+    let files_dir = entry_dir.join(FILES_DIR);
+    if !entry.files.iter().all(|file| files_dir.join(file).is_file()) {
+        return Ok(None);
+    }
+
+    fs::create_dir_all(output_dir)?;
+    for file in &entry.files {
+        copy_file(&files_dir.join(file), &output_dir.join(file))?;
+    }
+    if let Some(optimization) = &mut entry.optimization {
+        optimization.file = output_dir.join(&optimization.file);
+    }
+    Ok(Some(entry))
+}
+
+// The base names of other entries' modules in `output_dir`: `<name>_bg.wasm`
+// from wasm-bindgen, or `<name>.wasm` for WASI
+fn other_entries(output_dir: &Path, out_name: &str) -> Result<Vec<String>, CompileError> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(output_dir)? {
+        let file_name = entry?.file_name().to_string_lossy().into_owned();
+        let name = match file_name.strip_suffix("_bg.wasm").or_else(|| file_name.strip_suffix(".wasm")) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        if name != out_name && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+// Stores the output directory of a finished build under `key`: the glue and
+// module, and package.json, README.md, .gitignore and everything else wasm-pack
+// and the later stages wrote. Files of other entries sharing the directory and
+// the signature, which depends on the key used, are left out. The entry is
+// written to a temporary directory first so concurrent builds never see half an entry.
+pub fn store(
+    cache_dir: &Path,
+    key: &str,
+    options: &CompileOptions,
+    package_name: &str,
+    output_dir: &Path,
+    optimization: Option<&OptimizeReport>,
+) -> Result<CacheEntry, CompileError> {
+    let others = other_entries(output_dir, &options.out_name)?;
+    let is_other_entry = |file: &str| {
+        others.iter().any(|name| {
+            let (dir, base) = file.rsplit_once('/').unwrap_or(("", file));
+            base.starts_with(&format!("{}.", name))
+                || base.starts_with(&format!("{}_bg.", name))
+                || dir == format!("shimmed-src/{}", name)
+                || dir.starts_with(&format!("shimmed-src/{}/", name))
+        })
+    };
+    let mut files = files_under(output_dir, Path::new(""))?;
+    files.retain(|file| file != SIGNATURE_FILE && !is_other_entry(file));
+
+    let staging = cache_dir.join(format!("{}.tmp-{}", key, std::process::id()));
+    let files_dir = staging.join(FILES_DIR);
+    fs::create_dir_all(&files_dir)?;
+    let mut size = 0;
+    for file in &files {
+        size += copy_file(&output_dir.join(file), &files_dir.join(file))?;
+    }
+
+    let entry = CacheEntry {
+        key: key.to_string(),
+        package_name: package_name.to_string(),
+        target: options.target,
+        out_name: options.out_name.clone(),
+        created: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+        files,
+        size,
+        optimization: optimization.cloned().map(|mut optimization| {
+            optimization.file = optimization.file.strip_prefix(output_dir).unwrap_or(&optimization.file).to_path_buf();
+            optimization
+        }),
+    };
+    let json = serde_json::to_vec_pretty(&entry).map_err(|e| CompileError::PostProcess(e.to_string()))?;
+    fs::write(staging.join(ENTRY_FILE), json)?;
+
+    let entry_dir = cache_dir.join(key);
+    if entry_dir.exists() {
+        fs::remove_dir_all(&entry_dir)?;
+    }
+    fs::rename(&staging, &entry_dir)?;
+    Ok(entry)
+}
+
+// Every readable entry, newest first
+pub fn list(cache_dir: &Path) -> Result<CacheListing, CompileError> {
+    let mut entries = Vec::new();
+    if cache_dir.is_dir() {
+        for dir in fs::read_dir(cache_dir)? {
+            if let Some(entry) = read_entry(&dir?.path()) {
+                entries.push(entry);
+            }
+        }
+    }
+    entries.sort_by(|a, b| b.created.cmp(&a.created).then_with(|| a.key.cmp(&b.key)));
+    Ok(CacheListing { dir: cache_dir.to_path_buf(), entries })
+}
+
+// Removes every entry, including ones left behind by interrupted builds
+pub fn clean(cache_dir: &Path) -> Result<CleanReport, CompileError> {
+    let listing = list(cache_dir)?;
+    let freed = listing.total_size();
+    let mut removed = 0;
+    if cache_dir.is_dir() {
+        for dir in fs::read_dir(cache_dir)? {
+            let path = dir?.path();
+            if path.is_dir() {
+                fs::remove_dir_all(&path)?;
+                removed += 1;
+            }
+        }
+    }
+    Ok(CleanReport { dir: cache_dir.to_path_buf(), removed, freed })
+}
+
+fn age(created: u64) -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let seconds = now.saturating_sub(created);
+    match seconds {
+        0..=59 => format!("{}s ago", seconds),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        3600..=86399 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+impl fmt::Display for CacheListing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Cache: {}", self.dir.display())?;
+        for entry in &self.entries {
+            writeln!(
+                f,
+                "  {}  {:<24} {:<9} {:>12}  {}",
+                entry.short_key(),
+                format!("{} ({})", entry.package_name, entry.out_name),
+                entry.target,
+                format_size(entry.size),
+                age(entry.created)
+            )?;
+        }
+        write!(f, "{} entries, {}", self.entries.len(), format_size(self.total_size()))
+    }
+}
+
+impl fmt::Display for CleanReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Removed {} cache entries ({}) from {}",
+            self.removed,
+            format_size(self.freed),
+            self.dir.display()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rig-wasm-compiler-cache-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_hash_tree_skips_output() {
+        let dir = temp_dir("tree");
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/lib.rs"), "pub fn a() {}").unwrap();
+        let hash = |dir: &Path| {
+            let mut hasher = Sha256::new();
+            hash_tree(&mut hasher, dir, dir, &dir.join("dist")).unwrap();
+            hex(&hasher.finalize())
+        };
+        let before = hash(&dir);
+
+        // Build output, wasm-pack output directories and target/ do not change the key
+        fs::create_dir_all(dir.join("dist")).unwrap();
+        fs::write(dir.join("dist/rig_wasm_bg.wasm"), "wasm").unwrap();
+        fs::create_dir_all(dir.join("pkg")).unwrap();
+        fs::write(dir.join("pkg/.gitignore"), "*").unwrap();
+        fs::write(dir.join("pkg/rig_wasm.js"), "js").unwrap();
+        fs::create_dir_all(dir.join("target")).unwrap();
+        fs::write(dir.join("target/out"), "out").unwrap();
+        assert_eq!(hash(&dir), before);
+
+        fs::write(dir.join("src/lib.rs"), "pub fn b() {}").unwrap();
+        assert_ne!(hash(&dir), before);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_store_restore_list_clean() {
+        let cache = temp_dir("store");
+        let output = temp_dir("store-output");
+        let options = CompileOptions::new(".", &output).with_target(Target::Nodejs);
+        fs::write(output.join("rig_wasm.js"), "glue").unwrap();
+        fs::write(output.join("rig_wasm_bg.wasm"), "wasm").unwrap();
+
+        let stored = store(&cache, "abc123", &options, "rig-wasm", &output, None).unwrap();
+        assert_eq!(stored.files, vec!["rig_wasm.js", "rig_wasm_bg.wasm"]);
+
+        let restored_dir = temp_dir("store-restored");
+        let restored = restore(&cache, "abc123", &restored_dir).unwrap().unwrap();
+        assert_eq!(restored.size, 8);
+        assert_eq!(fs::read_to_string(restored_dir.join("rig_wasm.js")).unwrap(), "glue");
+        assert!(restore(&cache, "missing", &restored_dir).unwrap().is_none());
+
+        let listing = list(&cache).unwrap();
+        assert_eq!(listing.entries.len(), 1);
+        assert!(listing.to_string().contains("abc123"));
+
+        let cleaned = clean(&cache).unwrap();
+        assert_eq!((cleaned.removed, cleaned.freed), (1, 8));
+        assert!(list(&cache).unwrap().entries.is_empty());
+
+        for dir in [cache, output, restored_dir] {
+            fs::remove_dir_all(dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_store_whole_output_dir() {
+        let cache = temp_dir("whole");
+        let output = temp_dir("whole-output");
+        let options = CompileOptions::new(".", &output).with_out_name("agent");
+        for file in ["agent.js", "agent_bg.wasm", "package.json", "README.md", ".gitignore", "manifest.json.sig"] {
+            fs::write(output.join(file), file).unwrap();
+        }
+        // Another entry built into the same directory
+        for file in ["rag.js", "rag_bg.wasm", "rag.d.ts"] {
+            fs::write(output.join(file), file).unwrap();
+        }
+
+        let stored = store(&cache, "whole", &options, "agent", &output, None).unwrap();
+        assert_eq!(stored.files, vec![".gitignore", "README.md", "agent.js", "agent_bg.wasm", "package.json"]);
+
+        fs::remove_dir_all(&cache).unwrap();
+        fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_rustflags_change_the_key() {
+        let dir = temp_dir("rustflags");
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("Cargo.toml"), "[package]\nname = \"agent\"\nversion = \"0.1.0\"\n").unwrap();
+        fs::write(dir.join("src/lib.rs"), "pub fn a() {}").unwrap();
+        let resolved = ResolvedInput {
+            crate_dir: dir.clone(),
+            manifest_path: dir.join("Cargo.toml"),
+            package_name: "agent".to_string(),
+            generated: false,
+            source_file: None,
+        };
+        let options = CompileOptions::new(".", dir.join("pkg"));
+        let key = || cache_key(&options, &resolved, &dir.join("pkg")).unwrap();
+
+        fs::create_dir_all(dir.join(".cargo")).unwrap();
+        fs::write(dir.join(".cargo/config.toml"), "[build]\nrustflags = [\"-Ctarget-feature=+simd128\"]\n").unwrap();
+        let before = key();
+        assert_eq!(key(), before);
+
+        // Nothing but the flags differ, yet the build must not be reused
+        fs::write(dir.join(".cargo/config.toml"), "[build]\nrustflags = [\"-Ctarget-feature=+bulk-memory\"]\n").unwrap();
+        assert_ne!(key(), before);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,208 @@
+// Content-Security-Policy and hosting headers for deployed browser builds. The
+// policy allows compiling wasm ('wasm-unsafe-eval') and connecting to exactly
+// the provider origins the embedded config points at.
+use crate::compiler::error::CompileError;
+use crate::compiler::secrets::EmbeddedConfig;
+use crate::compiler::target::Target;
+use crate::config::{default_api_base_urls, WasmConfig};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Netlify and Cloudflare Pages read this from the site root
+pub const HEADERS_FILE: &str = "_headers";
+// To `include` in the nginx server block serving the output
+pub const NGINX_FILE: &str = "nginx-headers.conf";
+
+// The headers written for a build
+#[derive(Debug, Clone, Serialize)]
+pub struct HeadersReport {
+    pub csp: String,
+    // Origins in connect-src besides 'self', with the providers that use them
+    pub origins: Vec<ProviderOrigin>,
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct ProviderOrigin {
+    pub origin: String,
+    pub provider: String,
+}
+
+impl fmt::Display for HeadersReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Content-Security-Policy: {}", self.csp)?;
+        for origin in &self.origins {
+            writeln!(f, "  connect-src {} ({})", origin.origin, origin.provider)?;
+        }
+        let files: Vec<String> = self.files.iter().map(|file| file.display().to_string()).collect();
+        writeln!(f, "  headers written to {}", files.join(", "))?;
+        // script-src 'self' rejects the inline scripts `serve` injects
+        write!(f, "  for the deployed site; do not apply them to the dev server")
+    }
+}
+
+// The scheme, host and port of `url`, as CSP source expressions spell them
+pub fn origin(url: &str) -> Result<String, String> {
+    let (scheme, rest) = url.trim().split_once("://").ok_or_else(|| format!("{} is not an absolute URL", url))?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    // Credentials never belong in a policy
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    if host.is_empty() || !scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c)) {
+        return Err(format!("{} is not an absolute URL", url));
+    }
+    Ok(format!("{}://{}", scheme.to_ascii_lowercase(), host.to_ascii_lowercase()))
+}
+
+// The origins the providers in `config` connect to, one entry per origin and provider
+pub fn provider_origins(config: &WasmConfig) -> Result<Vec<ProviderOrigin>, CompileError> {
+    let mut origins = BTreeSet::new();
+    for (name, provider) in &config.providers {
+        let url = provider.base_url(name).ok_or_else(|| {
+            CompileError::InvalidInput(format!(
+                "providers.{} has no api_base_url and is not a known provider, so its origin cannot be allowed",
+                name
+            ))
+        })?;
+        let origin = origin(url)
+            .map_err(|e| CompileError::InvalidInput(format!("providers.{}.api_base_url: {}", name, e)))?;
+        origins.insert(ProviderOrigin { origin, provider: name.clone() });
+    }
+    Ok(origins.into_iter().collect())
+}
+
+// Every known provider's default origin, for builds without a config to say
+// which providers the page talks to
+pub fn default_origins() -> Vec<ProviderOrigin> {
+    let origins: BTreeSet<ProviderOrigin> = default_api_base_urls()
+        .iter()
+        .filter_map(|(provider, url)| {
+            let origin = origin(url).ok()?;
+            Some(ProviderOrigin { origin, provider: provider.to_string() })
+        })
+        .collect();
+    origins.into_iter().collect()
+}
+
+pub fn content_security_policy(origins: &[ProviderOrigin]) -> String {
+    let mut connect = vec!["'self'".to_string()];
+    for origin in origins {
+        if !connect.contains(&origin.origin) {
+            connect.push(origin.origin.clone());
+        }
+    }
+    [
+        "default-src 'self'".to_string(),
+        "script-src 'self' 'wasm-unsafe-eval'".to_string(),
+        format!("connect-src {}", connect.join(" ")),
+        "object-src 'none'".to_string(),
+        "base-uri 'self'".to_string(),
+        "frame-ancestors 'none'".to_string(),
+    ]
+    .join("; ")
+}
+
+pub fn headers_file(csp: &str) -> String {
+    format!(
+        "/*\n  Content-Security-Policy: {csp}\n  X-Content-Type-Options: nosniff\n\n/*.wasm\n  Content-Type: application/wasm\n",
+        csp = csp
+    )
+}
+
+pub fn nginx_snippet(csp: &str) -> String {
+    // add_header in a location drops the server-level ones, so the .wasm
+    // location repeats them
+    format!(
+        "add_header Content-Security-Policy \"{csp}\" always;\n\
+         add_header X-Content-Type-Options nosniff always;\n\
+         \n\
+         location ~* \\.wasm$ {{\n\
+         \x20   types {{ }}\n\
+         \x20   default_type application/wasm;\n\
+         \x20   add_header Content-Security-Policy \"{csp}\" always;\n\
+         \x20   add_header X-Content-Type-Options nosniff always;\n\
+         }}\n",
+        csp = csp
+    )
+}
+
+// Headers only mean something for output a browser loads from a web server
+pub fn check_target(target: Target) -> Result<(), CompileError> {
+    if matches!(target, Target::Nodejs | Target::Deno) || target.is_wasi() {
+        return Err(CompileError::InvalidInput(format!(
+            "--emit-headers is for pages served to a browser, not {} output",
+            target
+        )));
+    }
+    Ok(())
+}
+
+// Writes _headers and the nginx snippet for a browser build. Without an
+// embedded config every known provider's default origin is allowed.
+pub fn write_headers(output_dir: &Path, config: Option<&EmbeddedConfig>) -> Result<HeadersReport, CompileError> {
+    let origins = match config {
+        Some(config) => {
+            let config: WasmConfig = serde_json::from_str(&config.json)
+                .map_err(|e| CompileError::InvalidInput(format!("Invalid config {}: {}", config.path.display(), e)))?;
+            provider_origins(&config)?
+        }
+        None => default_origins(),
+    };
+    let csp = content_security_policy(&origins);
+
+    let files = vec![output_dir.join(HEADERS_FILE), output_dir.join(NGINX_FILE)];
+    fs::write(&files[0], headers_file(&csp))?;
+    fs::write(&files[1], nginx_snippet(&csp))?;
+    Ok(HeadersReport { csp, origins, files })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin() {
+        assert_eq!(origin("https://api.openai.com/v1").unwrap(), "https://api.openai.com");
+        assert_eq!(origin("HTTP://LocalHost:11434").unwrap(), "http://localhost:11434");
+        assert_eq!(origin("https://user:pw@proxy.example:8443/path?q=1").unwrap(), "https://proxy.example:8443");
+        assert!(origin("api.openai.com").is_err());
+    }
+
+    #[test]
+    fn test_content_security_policy() {
+        let config: WasmConfig = serde_json::from_value(serde_json::json!({
+            "providers": {
+                "openai": { "api_key": "", "model": "gpt-4o" },
+                "local": { "api_key": "", "model": "llama3", "api_base_url": "http://localhost:11434/api" },
+                "proxy": { "api_key": "", "model": "gpt-4o", "api_base_url": "https://api.openai.com/v1" }
+            }
+        }))
+        .unwrap();
+        let origins = provider_origins(&config).unwrap();
+        assert_eq!(
+            content_security_policy(&origins),
+            "default-src 'self'; script-src 'self' 'wasm-unsafe-eval'; \
+             connect-src 'self' http://localhost:11434 https://api.openai.com; \
+             object-src 'none'; base-uri 'self'; frame-ancestors 'none'"
+        );
+
+        let unknown: WasmConfig = serde_json::from_value(serde_json::json!({
+            "providers": { "custom": { "api_key": "", "model": "m" } }
+        }))
+        .unwrap();
+        assert!(provider_origins(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_default_origins() {
+        let origins = default_origins();
+        assert!(origins.contains(&ProviderOrigin {
+            origin: "https://api.openai.com".to_string(),
+            provider: "openai".to_string(),
+        }));
+        assert!(content_security_policy(&origins).contains(" https://openrouter.ai "));
+        assert_eq!(origins.len(), default_api_base_urls().len());
+    }
+}
@@ -0,0 +1,268 @@
+use crate::compiler::error::CompileError;
+use crate::compiler::report::CompileReport;
+use crate::compiler::Profile;
+use clap::ArgEnum;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+pub const WASM_TARGET: &str = "wasm32-unknown-unknown";
+
+// How compiler messages are reported on the command line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ArgEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MessageFormat {
+    // Rendered rustc output on stderr
+    #[default]
+    Human,
+    // One JSON record per line on stdout
+    Json,
+}
+
+// A rustc diagnostic as reported by `cargo build --message-format json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub level: String,
+    pub message: String,
+    #[serde(default, deserialize_with = "deserialize_code")]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub spans: Vec<DiagnosticSpan>,
+    // Notes and help attached to the diagnostic
+    #[serde(default)]
+    pub children: Vec<Diagnostic>,
+    #[serde(default)]
+    pub rendered: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticSpan {
+    pub file_name: String,
+    pub line_start: usize,
+    pub line_end: usize,
+    pub column_start: usize,
+    pub column_end: usize,
+    pub is_primary: bool,
+}
+
+impl Diagnostic {
+    pub fn is_error(&self) -> bool {
+        self.level == "error" || self.level == "error: internal compiler error"
+    }
+}
+
+// rustc reports codes as `{"code": "E0425", "explanation": "..."}`
+fn deserialize_code<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Code {
+        code: String,
+    }
+    Ok(Option::<Code>::deserialize(deserializer)?.map(|c| c.code))
+}
+
+#[derive(Deserialize)]
+struct CargoMessage {
+    reason: String,
+    message: Option<Diagnostic>,
+    // Set on `compiler-artifact` messages
+    target: Option<ArtifactTarget>,
+    #[serde(default)]
+    filenames: Vec<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct ArtifactTarget {
+    kind: Vec<String>,
+}
+
+// Parses one line of cargo's JSON output, returning the .wasm files of a
+// cdylib artifact it reports, if any
+pub fn parse_cargo_artifact(line: &str) -> Vec<PathBuf> {
+    let message: CargoMessage = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(_) => return Vec::new(),
+    };
+    let cdylib = message.target.is_some_and(|target| target.kind.iter().any(|kind| kind == "cdylib"));
+    if message.reason != "compiler-artifact" || !cdylib {
+        return Vec::new();
+    }
+    message
+        .filenames
+        .into_iter()
+        .filter(|file| file.extension().is_some_and(|ext| ext == "wasm"))
+        .collect()
+}
+
+// Parses one line of cargo's JSON output, returning the diagnostic it carries, if any
+pub fn parse_cargo_message(line: &str) -> Option<Diagnostic> {
+    let message: CargoMessage = serde_json::from_str(line).ok()?;
+    if message.reason == "compiler-message" {
+        message.message
+    } else {
+        None
+    }
+}
+
+// Reports a diagnostic in the requested format: rendered text on stderr, or a
+// `compiler-message` record on stdout
+pub fn emit_diagnostic(format: MessageFormat, diagnostic: &Diagnostic) {
+    write_diagnostic(&mut io::stdout(), format, diagnostic);
+}
+
+// Reports a diagnostic with its JSON record on stderr too
+pub fn emit_diagnostic_stderr(format: MessageFormat, diagnostic: &Diagnostic) {
+    write_diagnostic(&mut io::stderr(), format, diagnostic);
+}
+
+fn write_diagnostic(out: &mut dyn Write, format: MessageFormat, diagnostic: &Diagnostic) {
+    let _ = match format {
+        MessageFormat::Human => match &diagnostic.rendered {
+            Some(rendered) => write!(io::stderr(), "{}", rendered),
+            None => writeln!(io::stderr(), "{}: {}", diagnostic.level, diagnostic.message),
+        },
+        MessageFormat::Json => {
+            let record = serde_json::json!({
+                "reason": "compiler-message",
+                "diagnostic": diagnostic,
+            });
+            writeln!(out, "{}", record)
+        }
+    };
+}
+
+// Reports the final outcome of a compilation
+pub fn emit_result(format: MessageFormat, result: &Result<CompileReport, CompileError>) {
+    write_result(&mut io::stdout(), format, result);
+}
+
+// Reports the outcome on stderr, for commands such as `run` whose stdout
+// carries something other than the build
+pub fn emit_result_stderr(format: MessageFormat, result: &Result<CompileReport, CompileError>) {
+    write_result(&mut io::stderr(), format, result);
+}
+
+fn write_result(out: &mut dyn Write, format: MessageFormat, result: &Result<CompileReport, CompileError>) {
+    // Nothing sensible is left to do when the console is gone
+    let _ = match (format, result) {
+        (MessageFormat::Human, Ok(report)) => writeln!(out, "{}\nSuccessfully compiled to WASM", report),
+        (MessageFormat::Human, Err(e)) => writeln!(io::stderr(), "Compilation failed: {}", e),
+        (MessageFormat::Json, Ok(report)) => {
+            let record = serde_json::json!({
+                "reason": "compile-finished",
+                "success": true,
+                "report": report,
+            });
+            writeln!(out, "{}", record)
+        }
+        (MessageFormat::Json, Err(e)) => {
+            let record = serde_json::json!({
+                "reason": "compile-error",
+                "kind": e.kind(),
+                "message": e.to_string(),
+                "exit_code": e.exit_code(),
+            });
+            writeln!(out, "{}", record)
+        }
+    };
+}
+
+// Runs the same `cargo build` wasm-pack would, so rustc diagnostics can be
+// streamed to `on_diagnostic` as they arrive. `env` is passed to the build,
+// e.g. the embedded config. Returns the .wasm files cargo wrote for the crate.
+pub fn cargo_build(
+    manifest_path: &Path,
+    target: &str,
+    profile: Profile,
+    features: &[String],
+    no_default_features: bool,
+    env: &[(&str, String)],
+    args: &[String],
+    on_diagnostic: &mut dyn FnMut(&Diagnostic),
+) -> Result<Vec<PathBuf>, CompileError> {
+    let mut command = Command::new("cargo");
+    // Run where wasm-pack runs cargo, so both read the same .cargo/config.toml
+    if let Some(crate_dir) = manifest_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        command.current_dir(crate_dir);
+    }
+    command
+        .envs(env.iter().cloned())
+        .arg("build")
+        .arg("--lib")
+        .arg("--target").arg(target)
+        .arg("--manifest-path").arg(manifest_path)
+        .arg("--message-format").arg("json")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit());
+    // wasm-pack's profiling profile is cargo's release profile with debug info
+    if profile != Profile::Dev {
+        command.arg("--release");
+    }
+    if !features.is_empty() {
+        command.arg("--features").arg(features.join(","));
+    }
+    if no_default_features {
+        command.arg("--no-default-features");
+    }
+    command.args(args);
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| CompileError::spawn("cargo", "https://rustup.rs", e))?;
+
+    let mut diagnostics = Vec::new();
+    let mut artifacts = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines() {
+            let line = line?;
+            if let Some(diagnostic) = parse_cargo_message(&line) {
+                on_diagnostic(&diagnostic);
+                diagnostics.push(diagnostic);
+            }
+            artifacts.extend(parse_cargo_artifact(&line));
+        }
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(CompileError::Cargo {
+            message: format!("cargo build failed for {}", manifest_path.display()),
+            diagnostics: diagnostics.into_iter().filter(Diagnostic::is_error).collect(),
+        });
+    }
+
+    Ok(artifacts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cargo_artifact() {
+        let line = r#"{"reason":"compiler-artifact","package_id":"agent 0.1.0","target":{"kind":["cdylib","rlib"],"name":"agent"},"filenames":["/t/wasm32-wasip2/release/agent.wasm","/t/wasm32-wasip2/release/libagent.rlib"]}"#;
+        assert_eq!(parse_cargo_artifact(line), vec![PathBuf::from("/t/wasm32-wasip2/release/agent.wasm")]);
+
+        let dependency = r#"{"reason":"compiler-artifact","target":{"kind":["lib"],"name":"serde"},"filenames":["/t/libserde.rlib"]}"#;
+        assert!(parse_cargo_artifact(dependency).is_empty());
+    }
+
+    #[test]
+    fn test_parse_compiler_message() {
+        let line = r#"{"reason":"compiler-message","package_id":"demo 0.1.0","message":{"level":"error","message":"cannot find value `x` in this scope","code":{"code":"E0425","explanation":null},"spans":[{"file_name":"src/lib.rs","line_start":3,"line_end":3,"column_start":5,"column_end":6,"is_primary":true}],"rendered":"error[E0425]: cannot find value `x`"}}"#;
+
+        let diagnostic = parse_cargo_message(line).unwrap();
+        assert!(diagnostic.is_error());
+        assert_eq!(diagnostic.code.as_deref(), Some("E0425"));
+        assert_eq!(diagnostic.spans[0].line_start, 3);
+    }
+
+    #[test]
+    fn test_ignores_other_messages() {
+        assert!(parse_cargo_message(r#"{"reason":"build-finished","success":true}"#).is_none());
+        assert!(parse_cargo_message("not json").is_none());
+    }
+}
@@ -0,0 +1,192 @@
+use crate::compiler::budget::BudgetViolation;
+use crate::compiler::diagnostics::Diagnostic;
+use crate::compiler::report::format_size;
+use crate::compiler::secrets::SecretMatch;
+use std::fmt;
+use std::io;
+
+// Everything that can stop a compilation, grouped so that callers (and CI via
+// the exit code) can tell a bad input from a broken toolchain or a rustc error
+#[derive(Debug)]
+pub enum CompileError {
+    // The input path does not exist and is not a workspace member
+    InputNotFound(String),
+    // The input exists but cannot be built as a wasm module
+    InvalidInput(String),
+    // A required tool or rustup target is not installed
+    ToolchainMissing { tool: String, hint: String },
+    // cargo or rustc rejected the crate
+    Cargo { message: String, diagnostics: Vec<Diagnostic> },
+    // wasm-pack (bindgen or packaging) failed
+    WasmPack(String),
+    // A step after wasm-pack failed, e.g. the expected glue files are missing
+    PostProcess(String),
+    // The output is larger than the configured size budget
+    BudgetExceeded {
+        violations: Vec<BudgetViolation>,
+        // Largest functions by body size, read from the name section
+        largest_functions: Vec<(String, u64)>,
+    },
+    // The lint pass found wasm-incompatible APIs or crates and was set to deny them
+    Incompatible { diagnostics: Vec<Diagnostic> },
+    // An API key would end up in the module: a secret in the --config file, or
+    // a key-shaped string in the emitted .wasm/.js
+    SecretFound { secrets: Vec<SecretMatch> },
+    // `run` loaded the module but the export trapped, threw or returned an error
+    RunFailed(String),
+    // `verify` found a bad signature or a file that does not match the signed manifest
+    VerificationFailed(String),
+    Io(io::Error),
+}
+
+impl CompileError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CompileError::Io(_) => 1,
+            CompileError::InputNotFound(_) => 2,
+            CompileError::InvalidInput(_) => 3,
+            CompileError::ToolchainMissing { .. } => 4,
+            CompileError::Cargo { .. } => 5,
+            CompileError::WasmPack(_) => 6,
+            CompileError::PostProcess(_) => 7,
+            CompileError::BudgetExceeded { .. } => 8,
+            CompileError::Incompatible { .. } => 9,
+            CompileError::SecretFound { .. } => 10,
+            CompileError::RunFailed(_) => 11,
+            CompileError::VerificationFailed(_) => 12,
+        }
+    }
+
+    // Stable identifier used in JSON output
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CompileError::Io(_) => "io",
+            CompileError::InputNotFound(_) => "input-not-found",
+            CompileError::InvalidInput(_) => "invalid-input",
+            CompileError::ToolchainMissing { .. } => "toolchain-missing",
+            CompileError::Cargo { .. } => "cargo",
+            CompileError::WasmPack(_) => "wasm-pack",
+            CompileError::PostProcess(_) => "post-process",
+            CompileError::BudgetExceeded { .. } => "budget-exceeded",
+            CompileError::Incompatible { .. } => "wasm-incompatible",
+            CompileError::SecretFound { .. } => "secret-found",
+            CompileError::RunFailed(_) => "run-failed",
+            CompileError::VerificationFailed(_) => "verification-failed",
+        }
+    }
+
+    // Maps a failed process spawn to ToolchainMissing when the binary isn't on PATH
+    pub(crate) fn spawn(tool: &str, hint: &str, err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::NotFound {
+            CompileError::ToolchainMissing {
+                tool: tool.to_string(),
+                hint: hint.to_string(),
+            }
+        } else {
+            CompileError::Io(err)
+        }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::InputNotFound(input) => write!(f, "Input does not exist: {}", input),
+            CompileError::InvalidInput(reason) => write!(f, "Invalid input: {}", reason),
+            CompileError::ToolchainMissing { tool, hint } => {
+                write!(f, "{} is not installed. Install it with `{}`", tool, hint)
+            }
+            CompileError::Cargo { message, diagnostics } => {
+                let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+                if errors > 0 {
+                    write!(f, "{} ({} error(s))", message, errors)
+                } else {
+                    write!(f, "{}", message)
+                }
+            }
+            CompileError::WasmPack(stderr) => write!(f, "wasm-pack failed: {}", stderr),
+            CompileError::PostProcess(reason) => write!(f, "Post-processing failed: {}", reason),
+            CompileError::BudgetExceeded { violations, largest_functions } => {
+                write!(f, "Size budget exceeded:")?;
+                for violation in violations {
+                    write!(f, "\n  {}", violation)?;
+                }
+                if !largest_functions.is_empty() {
+                    write!(f, "\nLargest functions:")?;
+                    for (name, size) in largest_functions {
+                        write!(f, "\n  {:>12}  {}", format_size(*size), name)?;
+                    }
+                }
+                Ok(())
+            }
+            CompileError::Incompatible { diagnostics } => write!(
+                f,
+                "{} wasm-incompatible API or crate use(s) found; fix them or build with --lint warn",
+                diagnostics.len()
+            ),
+            CompileError::SecretFound { secrets } => {
+                write!(f, "Found {} secret(s) that would ship with the module:", secrets.len())?;
+                for secret in secrets {
+                    write!(f, "\n  {}", secret)?;
+                }
+                write!(
+                    f,
+                    "\nPass API keys to initialize_rig_wasm at runtime, or build with --config-secrets placeholder"
+                )
+            }
+            CompileError::RunFailed(reason) => write!(f, "Run failed: {}", reason),
+            CompileError::VerificationFailed(reason) => write!(f, "Verification failed: {}", reason),
+            CompileError::Io(err) => write!(f, "I/O error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CompileError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CompileError {
+    fn from(err: io::Error) -> Self {
+        CompileError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let errors = vec![
+            CompileError::Io(io::Error::other("io")),
+            CompileError::InputNotFound("a.rs".into()),
+            CompileError::InvalidInput("not a cdylib".into()),
+            CompileError::ToolchainMissing { tool: "wasm-pack".into(), hint: "cargo install wasm-pack".into() },
+            CompileError::Cargo { message: "cargo build failed".into(), diagnostics: vec![] },
+            CompileError::WasmPack("boom".into()),
+            CompileError::PostProcess("missing glue".into()),
+            CompileError::BudgetExceeded { violations: vec![], largest_functions: vec![] },
+            CompileError::Incompatible { diagnostics: vec![] },
+            CompileError::SecretFound { secrets: vec![] },
+            CompileError::RunFailed("trap".into()),
+            CompileError::VerificationFailed("bad signature".into()),
+        ];
+
+        let mut codes: Vec<i32> = errors.iter().map(CompileError::exit_code).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len());
+        assert!(!codes.contains(&0));
+    }
+
+    #[test]
+    fn test_missing_binary_is_toolchain_error() {
+        let err = CompileError::spawn("wasm-pack", "cargo install wasm-pack", io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!(err.kind(), "toolchain-missing");
+    }
+}
@@ -0,0 +1,286 @@
+use crate::compiler::error::CompileError;
+use serde::Deserialize;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Dependencies of the crate generated around a bare .rs file. These mirror the
+// versions this crate is built against so the examples compile unchanged, and
+// are also what `init` writes into new projects.
+pub(crate) const ENTRY_DEPENDENCIES: &str = r#"rig-core = "0.21"
+wasm-bindgen = "0.2"
+wasm-bindgen-futures = "0.4"
+js-sys = "0.3"
+web-sys = { version = "0.3", features = ["console"] }
+tokio_with_wasm = "0.7.1"
+serde = { version = "1.0", features = ["derive"] }
+serde_json = "1.0"
+serde-wasm-bindgen = "0.5"
+getrandom = { version = "0.2", features = ["js"] }
+async-trait = "0.1"
+"#;
+
+// A crate that wasm-pack can build, resolved from whatever was passed as `--input`
+#[derive(Debug, Clone)]
+pub struct ResolvedInput {
+    pub crate_dir: PathBuf,
+    pub manifest_path: PathBuf,
+    pub package_name: String,
+    // Set when the input was a bare .rs file wrapped in a generated crate
+    pub generated: bool,
+    // The wrapped .rs file, for generated crates
+    pub source_file: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct CargoMetadata {
+    packages: Vec<MetadataPackage>,
+    workspace_members: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct MetadataPackage {
+    id: String,
+    name: String,
+    manifest_path: PathBuf,
+    targets: Vec<MetadataTarget>,
+}
+
+#[derive(Deserialize)]
+struct MetadataTarget {
+    crate_types: Vec<String>,
+}
+
+// Accepts a crate directory, a path to a Cargo.toml, a workspace member name
+// or a single .rs file
+pub fn resolve_input(input: &str) -> Result<ResolvedInput, CompileError> {
+    let path = Path::new(input);
+
+    if path.is_file() {
+        if path.file_name().is_some_and(|name| name == "Cargo.toml") {
+            return from_manifest(path);
+        }
+        if path.extension().is_some_and(|ext| ext == "rs") {
+            return wrap_source_file(path);
+        }
+        return Err(CompileError::InvalidInput(format!(
+            "{} is neither a Cargo.toml nor a .rs file",
+            input
+        )));
+    }
+
+    if path.is_dir() {
+        let manifest = path.join("Cargo.toml");
+        if !manifest.is_file() {
+            return Err(CompileError::InvalidInput(format!("No Cargo.toml found in {}", input)));
+        }
+        return from_manifest(&manifest);
+    }
+
+    find_workspace_member(input)
+}
+
+fn cargo_metadata(manifest_path: Option<&Path>) -> Result<CargoMetadata, CompileError> {
+    let mut command = Command::new("cargo");
+    command.args(["metadata", "--no-deps", "--format-version", "1"]);
+    if let Some(manifest_path) = manifest_path {
+        command.arg("--manifest-path").arg(manifest_path);
+    }
+
+    let output = command
+        .output()
+        .map_err(|e| CompileError::spawn("cargo", "https://rustup.rs", e))?;
+    if !output.status.success() {
+        return Err(CompileError::Cargo {
+            message: format!("cargo metadata failed: {}", String::from_utf8_lossy(&output.stderr)),
+            diagnostics: Vec::new(),
+        });
+    }
+
+    serde_json::from_slice(&output.stdout).map_err(|e| CompileError::Cargo {
+        message: format!("Failed to parse cargo metadata: {}", e),
+        diagnostics: Vec::new(),
+    })
+}
+
+fn from_manifest(manifest_path: &Path) -> Result<ResolvedInput, CompileError> {
+    let manifest_path = fs::canonicalize(manifest_path)?;
+    let metadata = cargo_metadata(Some(&manifest_path))?;
+
+    let package = metadata.packages
+        .into_iter()
+        .find(|p| p.manifest_path == manifest_path)
+        .ok_or_else(|| CompileError::InvalidInput(format!(
+            "{} is a virtual workspace manifest; pass the name of a member crate instead",
+            manifest_path.display()
+        )))?;
+
+    into_resolved(package)
+}
+
+fn find_workspace_member(name: &str) -> Result<ResolvedInput, CompileError> {
+    let metadata = match cargo_metadata(None) {
+        Ok(metadata) => metadata,
+        // Outside a cargo project there is no member to find. A cargo that
+        // cannot be run is reported as such.
+        Err(CompileError::Cargo { .. }) => return Err(CompileError::InputNotFound(name.to_string())),
+        Err(e) => return Err(e),
+    };
+
+    let package = metadata.packages
+        .into_iter()
+        .filter(|p| metadata.workspace_members.contains(&p.id))
+        .find(|p| p.name == name)
+        .ok_or_else(|| CompileError::InputNotFound(name.to_string()))?;
+
+    into_resolved(package)
+}
+
+fn into_resolved(package: MetadataPackage) -> Result<ResolvedInput, CompileError> {
+    let is_cdylib = package.targets
+        .iter()
+        .any(|t| t.crate_types.iter().any(|c| c == "cdylib"));
+    if !is_cdylib {
+        return Err(CompileError::InvalidInput(format!(
+            "Crate {} must set `crate-type = [\"cdylib\", \"rlib\"]` in its [lib] section",
+            package.name
+        )));
+    }
+
+    let crate_dir = package.manifest_path
+        .parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| CompileError::InvalidInput("Manifest path has no parent directory".to_string()))?;
+
+    Ok(ResolvedInput {
+        crate_dir,
+        manifest_path: package.manifest_path,
+        package_name: package.name,
+        generated: false,
+        source_file: None,
+    })
+}
+
+// Wraps a single source file in a cdylib crate under the system temp directory.
+// The directory is keyed by the file's path so repeated builds reuse cargo's
+// incremental state.
+fn wrap_source_file(source: &Path) -> Result<ResolvedInput, CompileError> {
+    let source = fs::canonicalize(source)?;
+    let stem = source
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| CompileError::InvalidInput("Input file name is not valid UTF-8".to_string()))?;
+    let package_name = sanitize_package_name(stem);
+
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    let crate_dir = std::env::temp_dir()
+        .join("rig-wasm-compiler")
+        .join(format!("{}-{:016x}", package_name, hasher.finish()));
+    fs::create_dir_all(&crate_dir)?;
+
+    let manifest = format!(
+        r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+publish = false
+
+[lib]
+crate-type = ["cdylib", "rlib"]
+path = "{path}"
+
+[dependencies]
+{deps}
+[workspace]
+"#,
+        name = package_name,
+        path = source.display().to_string().replace('\\', "/"),
+        deps = ENTRY_DEPENDENCIES,
+    );
+
+    let manifest_path = crate_dir.join("Cargo.toml");
+    fs::write(&manifest_path, manifest)?;
+
+    Ok(ResolvedInput {
+        crate_dir,
+        manifest_path,
+        package_name,
+        generated: true,
+        source_file: Some(source),
+    })
+}
+
+// Default module name for an entry: the file stem of a .rs file, otherwise the
+// crate directory or workspace member name, usable as a JS identifier
+pub fn entry_name(input: &str) -> String {
+    let path = Path::new(input);
+    let path = if path.file_name().is_some_and(|name| name == "Cargo.toml") {
+        path.parent().unwrap_or(path)
+    } else {
+        path
+    };
+    let stem = if path.extension().is_some_and(|ext| ext == "rs") {
+        path.file_stem()
+    } else {
+        path.file_name()
+    };
+    let stem = stem.map(|s| s.to_string_lossy().into_owned()).unwrap_or_else(|| "rig_wasm".to_string());
+    sanitize_package_name(&stem).replace('-', "_")
+}
+
+fn sanitize_package_name(stem: &str) -> String {
+    let name: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect();
+
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => name,
+        _ => format!("rig_{}", name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_source_file() {
+        let resolved = resolve_input("examples/simple_agent.rs").unwrap();
+        assert!(resolved.generated);
+        assert_eq!(resolved.package_name, "simple_agent");
+
+        let manifest = fs::read_to_string(&resolved.manifest_path).unwrap();
+        assert!(manifest.contains("crate-type = [\"cdylib\", \"rlib\"]"));
+        assert!(manifest.contains("simple_agent.rs"));
+        assert!(manifest.contains("tokio_with_wasm"));
+    }
+
+    #[test]
+    fn test_entry_rig_core_matches_crate() {
+        let rig_core = |manifest: &str| manifest.lines().find(|line| line.starts_with("rig-core")).map(str::to_string);
+        assert_eq!(rig_core(ENTRY_DEPENDENCIES), rig_core(include_str!("../../Cargo.toml")));
+    }
+
+    #[test]
+    fn test_missing_input() {
+        let err = resolve_input("does/not/exist.rs").unwrap_err();
+        assert_eq!(err.kind(), "input-not-found");
+    }
+
+    #[test]
+    fn test_sanitize_package_name() {
+        assert_eq!(sanitize_package_name("rag agent"), "rag_agent");
+        assert_eq!(sanitize_package_name("1st"), "rig_1st");
+    }
+
+    #[test]
+    fn test_entry_name() {
+        assert_eq!(entry_name("examples/rag_agent.rs"), "rag_agent");
+        assert_eq!(entry_name("crates/simple-agent/Cargo.toml"), "simple_agent");
+        assert_eq!(entry_name("simple-agent"), "simple_agent");
+    }
+}
@@ -0,0 +1,291 @@
+// `inspect`: what a built module exports and imports, how much memory it asks
+// for and which custom sections it carries
+use crate::compiler::error::CompileError;
+use crate::compiler::report::format_size;
+use crate::compiler::wasm_module::{self, ComponentExtern, Export, Import, Limits};
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const PAGE_SIZE: u64 = 64 * 1024;
+
+// Where an import is expected to come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImportOrigin {
+    // The wasm-bindgen JS glue
+    Bindgen,
+    // A WASI host
+    Wasi,
+    // `env`: symbols a native dependency expected the C toolchain to link
+    Env,
+    Unknown,
+}
+
+impl ImportOrigin {
+    fn of(module: &str) -> Self {
+        if module == "wbg" || module.starts_with("__wbindgen") || module.ends_with(".js") {
+            ImportOrigin::Bindgen
+        } else if module.starts_with("wasi_snapshot_preview") || module.starts_with("wasi:") {
+            ImportOrigin::Wasi
+        } else if module == "env" {
+            ImportOrigin::Env
+        } else {
+            ImportOrigin::Unknown
+        }
+    }
+
+    pub fn is_expected(&self) -> bool {
+        matches!(self, ImportOrigin::Bindgen | ImportOrigin::Wasi)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InspectedImport {
+    #[serde(flatten)]
+    pub import: Import,
+    pub origin: ImportOrigin,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CustomSection {
+    pub name: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InspectReport {
+    pub file: PathBuf,
+    pub size: u64,
+    // Components list their top-level imports and exports by name; the types
+    // are in the WIT world
+    pub component: bool,
+    pub component_exports: Vec<ComponentExtern>,
+    pub component_imports: Vec<ComponentExtern>,
+    // Exports meant to be called, i.e. everything but wasm-bindgen's own
+    pub exports: Vec<Export>,
+    // wasm-bindgen and linker internals such as __wbindgen_malloc
+    pub internal_exports: Vec<String>,
+    pub imports: Vec<InspectedImport>,
+    // Imported memories first, then the ones the module defines
+    pub memories: Vec<Limits>,
+    pub custom_sections: Vec<CustomSection>,
+    pub name_section: bool,
+    pub dwarf: bool,
+}
+
+impl InspectReport {
+    pub fn unexpected_imports(&self) -> impl Iterator<Item = &InspectedImport> {
+        self.imports.iter().filter(|i| !i.origin.is_expected())
+    }
+}
+
+fn is_internal_export(name: &str) -> bool {
+    name.starts_with("__wbindgen")
+        || name.starts_with("__wbg_")
+        || name.starts_with("__externref")
+        || matches!(name, "__data_end" | "__heap_base" | "__indirect_function_table")
+}
+
+pub fn inspect(path: &Path) -> Result<InspectReport, CompileError> {
+    if !path.is_file() {
+        return Err(CompileError::InputNotFound(path.display().to_string()));
+    }
+    let bytes = fs::read(path)?;
+    inspect_bytes(path, &bytes)
+        .map_err(|reason| CompileError::InvalidInput(format!("{}: {}", path.display(), reason)))
+}
+
+fn inspect_bytes(path: &Path, bytes: &[u8]) -> Result<InspectReport, String> {
+    let sections = wasm_module::sections(bytes)?;
+    let custom_sections: Vec<CustomSection> = sections
+        .iter()
+        .filter_map(|section| {
+            section.name.as_ref().map(|name| CustomSection {
+                name: name.clone(),
+                size: section.range.len() as u64,
+            })
+        })
+        .collect();
+    let name_section = custom_sections.iter().any(|s| s.name == "name");
+    let dwarf = custom_sections.iter().any(|s| s.name.starts_with(".debug_"));
+
+    let component = wasm_module::is_component(bytes);
+    let (component_exports, component_imports) = if component {
+        (wasm_module::component_exports(bytes)?, wasm_module::component_imports(bytes)?)
+    } else {
+        (Vec::new(), Vec::new())
+    };
+    let (exports, internal_exports, imports, memories) = if component {
+        (Vec::new(), Vec::new(), Vec::new(), Vec::new())
+    } else {
+        let (internal, exports): (Vec<Export>, Vec<Export>) =
+            wasm_module::exports(bytes)?.into_iter().partition(|e| is_internal_export(&e.name));
+        let imports: Vec<InspectedImport> = wasm_module::imports(bytes)?
+            .into_iter()
+            .map(|import| InspectedImport { origin: ImportOrigin::of(&import.module), import })
+            .collect();
+        let memories = imports
+            .iter()
+            .filter_map(|i| i.import.memory)
+            .chain(wasm_module::memories(bytes)?)
+            .collect();
+        (exports, internal.into_iter().map(|e| e.name).collect(), imports, memories)
+    };
+
+    Ok(InspectReport {
+        file: path.to_path_buf(),
+        size: bytes.len() as u64,
+        component,
+        component_exports,
+        component_imports,
+        exports,
+        internal_exports,
+        imports,
+        memories,
+        custom_sections,
+        name_section,
+        dwarf,
+    })
+}
+
+fn pages(pages: u64) -> String {
+    format!("{} pages ({})", pages, format_size(pages * PAGE_SIZE))
+}
+
+impl fmt::Display for ImportOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ImportOrigin::Bindgen => "wasm-bindgen glue",
+            ImportOrigin::Wasi => "WASI",
+            ImportOrigin::Env => "env",
+            ImportOrigin::Unknown => "unknown host module",
+        })
+    }
+}
+
+impl fmt::Display for InspectReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Module: {} ({})", self.file.display(), format_size(self.size))?;
+
+        if self.component {
+            writeln!(f, "Component exports ({}):", self.component_exports.len())?;
+            for export in &self.component_exports {
+                writeln!(f, "  {:<9} {}", export.kind.as_str(), export.name)?;
+            }
+            writeln!(f, "Component imports ({}):", self.component_imports.len())?;
+            for import in &self.component_imports {
+                writeln!(f, "  {:<9} {}", import.kind.as_str(), import.name)?;
+            }
+            writeln!(f, "  (wasm-tools component wit {} prints the full WIT world)", self.file.display())?;
+        } else {
+            writeln!(f, "Exports ({}, plus {} internal):", self.exports.len(), self.internal_exports.len())?;
+            for export in &self.exports {
+                writeln!(f, "  {:<7} {}", export.kind.as_str(), export.name)?;
+            }
+
+            writeln!(f, "Imports ({}):", self.imports.len())?;
+            for origin in [ImportOrigin::Bindgen, ImportOrigin::Wasi] {
+                let count = self.imports.iter().filter(|i| i.origin == origin).count();
+                if count > 0 {
+                    writeln!(f, "  {} from {}", count, origin)?;
+                }
+            }
+            for import in self.unexpected_imports() {
+                let hint = match import.origin {
+                    ImportOrigin::Env => "a native dependency (C code or libc) that nothing will provide",
+                    _ => "not provided by the JS glue or a WASI host",
+                };
+                writeln!(
+                    f,
+                    "  warning: {}.{} ({}): {}",
+                    import.import.module,
+                    import.import.name,
+                    import.import.kind.as_str(),
+                    hint
+                )?;
+            }
+
+            for memory in &self.memories {
+                write!(f, "Memory: {} initial", pages(memory.initial))?;
+                match memory.maximum {
+                    Some(maximum) => write!(f, ", {} max", pages(maximum))?,
+                    None => write!(f, ", no maximum")?,
+                }
+                if memory.shared {
+                    write!(f, ", shared")?;
+                }
+                if memory.memory64 {
+                    write!(f, ", 64-bit")?;
+                }
+                writeln!(f)?;
+            }
+            if self.memories.is_empty() {
+                writeln!(f, "Memory: none")?;
+            }
+        }
+
+        writeln!(f, "Custom sections ({}):", self.custom_sections.len())?;
+        for section in &self.custom_sections {
+            writeln!(f, "  {:>12}  {}", format_size(section.size), section.name)?;
+        }
+        let yes_no = |present: bool| if present { "yes" } else { "no" };
+        write!(f, "Debug info: name section {}, DWARF {}", yes_no(self.name_section), yes_no(self.dwarf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::wasm_module::tests::{custom_section, sample_component, sample_module};
+
+    #[test]
+    fn test_env_import_is_unexpected() {
+        let report = inspect_bytes(Path::new("sample.wasm"), &sample_module()).unwrap();
+        let unexpected: Vec<_> = report.unexpected_imports().collect();
+        assert_eq!(unexpected.len(), 1);
+        assert_eq!(unexpected[0].origin, ImportOrigin::Env);
+        assert!(report.name_section);
+        assert!(!report.dwarf);
+        assert!(report.to_string().contains("warning: env.f (func)"));
+
+        assert_eq!(ImportOrigin::of("./rig_wasm_bg.js"), ImportOrigin::Bindgen);
+        assert_eq!(ImportOrigin::of("wbg"), ImportOrigin::Bindgen);
+        assert_eq!(ImportOrigin::of("wasi_snapshot_preview1"), ImportOrigin::Wasi);
+    }
+
+    #[test]
+    fn test_dwarf_and_json() {
+        let mut module = sample_module();
+        module.extend(custom_section(".debug_info", &[0, 0]));
+        let report = inspect_bytes(Path::new("sample.wasm"), &module).unwrap();
+        assert!(report.dwarf);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["imports"][0]["module"], "env");
+        assert_eq!(json["imports"][0]["origin"], "env");
+        assert_eq!(json["custom_sections"][2]["name"], ".debug_info");
+    }
+
+    #[test]
+    fn test_component_lists_imports_and_exports() {
+        let report = inspect_bytes(Path::new("agent.wasm"), &sample_component()).unwrap();
+        assert!(report.component);
+        assert_eq!(report.component_exports[0].name, "rig:agent/agent@0.1.0");
+        assert_eq!(report.component_imports[0].name, "wasi:cli/environment@0.2.0");
+
+        let text = report.to_string();
+        assert!(text.contains("instance  rig:agent/agent@0.1.0"));
+        assert!(text.contains("instance  wasi:cli/environment@0.2.0"));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["component_exports"][0]["kind"], "instance");
+    }
+
+    #[test]
+    fn test_not_wasm() {
+        assert!(inspect_bytes(Path::new("a.wasm"), b"not wasm").is_err());
+        assert!(matches!(inspect(Path::new("does-not-exist.wasm")), Err(CompileError::InputNotFound(_))));
+    }
+}
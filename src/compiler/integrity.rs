@@ -0,0 +1,568 @@
+// Reproducible builds and Subresource Integrity. Absolute paths are remapped so
+// the output does not depend on where the crate, its dependencies or the cargo
+// home live, file times are pinned to $SOURCE_DATE_EPOCH, and every emitted
+// file is listed in manifest.json with its SHA-384 SRI hash.
+use crate::compiler::error::CompileError;
+use crate::compiler::input::ResolvedInput;
+use crate::compiler::signing::{self, SignatureReport};
+use crate::compiler::target::Target;
+use crate::compiler::toolchain;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha384};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+// The reproducible-builds.org variable; unset means the Unix epoch
+pub const SOURCE_DATE_EPOCH_ENV: &str = "SOURCE_DATE_EPOCH";
+
+// What the crate's workspace and the cargo home are remapped to
+const BUILD_ROOT: &str = "/build";
+const CARGO_HOME_ROOT: &str = "/cargo";
+
+// Text outputs that may mention a build path
+const TEXT_EXTENSIONS: &[&str] = &["js", "mjs", "cjs", "ts", "json", "wit", "md"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileIntegrity {
+    // Relative to the output directory, with `/` separators
+    pub path: String,
+    pub size: u64,
+    // `sha384-<base64>`, as used by the integrity attribute
+    pub integrity: String,
+}
+
+// manifest.json. It holds no times or absolute paths, so identical inputs give
+// an identical manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityManifest {
+    pub files: Vec<FileIntegrity>,
+}
+
+impl IntegrityManifest {
+    pub fn file(&self, path: &str) -> Option<&FileIntegrity> {
+        self.files.iter().find(|file| file.path == path)
+    }
+}
+
+// The integrity stage of a compile report
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityReport {
+    pub manifest: PathBuf,
+    pub files: usize,
+    // File times of a --reproducible build
+    pub source_date_epoch: Option<u64>,
+    pub signature: Option<SignatureReport>,
+    // `<script>` tags for the JS glue, for targets a page loads directly
+    pub snippets: Vec<String>,
+}
+
+impl fmt::Display for IntegrityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Integrity: {} files hashed (sha384) in {}", self.files, self.manifest.display())?;
+        if let Some(epoch) = self.source_date_epoch {
+            write!(f, ", timestamps set to {}", epoch)?;
+        }
+        if let Some(signature) = &self.signature {
+            write!(f, "\n{}", signature)?;
+        }
+        for snippet in &self.snippets {
+            write!(f, "\n  {}", snippet)?;
+        }
+        Ok(())
+    }
+}
+
+// $SOURCE_DATE_EPOCH, or 0
+pub fn source_date_epoch() -> Result<u64, CompileError> {
+    match std::env::var(SOURCE_DATE_EPOCH_ENV) {
+        Ok(value) => value.trim().parse().map_err(|_| {
+            CompileError::InvalidInput(format!("{} must be a number of seconds, not {:?}", SOURCE_DATE_EPOCH_ENV, value))
+        }),
+        Err(_) => Ok(0),
+    }
+}
+
+fn cargo_home() -> Option<PathBuf> {
+    std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cargo")))
+}
+
+// Absolute prefixes and what they become: the workspace root (or the crate
+// directory outside a workspace), then the cargo home with the registry sources
+pub fn path_prefixes(resolved: &ResolvedInput) -> Vec<(PathBuf, String)> {
+    let root = toolchain::find_lock_file(&resolved.crate_dir)
+        .and_then(|lock_file| lock_file.parent().map(Path::to_path_buf))
+        .unwrap_or_else(|| resolved.crate_dir.clone());
+    let mut prefixes = vec![(fs::canonicalize(&root).unwrap_or(root), BUILD_ROOT.to_string())];
+    if let Some(home) = cargo_home() {
+        prefixes.push((fs::canonicalize(&home).unwrap_or(home), CARGO_HOME_ROOT.to_string()));
+    }
+    prefixes
+}
+
+// How the --remap-path-prefix flags reach rustc without dropping the user's own flags
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemapFlags {
+    // RUSTFLAGS or CARGO_ENCODED_RUSTFLAGS is set, so cargo ignores the rustflags in
+    // its config files. The flags are appended to what was set, in the encoded
+    // form that keeps paths with spaces intact.
+    Env(String),
+    // A `--config target.<triple>.rustflags=[..]` argument for cargo, whose arrays
+    // are joined with the config files'
+    Config(String),
+}
+
+impl RemapFlags {
+    pub fn env(&self) -> Option<(&'static str, String)> {
+        match self {
+            RemapFlags::Env(encoded) => Some(("CARGO_ENCODED_RUSTFLAGS", encoded.clone())),
+            RemapFlags::Config(_) => None,
+        }
+    }
+
+    pub fn cargo_args(&self) -> Vec<String> {
+        match self {
+            RemapFlags::Env(_) => Vec::new(),
+            RemapFlags::Config(config) => vec!["--config".to_string(), config.clone()],
+        }
+    }
+}
+
+// The rustflags set in the environment, which win over every config file
+pub fn env_rustflags() -> Option<Vec<String>> {
+    match std::env::var("CARGO_ENCODED_RUSTFLAGS") {
+        Ok(encoded) => Some(encoded.split('\x1f').filter(|flag| !flag.is_empty()).map(str::to_string).collect()),
+        Err(_) => std::env::var("RUSTFLAGS")
+            .ok()
+            .map(|flags| flags.split_whitespace().map(str::to_string).collect()),
+    }
+}
+
+// The cargo config files that apply to a build run in `dir`, lowest precedence
+// first: the cargo home, then .cargo/config.toml in each directory down to `dir`
+pub fn cargo_config_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    let mut add = |dir: &Path| {
+        for name in ["config.toml", "config"] {
+            let file = dir.join(name);
+            if file.is_file() {
+                if !files.contains(&file) {
+                    files.push(file);
+                }
+                break;
+            }
+        }
+    };
+    if let Some(home) = cargo_home() {
+        add(&home);
+    }
+    let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    let ancestors: Vec<&Path> = dir.ancestors().collect();
+    for ancestor in ancestors.into_iter().rev() {
+        add(&ancestor.join(".cargo"));
+    }
+    files
+}
+
+// A rustflags value: an array, or a string of space-separated flags
+fn flag_list(value: &toml::Value) -> Vec<String> {
+    match value {
+        toml::Value::String(flags) => flags.split_whitespace().map(str::to_string).collect(),
+        toml::Value::Array(flags) => flags.iter().filter_map(|flag| flag.as_str()).map(str::to_string).collect(),
+        _ => Vec::new(),
+    }
+}
+
+// The remap flags for a build of `crate_dir` for `rust_target`. cargo runs in
+// `crate_dir`, so its config files are the ones that apply.
+pub fn remap_flags(crate_dir: &Path, rust_target: &str, prefixes: &[(PathBuf, String)]) -> Result<RemapFlags, CompileError> {
+    let mut configs = Vec::new();
+    for file in cargo_config_files(crate_dir) {
+        let contents = fs::read_to_string(&file)?;
+        let config: toml::Value = toml::from_str(&contents)
+            .map_err(|e| CompileError::InvalidInput(format!("Invalid cargo config {}: {}", file.display(), e)))?;
+        configs.push(config);
+    }
+
+    // `target.'cfg(..)'` tables need the target's cfg values to be matched
+    let has_cfg_tables = configs.iter().any(|config| {
+        config
+            .get("target")
+            .and_then(toml::Value::as_table)
+            .is_some_and(|targets| targets.keys().any(|key| key.starts_with("cfg(")))
+    });
+    let target_cfg = if has_cfg_tables { toolchain::target_cfg(rust_target)? } else { Vec::new() };
+
+    Ok(merge_remap_flags(env_rustflags(), &configs, rust_target, &target_cfg, prefixes))
+}
+
+// cargo takes rustflags from the first of: the environment, the
+// `target.<triple>` and matching `target.'cfg(..)'` tables, `build.rustflags`.
+// The remap flags are added at that level, so none of the user's flags are lost.
+fn merge_remap_flags(
+    env_flags: Option<Vec<String>>,
+    configs: &[toml::Value],
+    rust_target: &str,
+    target_cfg: &[String],
+    prefixes: &[(PathBuf, String)],
+) -> RemapFlags {
+    // rustc applies the last matching remapping, so `prefixes` go from broad to specific
+    let remaps = prefixes.iter().map(|(from, to)| format!("--remap-path-prefix={}={}", from.display(), to));
+
+    if let Some(mut flags) = env_flags {
+        flags.extend(remaps);
+        return RemapFlags::Env(flags.join("\x1f"));
+    }
+
+    let target_level = configs.iter().any(|config| {
+        config.get("target").and_then(toml::Value::as_table).is_some_and(|targets| {
+            targets.iter().any(|(key, table)| {
+                table.get("rustflags").is_some() && (key == rust_target || cfg_matches(key, target_cfg))
+            })
+        })
+    });
+
+    // Target-level flags of our own would make cargo ignore build.rustflags, so
+    // those are carried over
+    let mut flags: Vec<String> = if target_level {
+        Vec::new()
+    } else {
+        configs
+            .iter()
+            .filter_map(|config| config.get("build").and_then(|build| build.get("rustflags")))
+            .flat_map(flag_list)
+            .collect()
+    };
+    flags.extend(remaps);
+
+    let array = toml::Value::Array(flags.into_iter().map(toml::Value::String).collect());
+    RemapFlags::Config(format!("target.{}.rustflags={}", rust_target, array))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum CfgToken {
+    Ident(String),
+    Str(String),
+    Punct(char),
+}
+
+fn cfg_tokens(expr: &str) -> Vec<CfgToken> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let value: String = chars.by_ref().take_while(|&c| c != '"').collect();
+            tokens.push(CfgToken::Str(value));
+        } else if c.is_alphanumeric() || c == '_' {
+            let mut ident = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+                ident.push(c);
+                chars.next();
+            }
+            tokens.push(CfgToken::Ident(ident));
+        } else {
+            tokens.push(CfgToken::Punct(c));
+            chars.next();
+        }
+    }
+    tokens
+}
+
+// One cfg predicate starting at `tokens[*pos]`; None if it does not parse
+fn cfg_eval(tokens: &[CfgToken], pos: &mut usize, cfg: &[String]) -> Option<bool> {
+    let name = match tokens.get(*pos)? {
+        CfgToken::Ident(name) => name.clone(),
+        _ => return None,
+    };
+    *pos += 1;
+    match tokens.get(*pos) {
+        Some(CfgToken::Punct('(')) if ["all", "any", "not"].contains(&name.as_str()) => {
+            *pos += 1;
+            let mut values = Vec::new();
+            while tokens.get(*pos) != Some(&CfgToken::Punct(')')) {
+                values.push(cfg_eval(tokens, pos, cfg)?);
+                if tokens.get(*pos) == Some(&CfgToken::Punct(',')) {
+                    *pos += 1;
+                }
+            }
+            *pos += 1;
+            match name.as_str() {
+                "all" => Some(values.iter().all(|&value| value)),
+                "any" => Some(values.iter().any(|&value| value)),
+                _ => Some(values.len() == 1 && !values[0]),
+            }
+        }
+        Some(CfgToken::Punct('=')) => match tokens.get(*pos + 1)? {
+            CfgToken::Str(value) => {
+                *pos += 2;
+                Some(cfg.contains(&format!("{}=\"{}\"", name, value)))
+            }
+            _ => None,
+        },
+        _ => Some(cfg.contains(&name)),
+    }
+}
+
+// Whether a `[target]` key such as `cfg(target_arch = "wasm32")` matches the
+// target's `rustc --print cfg` lines
+fn cfg_matches(key: &str, cfg: &[String]) -> bool {
+    let expr = match key.trim().strip_prefix("cfg(").and_then(|key| key.strip_suffix(')')) {
+        Some(expr) => expr,
+        None => return false,
+    };
+    let tokens = cfg_tokens(expr);
+    let mut pos = 0;
+    cfg_eval(&tokens, &mut pos, cfg) == Some(true) && pos == tokens.len()
+}
+
+// Replaces the prefixes in text, longest first so nested paths map to the most
+// specific replacement
+pub fn normalize_text(text: &str, prefixes: &[(PathBuf, String)]) -> String {
+    let mut prefixes: Vec<(String, &str)> =
+        prefixes.iter().map(|(from, to)| (from.to_string_lossy().into_owned(), to.as_str())).collect();
+    prefixes.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+    prefixes
+        .iter()
+        .filter(|(from, _)| !from.is_empty())
+        .fold(text.to_string(), |text, (from, to)| text.replace(from.as_str(), to))
+}
+
+// Files under `dir`, relative to `base`, with `/` separators, sorted
+fn files_under(base: &Path, dir: &Path) -> Result<Vec<String>, CompileError> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(files_under(base, &path)?);
+        } else if path.is_file() {
+            let relative = path.strip_prefix(base).unwrap_or(&path);
+            files.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+// Rewrites build paths out of the text files in `output_dir`. Returns the files changed.
+pub fn normalize_outputs(output_dir: &Path, prefixes: &[(PathBuf, String)]) -> Result<Vec<String>, CompileError> {
+    let mut changed = Vec::new();
+    for file in files_under(output_dir, output_dir)? {
+        let path = output_dir.join(&file);
+        let is_text = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| TEXT_EXTENSIONS.contains(&extension));
+        if !is_text {
+            continue;
+        }
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(_) => continue,
+        };
+        let normalized = normalize_text(&contents, prefixes);
+        if normalized != contents {
+            fs::write(&path, normalized)?;
+            changed.push(file);
+        }
+    }
+    Ok(changed)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &byte)| n | ((byte as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+// The Subresource Integrity value for `bytes`
+pub fn sri(bytes: &[u8]) -> String {
+    format!("sha384-{}", base64(&Sha384::digest(bytes)))
+}
+
+// Hashes every file in `output_dir` except the manifest and its signature and writes manifest.json
+pub fn write_manifest(output_dir: &Path) -> Result<IntegrityManifest, CompileError> {
+    let mut files = Vec::new();
+    for path in files_under(output_dir, output_dir)? {
+        if path == MANIFEST_FILE || path == signing::SIGNATURE_FILE {
+            continue;
+        }
+        let bytes = fs::read(output_dir.join(&path))?;
+        files.push(FileIntegrity {
+            path,
+            size: bytes.len() as u64,
+            integrity: sri(&bytes),
+        });
+    }
+    let manifest = IntegrityManifest { files };
+    let mut json = serde_json::to_vec_pretty(&manifest).map_err(|e| CompileError::PostProcess(e.to_string()))?;
+    json.push(b'\n');
+    fs::write(output_dir.join(MANIFEST_FILE), json)?;
+    Ok(manifest)
+}
+
+// Sets the modification time of every file in `output_dir` to `epoch`
+pub fn set_timestamps(output_dir: &Path, epoch: u64) -> Result<(), CompileError> {
+    let time = UNIX_EPOCH + Duration::from_secs(epoch);
+    for file in files_under(output_dir, output_dir)? {
+        fs::File::options().write(true).open(output_dir.join(file))?.set_modified(time)?;
+    }
+    Ok(())
+}
+
+// `<script>` tags loading the JS glue of `out_name`. Only targets a page loads
+// with a script tag get one; bundlers and runtimes read the manifest instead.
+pub fn script_snippets(manifest: &IntegrityManifest, target: Target, out_name: &str) -> Vec<String> {
+    let script_type = match target {
+        Target::Web => " type=\"module\"",
+        Target::NoModules => "",
+        _ => return Vec::new(),
+    };
+    target
+        .glue_files(out_name)
+        .iter()
+        .filter(|file| file.ends_with(".js"))
+        .filter_map(|file| manifest.file(file))
+        .map(|file| {
+            format!(
+                "<script{} src=\"./{}\" integrity=\"{}\" crossorigin=\"anonymous\"></script>",
+                script_type, file.path, file.integrity
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sri() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        // echo -n "alert('Hello, world.');" | openssl dgst -sha384 -binary | openssl base64 -A
+        assert_eq!(
+            sri(b"alert('Hello, world.');"),
+            "sha384-H8BRh8j48O9oYatfu5AZzq6A9RINhZO5H16dQZngK7T62em8MUt1FLm52t+eX6xO"
+        );
+    }
+
+    #[test]
+    fn test_normalize_text() {
+        let prefixes = vec![
+            (PathBuf::from("/home/ci/project"), BUILD_ROOT.to_string()),
+            (PathBuf::from("/home/ci/.cargo"), CARGO_HOME_ROOT.to_string()),
+        ];
+        let text = "at /home/ci/project/src/lib.rs and /home/ci/.cargo/registry/src/serde/lib.rs";
+        assert_eq!(
+            normalize_text(text, &prefixes),
+            "at /build/src/lib.rs and /cargo/registry/src/serde/lib.rs"
+        );
+    }
+
+    #[test]
+    fn test_manifest_and_snippets() {
+        let dir = std::env::temp_dir().join("rig-wasm-compiler-integrity-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("snippets")).unwrap();
+        fs::write(dir.join("agent.js"), "export {}").unwrap();
+        fs::write(dir.join("agent_bg.wasm"), [0u8, 97, 115, 109]).unwrap();
+        fs::write(dir.join("snippets/inline0.js"), "export const a = 1;").unwrap();
+
+        let manifest = write_manifest(&dir).unwrap();
+        let paths: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["agent.js", "agent_bg.wasm", "snippets/inline0.js"]);
+        // Writing it again hashes the same files: the manifest does not list itself
+        let again = write_manifest(&dir).unwrap();
+        assert_eq!(again.files, manifest.files);
+
+        let snippets = script_snippets(&manifest, Target::Web, "agent");
+        assert_eq!(snippets.len(), 1);
+        assert!(snippets[0].starts_with("<script type=\"module\" src=\"./agent.js\" integrity=\"sha384-"));
+        assert!(script_snippets(&manifest, Target::Nodejs, "agent").is_empty());
+
+        set_timestamps(&dir, 1_700_000_000).unwrap();
+        let modified = fs::metadata(dir.join("agent.js")).unwrap().modified().unwrap();
+        assert_eq!(modified, UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_remap_flags_keep_cargo_config() {
+        let dir = std::env::temp_dir().join("rig-wasm-compiler-rustflags-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join(".cargo")).unwrap();
+        fs::write(dir.join(".cargo/config.toml"), "[build]\nrustflags = [\"--cfg\", \"from_config\"]\n").unwrap();
+        assert!(cargo_config_files(&dir).ends_with(&[fs::canonicalize(&dir).unwrap().join(".cargo/config.toml")]));
+
+        let configs: Vec<toml::Value> =
+            vec![toml::from_str(&fs::read_to_string(dir.join(".cargo/config.toml")).unwrap()).unwrap()];
+        let prefixes = vec![(PathBuf::from("/work/my agent"), BUILD_ROOT.to_string())];
+
+        // build.rustflags would be ignored next to target-level flags, so they are carried over
+        let flags = merge_remap_flags(None, &configs, "wasm32-unknown-unknown", &[], &prefixes);
+        assert_eq!(
+            flags,
+            RemapFlags::Config(
+                "target.wasm32-unknown-unknown.rustflags=[\"--cfg\", \"from_config\", \"--remap-path-prefix=/work/my agent=/build\"]"
+                    .to_string()
+            )
+        );
+        assert!(flags.env().is_none());
+        assert_eq!(flags.cargo_args()[0], "--config");
+
+        // Target-level flags are joined with the --config array by cargo itself
+        let target: toml::Value = toml::from_str(
+            "[target.'cfg(all(target_arch = \"wasm32\", not(unix)))']\nrustflags = [\"-Ctarget-feature=+simd128\"]\n",
+        )
+        .unwrap();
+        let cfg = vec!["target_arch=\"wasm32\"".to_string(), "target_os=\"unknown\"".to_string()];
+        let flags = merge_remap_flags(None, &[configs[0].clone(), target], "wasm32-unknown-unknown", &cfg, &prefixes);
+        assert_eq!(
+            flags,
+            RemapFlags::Config(
+                "target.wasm32-unknown-unknown.rustflags=[\"--remap-path-prefix=/work/my agent=/build\"]".to_string()
+            )
+        );
+
+        // Flags from the environment win over the config files and are extended in place
+        let flags = merge_remap_flags(Some(vec!["-Dwarnings".to_string()]), &configs, "wasm32-unknown-unknown", &[], &prefixes);
+        assert_eq!(
+            flags.env(),
+            Some(("CARGO_ENCODED_RUSTFLAGS", "-Dwarnings\x1f--remap-path-prefix=/work/my agent=/build".to_string()))
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cfg_matches() {
+        let cfg = vec!["target_arch=\"wasm32\"".to_string(), "target_family=\"wasm\"".to_string(), "panic=\"abort\"".to_string()];
+        assert!(cfg_matches("cfg(target_arch = \"wasm32\")", &cfg));
+        assert!(cfg_matches("cfg(all(target_family = \"wasm\", not(unix)))", &cfg));
+        assert!(cfg_matches("cfg(any(unix, target_arch = \"wasm32\"))", &cfg));
+        assert!(!cfg_matches("cfg(unix)", &cfg));
+        assert!(!cfg_matches("cfg(not(target_arch = \"wasm32\"))", &cfg));
+        assert!(!cfg_matches("x86_64-unknown-linux-gnu", &cfg));
+    }
+}
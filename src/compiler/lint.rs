@@ -0,0 +1,728 @@
+use crate::compiler::diagnostics::{Diagnostic, DiagnosticSpan, WASM_TARGET};
+use crate::compiler::error::CompileError;
+use crate::compiler::input::ResolvedInput;
+use clap::ArgEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use syn::visit::{self, Visit};
+
+// What to do about wasm-incompatible code found before the build
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ArgEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintLevel {
+    // Skip the analysis
+    Allow,
+    // Report findings as warnings and build anyway
+    #[default]
+    Warn,
+    // Report findings as errors and stop before cargo runs
+    Deny,
+}
+
+// A path prefix that does not work on wasm32-unknown-unknown
+struct ApiRule {
+    path: &'static [&'static str],
+    message: &'static str,
+    help: &'static str,
+}
+
+// More specific paths come first; the first matching rule wins
+const API_RULES: &[ApiRule] = &[
+    ApiRule {
+        path: &["tokio", "main"],
+        message: "`#[tokio::main]` starts a native runtime, which does not exist in the browser",
+        help: "export an async fn with #[wasm_bindgen], or use #[wasm_bindgen(start)] for setup code",
+    },
+    ApiRule {
+        path: &["tokio"],
+        message: "full `tokio` needs OS threads, sockets and files",
+        help: "use `tokio_with_wasm::alias as tokio`, which runs tasks on the browser event loop",
+    },
+    ApiRule {
+        path: &["std", "thread", "sleep"],
+        message: "`std::thread::sleep` blocks the browser's only thread and panics on wasm32",
+        help: "await `tokio_with_wasm::alias::time::sleep` instead",
+    },
+    ApiRule {
+        path: &["std", "thread"],
+        message: "`std::thread` cannot spawn threads on wasm32-unknown-unknown",
+        help: "use `tokio_with_wasm::alias::spawn` or `wasm_bindgen_futures::spawn_local`",
+    },
+    ApiRule {
+        path: &["std", "fs"],
+        message: "`std::fs` has no file system to read from in the browser",
+        help: "embed files with include_str!/include_bytes!, or fetch them with WasmHttpClient",
+    },
+    ApiRule {
+        path: &["std", "time", "Instant"],
+        message: "`std::time::Instant::now` panics on wasm32-unknown-unknown",
+        help: "use `js_sys::Date::now()` or `web_time::Instant`",
+    },
+    ApiRule {
+        path: &["std", "time", "SystemTime"],
+        message: "`std::time::SystemTime::now` panics on wasm32-unknown-unknown",
+        help: "use `js_sys::Date::now()` or `web_time::SystemTime`",
+    },
+    ApiRule {
+        path: &["std", "net"],
+        message: "`std::net` has no sockets in the browser",
+        help: "make requests with WasmHttpClient, or use web_sys::WebSocket",
+    },
+    ApiRule {
+        path: &["std", "process"],
+        message: "`std::process` cannot spawn processes in the browser",
+        help: "move the work into a server endpoint and call it with WasmHttpClient",
+    },
+    ApiRule {
+        path: &["std", "env", "var"],
+        message: "environment variables are always empty on wasm32-unknown-unknown",
+        help: "pass API keys and settings in through WasmConfig",
+    },
+    ApiRule {
+        path: &["reqwest", "blocking"],
+        message: "`reqwest::blocking` is not available on wasm32",
+        help: "use WasmHttpClient, or the async reqwest::Client",
+    },
+    ApiRule {
+        path: &["reqwest", "Proxy"],
+        message: "`reqwest::Proxy` is not available on wasm32, where the browser picks the proxy",
+        help: "drop the proxy setting; fetch uses the browser's proxy configuration",
+    },
+    ApiRule {
+        path: &["reqwest", "tls"],
+        message: "`reqwest::tls` is not available on wasm32, where the browser handles TLS",
+        help: "drop the TLS settings; the browser validates certificates itself",
+    },
+    ApiRule {
+        path: &["reqwest", "Certificate"],
+        message: "`reqwest::Certificate` is not available on wasm32, where the browser handles TLS",
+        help: "drop the certificate; the browser validates certificates itself",
+    },
+    ApiRule {
+        path: &["reqwest", "Identity"],
+        message: "`reqwest::Identity` is not available on wasm32, where the browser handles TLS",
+        help: "drop the client certificate; the browser handles client authentication",
+    },
+];
+
+// ClientBuilder methods reqwest leaves out on wasm32. They are matched by name,
+// since the receiver's type is not known before the build.
+const METHOD_RULES: &[ApiRule] = &[
+    ApiRule {
+        path: &["danger_accept_invalid_certs"],
+        message: "`ClientBuilder::danger_accept_invalid_certs` is not available on wasm32",
+        help: "the browser validates certificates itself; remove the call",
+    },
+    ApiRule {
+        path: &["danger_accept_invalid_hostnames"],
+        message: "`ClientBuilder::danger_accept_invalid_hostnames` is not available on wasm32",
+        help: "the browser validates certificates itself; remove the call",
+    },
+    ApiRule {
+        path: &["add_root_certificate"],
+        message: "`ClientBuilder::add_root_certificate` is not available on wasm32",
+        help: "the browser validates certificates itself; remove the call",
+    },
+    ApiRule {
+        path: &["use_native_tls"],
+        message: "`ClientBuilder::use_native_tls` is not available on wasm32",
+        help: "the browser handles TLS; remove the call",
+    },
+    ApiRule {
+        path: &["use_rustls_tls"],
+        message: "`ClientBuilder::use_rustls_tls` is not available on wasm32",
+        help: "the browser handles TLS; remove the call",
+    },
+];
+
+// A dependency (or dependency feature) that does not build or run on wasm32
+struct CrateRule {
+    name: &'static str,
+    // Only these features are a problem; empty means the crate itself is
+    features: &'static [&'static str],
+    message: &'static str,
+    help: &'static str,
+}
+
+const CRATE_RULES: &[CrateRule] = &[
+    CrateRule {
+        name: "tokio",
+        features: &["rt-multi-thread", "net", "fs", "process", "signal", "io-std", "full"],
+        message: "needs OS threads, sockets or files",
+        help: "depend on tokio_with_wasm and `use tokio_with_wasm::alias as tokio;`",
+    },
+    CrateRule {
+        name: "reqwest",
+        // reqwest ignores its TLS features on wasm32; a TLS backend that does get
+        // compiled in is caught by the native-tls and openssl-sys rules
+        features: &["blocking"],
+        message: "is not supported in the browser, where requests go through fetch",
+        help: "use WasmHttpClient, or reqwest's async Client",
+    },
+    CrateRule {
+        name: "native-tls",
+        features: &[],
+        message: "links the platform's TLS library",
+        help: "let the browser handle TLS; make requests with WasmHttpClient",
+    },
+    CrateRule {
+        name: "openssl-sys",
+        features: &[],
+        message: "links OpenSSL, which does not build for wasm32",
+        help: "let the browser handle TLS; make requests with WasmHttpClient",
+    },
+    CrateRule {
+        name: "mio",
+        features: &[],
+        message: "polls OS sockets, which do not exist in the browser",
+        help: "use tokio_with_wasm instead of tokio's I/O driver",
+    },
+    CrateRule {
+        name: "socket2",
+        features: &[],
+        message: "wraps OS sockets, which do not exist in the browser",
+        help: "make requests with WasmHttpClient",
+    },
+];
+
+// A problem found by the analysis, before it is turned into a diagnostic
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub code: &'static str,
+    pub message: String,
+    pub help: String,
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Finding {
+    // The finding as a rustc-style diagnostic at `level` ("warning" or "error")
+    pub fn to_diagnostic(&self, level: &str) -> Diagnostic {
+        let file_name = display_path(&self.file);
+        let rendered = format!(
+            "{level}: {message}\n  --> {file}:{line}:{column}\n   = help: {help}\n\n",
+            level = level,
+            message = self.message,
+            file = file_name,
+            line = self.line,
+            column = self.column,
+            help = self.help,
+        );
+
+        Diagnostic {
+            level: level.to_string(),
+            message: self.message.clone(),
+            code: Some(self.code.to_string()),
+            spans: vec![DiagnosticSpan {
+                file_name,
+                line_start: self.line,
+                line_end: self.line,
+                column_start: self.column,
+                column_end: self.column,
+                is_primary: true,
+            }],
+            children: vec![Diagnostic {
+                level: "help".to_string(),
+                message: self.help.clone(),
+                code: None,
+                spans: Vec::new(),
+                children: Vec::new(),
+                rendered: None,
+            }],
+            rendered: Some(rendered),
+        }
+    }
+}
+
+// Paths under the working directory are shown relative to it, like rustc does
+fn display_path(path: &Path) -> String {
+    let relative = std::env::current_dir()
+        .ok()
+        .and_then(|dir| path.strip_prefix(dir).ok().map(Path::to_path_buf));
+    relative.unwrap_or_else(|| path.to_path_buf()).display().to_string()
+}
+
+// `#[test]` (or `#[tokio::test]`), `#[cfg(test)]` and code that is compiled out on wasm never reaches the browser
+pub(crate) fn compiled_out(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        if attr.path.segments.last().is_some_and(|segment| segment.ident == "test") {
+            return true;
+        }
+        if !attr.path.is_ident("cfg") {
+            return false;
+        }
+        let predicate: String = attr.tokens.to_string().chars().filter(|c| !c.is_whitespace()).collect();
+        predicate == "(test)"
+            || predicate.contains("not(target_arch=\"wasm32\")")
+            || predicate.contains("not(target_family=\"wasm\")")
+    })
+}
+
+pub(crate) fn item_attrs(item: &syn::Item) -> &[syn::Attribute] {
+    match item {
+        syn::Item::Const(item) => &item.attrs,
+        syn::Item::Enum(item) => &item.attrs,
+        syn::Item::Fn(item) => &item.attrs,
+        syn::Item::Impl(item) => &item.attrs,
+        syn::Item::Mod(item) => &item.attrs,
+        syn::Item::Static(item) => &item.attrs,
+        syn::Item::Struct(item) => &item.attrs,
+        syn::Item::Trait(item) => &item.attrs,
+        syn::Item::Use(item) => &item.attrs,
+        _ => &[],
+    }
+}
+
+// Names a file binds with `use ... as name`, e.g. `use tokio_with_wasm::alias as tokio;`.
+// Paths starting with these names do not refer to the crate of the same name.
+#[derive(Default)]
+struct Aliases(HashSet<String>);
+
+impl<'ast> Visit<'ast> for Aliases {
+    fn visit_use_rename(&mut self, rename: &'ast syn::UseRename) {
+        self.0.insert(rename.rename.to_string());
+    }
+}
+
+struct ApiVisitor<'a> {
+    file: &'a Path,
+    aliases: HashSet<String>,
+    findings: Vec<Finding>,
+}
+
+impl ApiVisitor<'_> {
+    fn check(&mut self, segments: &[String], span: proc_macro2::Span) {
+        if segments.first().is_none_or(|first| self.aliases.contains(first)) {
+            return;
+        }
+        let rule = API_RULES.iter().find(|rule| {
+            rule.path.len() <= segments.len() && rule.path.iter().zip(segments).all(|(a, b)| a == b)
+        });
+        if let Some(rule) = rule {
+            self.report(rule, span);
+        }
+    }
+
+    fn report(&mut self, rule: &ApiRule, span: proc_macro2::Span) {
+        let start = span.start();
+        let finding = Finding {
+            code: "wasm-incompatible-api",
+            message: rule.message.to_string(),
+            help: rule.help.to_string(),
+            file: self.file.to_path_buf(),
+            line: start.line,
+            column: start.column + 1,
+        };
+        if !self.findings.contains(&finding) {
+            self.findings.push(finding);
+        }
+    }
+
+    fn check_use_tree(&mut self, prefix: &mut Vec<String>, tree: &syn::UseTree) {
+        match tree {
+            syn::UseTree::Path(path) => {
+                prefix.push(path.ident.to_string());
+                self.check_use_tree(prefix, &path.tree);
+                prefix.pop();
+            }
+            syn::UseTree::Name(name) => {
+                prefix.push(name.ident.to_string());
+                self.check(prefix, name.ident.span());
+                prefix.pop();
+            }
+            syn::UseTree::Rename(rename) => {
+                prefix.push(rename.ident.to_string());
+                self.check(prefix, rename.ident.span());
+                prefix.pop();
+            }
+            syn::UseTree::Glob(glob) => self.check(prefix, glob.star_token.spans[0]),
+            syn::UseTree::Group(group) => {
+                for tree in &group.items {
+                    self.check_use_tree(prefix, tree);
+                }
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for ApiVisitor<'_> {
+    fn visit_item(&mut self, item: &'ast syn::Item) {
+        if !compiled_out(item_attrs(item)) {
+            visit::visit_item(self, item);
+        }
+    }
+
+    fn visit_impl_item_method(&mut self, method: &'ast syn::ImplItemMethod) {
+        if !compiled_out(&method.attrs) {
+            visit::visit_impl_item_method(self, method);
+        }
+    }
+
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        self.check_use_tree(&mut Vec::new(), &item.tree);
+    }
+
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        let segments: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+        if let Some(first) = path.segments.first() {
+            self.check(&segments, first.ident.span());
+        }
+        visit::visit_path(self, path);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        let method = call.method.to_string();
+        if let Some(rule) = METHOD_RULES.iter().find(|rule| rule.path[0] == method) {
+            self.report(rule, call.method.span());
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+}
+
+// Checks one source file. Files that do not parse are skipped, since cargo
+// reports those with better messages.
+pub fn lint_source(file: &Path, source: &str) -> Vec<Finding> {
+    let syntax = match syn::parse_file(source) {
+        Ok(syntax) => syntax,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut aliases = Aliases::default();
+    aliases.visit_file(&syntax);
+
+    let mut visitor = ApiVisitor {
+        file,
+        aliases: aliases.0,
+        findings: Vec::new(),
+    };
+    visitor.visit_file(&syntax);
+    visitor.findings
+}
+
+fn rust_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), CompileError> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            rust_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// Checks the crate's library sources: the wrapped file for a bare .rs input,
+// otherwise everything under src/
+pub fn lint_sources(resolved: &ResolvedInput) -> Result<Vec<Finding>, CompileError> {
+    let mut files = Vec::new();
+    match &resolved.source_file {
+        Some(source_file) => files.push(source_file.clone()),
+        None => {
+            let src = resolved.crate_dir.join("src");
+            if src.is_dir() {
+                rust_files(&src, &mut files)?;
+            }
+        }
+    }
+    files.sort();
+
+    let mut findings = Vec::new();
+    for file in files {
+        let source = fs::read_to_string(&file)?;
+        findings.extend(lint_source(&file, &source));
+    }
+    Ok(findings)
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<MetadataPackage>,
+    resolve: Option<Resolve>,
+}
+
+#[derive(Deserialize)]
+struct MetadataPackage {
+    id: String,
+    name: String,
+    version: String,
+    manifest_path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct Resolve {
+    nodes: Vec<ResolveNode>,
+}
+
+#[derive(Deserialize)]
+struct ResolveNode {
+    id: String,
+    #[serde(default)]
+    deps: Vec<NodeDep>,
+    #[serde(default)]
+    features: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct NodeDep {
+    pkg: String,
+    #[serde(default)]
+    dep_kinds: Vec<DepKind>,
+}
+
+#[derive(Deserialize)]
+struct DepKind {
+    kind: Option<String>,
+}
+
+impl NodeDep {
+    // Dev and build dependencies never end up in the .wasm
+    fn is_normal(&self) -> bool {
+        self.dep_kinds.is_empty() || self.dep_kinds.iter().any(|kind| kind.kind.is_none())
+    }
+}
+
+// The line of Cargo.toml that declares `name`, so crate findings point somewhere useful
+fn dependency_line(manifest: &str, name: &str) -> usize {
+    manifest
+        .lines()
+        .position(|line| {
+            let line = line.trim();
+            line.strip_prefix(name).is_some_and(|rest| rest.trim_start().starts_with('='))
+                || line.ends_with(&format!("dependencies.{}]", name))
+        })
+        .map_or(1, |index| index + 1)
+}
+
+// Checks the resolved wasm32 dependency graph against CRATE_RULES. Needs
+// `cargo metadata`, which may have to fetch the index for new lock files.
+pub fn scan_dependencies(resolved: &ResolvedInput) -> Result<Vec<Finding>, CompileError> {
+    let output = Command::new("cargo")
+        .args(["metadata", "--format-version", "1", "--filter-platform", WASM_TARGET, "--manifest-path"])
+        .arg(&resolved.manifest_path)
+        .output()
+        .map_err(|e| CompileError::spawn("cargo", "https://rustup.rs", e))?;
+    if !output.status.success() {
+        return Err(CompileError::Cargo {
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            diagnostics: Vec::new(),
+        });
+    }
+    let metadata: Metadata = serde_json::from_slice(&output.stdout)
+        .map_err(|e| CompileError::InvalidInput(format!("Unexpected cargo metadata output: {}", e)))?;
+    let manifest = fs::read_to_string(&resolved.manifest_path)?;
+    Ok(check_dependencies(&metadata, resolved, &manifest))
+}
+
+fn check_dependencies(metadata: &Metadata, resolved: &ResolvedInput, manifest: &str) -> Vec<Finding> {
+    let packages: HashMap<&str, &MetadataPackage> = metadata.packages.iter().map(|p| (p.id.as_str(), p)).collect();
+    let nodes: HashMap<&str, &ResolveNode> = match &metadata.resolve {
+        Some(resolve) => resolve.nodes.iter().map(|n| (n.id.as_str(), n)).collect(),
+        None => return Vec::new(),
+    };
+    let root = metadata
+        .packages
+        .iter()
+        .find(|p| p.manifest_path == resolved.manifest_path || p.name == resolved.package_name);
+    let root = match root {
+        Some(root) => root.id.as_str(),
+        None => return Vec::new(),
+    };
+
+    // Walk normal dependencies breadth-first, remembering which direct
+    // dependency pulled each crate in
+    let mut introduced_by: HashMap<&str, &str> = HashMap::new();
+    let mut queue = VecDeque::new();
+    if let Some(node) = nodes.get(root) {
+        for dep in node.deps.iter().filter(|dep| dep.is_normal()) {
+            introduced_by.insert(dep.pkg.as_str(), dep.pkg.as_str());
+            queue.push_back(dep.pkg.as_str());
+        }
+    }
+    while let Some(id) = queue.pop_front() {
+        let direct = introduced_by[id];
+        if let Some(node) = nodes.get(id) {
+            for dep in node.deps.iter().filter(|dep| dep.is_normal()) {
+                if !introduced_by.contains_key(dep.pkg.as_str()) {
+                    introduced_by.insert(dep.pkg.as_str(), direct);
+                    queue.push_back(dep.pkg.as_str());
+                }
+            }
+        }
+    }
+
+    let mut reached: Vec<&str> = introduced_by.keys().copied().collect();
+    reached.sort();
+
+    let mut findings = Vec::new();
+    for id in reached {
+        let (package, node) = match (packages.get(id), nodes.get(id)) {
+            (Some(package), Some(node)) => (package, node),
+            _ => continue,
+        };
+        let direct = packages.get(introduced_by[id]).map_or(package.name.as_str(), |p| p.name.as_str());
+        let via = if direct == package.name {
+            String::new()
+        } else {
+            format!(" (pulled in by `{}`)", direct)
+        };
+
+        let mut problems = Vec::new();
+        for rule in CRATE_RULES.iter().filter(|rule| rule.name == package.name) {
+            if rule.features.is_empty() {
+                problems.push((format!("`{}` {}{}", package.name, rule.message, via), rule.help.to_string()));
+            }
+            for feature in rule.features.iter().filter(|f| node.features.iter().any(|enabled| enabled == *f)) {
+                problems.push((
+                    format!("`{}` feature `{}` {}{}", package.name, feature, rule.message, via),
+                    rule.help.to_string(),
+                ));
+            }
+        }
+        // getrandom 0.2 only finds the browser's crypto API with its js feature
+        if package.name == "getrandom" && package.version.starts_with("0.2") && !node.features.iter().any(|f| f == "js") {
+            problems.push((
+                format!("`getrandom` {} has no entropy source on wasm32 without its `js` feature{}", package.version, via),
+                "add `getrandom = { version = \"0.2\", features = [\"js\"] }` to [dependencies]".to_string(),
+            ));
+        }
+
+        for (message, help) in problems {
+            findings.push(Finding {
+                code: "wasm-incompatible-crate",
+                message,
+                help,
+                file: resolved.manifest_path.clone(),
+                line: dependency_line(manifest, direct),
+                column: 1,
+            });
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_source() {
+        let source = r#"
+use std::fs;
+use std::time::{Duration, Instant};
+use tokio_with_wasm::alias as tokio;
+
+#[wasm_bindgen]
+pub async fn run() {
+    std::thread::spawn(|| {});
+    tokio::spawn(async {});
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn native_only() {
+    let _ = std::net::TcpStream::connect("localhost:80");
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn it_works() {}
+}
+"#;
+        let findings = lint_source(Path::new("src/lib.rs"), source);
+        let found: Vec<(usize, &str)> = findings.iter().map(|f| (f.line, f.message.as_str())).collect();
+
+        assert_eq!(found.len(), 3, "{:?}", found);
+        assert_eq!(found[0].0, 2);
+        assert!(found[0].1.contains("std::fs"));
+        assert_eq!(found[1].0, 3);
+        assert!(found[1].1.contains("Instant"));
+        assert_eq!(found[2].0, 8);
+        assert!(found[2].1.contains("std::thread"));
+    }
+
+    #[test]
+    fn test_tokio_main_is_flagged() {
+        let findings = lint_source(Path::new("src/main.rs"), "#[tokio::main]\nasync fn main() {}\n");
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].message.contains("tokio::main"));
+
+        let diagnostic = findings[0].to_diagnostic("error");
+        assert!(diagnostic.is_error());
+        assert_eq!(diagnostic.code.as_deref(), Some("wasm-incompatible-api"));
+        assert!(diagnostic.rendered.unwrap().contains("src/main.rs:1:3"));
+    }
+
+    #[test]
+    fn test_reqwest_rules() {
+        let source = r#"
+use reqwest::Client;
+
+pub async fn fetch(url: &str) -> reqwest::Result<String> {
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()?;
+    let _ = reqwest::blocking::get(url);
+    client.get(url).send().await?.text().await
+}
+"#;
+        let findings = lint_source(Path::new("src/lib.rs"), source);
+        let found: Vec<(usize, &str)> = findings.iter().map(|f| (f.line, f.message.as_str())).collect();
+
+        // The async client works on wasm32; only the unsupported parts are flagged
+        assert_eq!(found.len(), 2, "{:?}", found);
+        assert_eq!(found[0].0, 6);
+        assert!(found[0].1.contains("danger_accept_invalid_certs"));
+        assert_eq!(found[1].0, 8);
+        assert!(found[1].1.contains("reqwest::blocking"));
+    }
+
+    #[test]
+    fn test_check_dependencies() {
+        let metadata: Metadata = serde_json::from_value(serde_json::json!({
+            "packages": [
+                { "id": "app", "name": "app", "version": "0.1.0", "manifest_path": "/app/Cargo.toml" },
+                { "id": "rig", "name": "rig-core", "version": "0.1.0", "manifest_path": "/rig/Cargo.toml" },
+                { "id": "reqwest", "name": "reqwest", "version": "0.11.0", "manifest_path": "/r/Cargo.toml" },
+                { "id": "tokio", "name": "tokio", "version": "1.0.0", "manifest_path": "/t/Cargo.toml" },
+                { "id": "getrandom", "name": "getrandom", "version": "0.2.10", "manifest_path": "/g/Cargo.toml" },
+                { "id": "criterion", "name": "mio", "version": "0.8.0", "manifest_path": "/m/Cargo.toml" },
+            ],
+            "resolve": { "nodes": [
+                { "id": "app", "deps": [
+                    { "pkg": "rig", "dep_kinds": [{ "kind": null }] },
+                    { "pkg": "tokio", "dep_kinds": [{ "kind": null }] },
+                    { "pkg": "criterion", "dep_kinds": [{ "kind": "dev" }] },
+                ] },
+                { "id": "rig", "deps": [{ "pkg": "reqwest", "dep_kinds": [{ "kind": null }] }] },
+                { "id": "reqwest", "features": ["blocking", "default-tls", "json"],
+                  "deps": [{ "pkg": "getrandom", "dep_kinds": [{ "kind": null }] }] },
+                { "id": "tokio", "features": ["macros", "rt-multi-thread"] },
+                { "id": "getrandom", "features": ["std"] },
+                { "id": "criterion" },
+            ] },
+        }))
+        .unwrap();
+
+        let resolved = ResolvedInput {
+            crate_dir: "/app".into(),
+            manifest_path: "/app/Cargo.toml".into(),
+            package_name: "app".to_string(),
+            generated: false,
+            source_file: None,
+        };
+        let manifest = "[package]\nname = \"app\"\n\n[dependencies]\nrig-core = \"0.1\"\ntokio = { version = \"1\" }\n";
+        let findings = check_dependencies(&metadata, &resolved, manifest);
+        let found: Vec<(usize, &str)> = findings.iter().map(|f| (f.line, f.message.as_str())).collect();
+
+        assert_eq!(found.len(), 3, "{:?}", found);
+        assert!(found[0].1.contains("`getrandom`") && found[0].1.contains("pulled in by `rig-core`"));
+        assert_eq!(found[0].0, 5);
+        assert!(found[1].1.contains("`reqwest` feature `blocking`"));
+        assert!(found[2].1.contains("`tokio` feature `rt-multi-thread`"));
+        assert_eq!(found[2].0, 6);
+    }
+}
@@ -0,0 +1,543 @@
+use crate::compiler::budget::SizeBudget;
+use crate::compiler::error::CompileError;
+use crate::compiler::input::entry_name;
+use crate::compiler::lint::LintLevel;
+use crate::compiler::secrets::SecretPolicy;
+use crate::compiler::optimize::OptLevel;
+use crate::compiler::preset::Preset;
+use crate::compiler::target::Target;
+use crate::compiler::{CompileOptions, Profile};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const MANIFEST_FILE: &str = "rig-wasm.toml";
+
+// One of several crates or examples built into the same package
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Entry {
+    pub input: String,
+    // Defaults to the file stem or crate directory name
+    pub out_name: Option<String>,
+}
+
+impl Entry {
+    pub fn new(input: impl Into<String>) -> Self {
+        Entry {
+            input: input.into(),
+            out_name: None,
+        }
+    }
+
+    pub fn out_name(&self) -> String {
+        self.out_name.clone().unwrap_or_else(|| entry_name(&self.input))
+    }
+}
+
+// npm package fields for `pack`. Unset fields come from the crate's Cargo.toml.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PackageMetadata {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub license: Option<String>,
+    pub repository: Option<String>,
+    pub homepage: Option<String>,
+    pub keywords: Vec<String>,
+}
+
+impl PackageMetadata {
+    // Fields set here win over `fallback`
+    pub fn or(self, fallback: PackageMetadata) -> PackageMetadata {
+        PackageMetadata {
+            name: self.name.or(fallback.name),
+            version: self.version.or(fallback.version),
+            description: self.description.or(fallback.description),
+            license: self.license.or(fallback.license),
+            repository: self.repository.or(fallback.repository),
+            homepage: self.homepage.or(fallback.homepage),
+            keywords: if self.keywords.is_empty() { fallback.keywords } else { self.keywords },
+        }
+    }
+}
+
+// Build settings read from rig-wasm.toml or `[package.metadata.rig-wasm]`.
+// Every field is optional; CLI flags and builder calls are merged on top.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ProjectManifest {
+    pub input: Option<String>,
+    // Several inputs sharing one output directory and index; replaces `input`
+    pub entries: Vec<Entry>,
+    pub output: Option<PathBuf>,
+    // Each target is built into its own subdirectory when more than one is listed
+    pub targets: Vec<Target>,
+    pub out_name: Option<String>,
+    pub features: Vec<String>,
+    pub no_default_features: Option<bool>,
+    pub typescript: Option<bool>,
+    pub profile: Option<Profile>,
+    // `--profile size|speed|debug` on the command line
+    pub preset: Option<Preset>,
+    // Keep DWARF and the name section and write a symbol map; on with the debug preset
+    pub debug_info: Option<bool>,
+    pub opt_level: Option<OptLevel>,
+    pub strip_names: Option<bool>,
+    pub strip_custom_sections: Option<bool>,
+    pub budget: Option<SizeBudget>,
+    // WasmConfig file to embed into the module
+    pub config: Option<PathBuf>,
+    pub config_secrets: Option<SecretPolicy>,
+    pub lint: Option<LintLevel>,
+    pub shim: Option<bool>,
+    pub emit_shimmed_source: Option<bool>,
+    pub cache: Option<bool>,
+    // Remap build paths, pin file times and write manifest.json with SRI hashes
+    pub reproducible: Option<bool>,
+    // ed25519 key (PKCS#8 PEM or hex seed) to sign manifest.json with
+    pub sign_key: Option<PathBuf>,
+    // Write a Content-Security-Policy as _headers and an nginx snippet
+    pub emit_headers: Option<bool>,
+    // Write a Web Worker entry and a main-thread proxy for the exports
+    pub emit_worker: Option<bool>,
+    pub npm: Option<PackageMetadata>,
+}
+
+impl ProjectManifest {
+    // Reads a rig-wasm.toml, or the `[package.metadata.rig-wasm]` table when
+    // `path` is a Cargo.toml. Relative paths are resolved against the file's directory.
+    pub fn from_file(path: &Path) -> Result<Self, CompileError> {
+        let contents = fs::read_to_string(path)?;
+        let invalid = |e: toml::de::Error| {
+            CompileError::InvalidInput(format!("Invalid build settings in {}: {}", path.display(), e))
+        };
+
+        let manifest = if path.file_name().is_some_and(|name| name == "Cargo.toml") {
+            let cargo: toml::Value = toml::from_str(&contents).map_err(invalid)?;
+            match cargo.get("package").and_then(|p| p.get("metadata")).and_then(|m| m.get("rig-wasm")) {
+                Some(table) => table.clone().try_into().map_err(invalid)?,
+                None => ProjectManifest::default(),
+            }
+        } else {
+            toml::from_str(&contents).map_err(invalid)?
+        };
+
+        let base = path.parent().unwrap_or_else(|| Path::new("."));
+        Ok(manifest.relative_to(base))
+    }
+
+    // Looks for rig-wasm.toml in `dir`, then for a rig-wasm table in `dir`/Cargo.toml
+    pub fn discover(dir: &Path) -> Result<Option<Self>, CompileError> {
+        let manifest_file = dir.join(MANIFEST_FILE);
+        if manifest_file.is_file() {
+            return Self::from_file(&manifest_file).map(Some);
+        }
+
+        let cargo_toml = dir.join("Cargo.toml");
+        if cargo_toml.is_file() {
+            let manifest = Self::from_file(&cargo_toml)?;
+            if manifest != ProjectManifest::default() {
+                return Ok(Some(manifest));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn relative_to(mut self, base: &Path) -> Self {
+        if base.as_os_str().is_empty() {
+            return self;
+        }
+        // Workspace member names are not paths and stay as they are
+        let resolve = |input: &mut String| {
+            let candidate = base.join(&*input);
+            if candidate.exists() {
+                *input = candidate.to_string_lossy().into_owned();
+            }
+        };
+        if let Some(input) = &mut self.input {
+            resolve(input);
+        }
+        for entry in &mut self.entries {
+            resolve(&mut entry.input);
+        }
+        self.output = self.output.map(|output| base.join(output));
+        self.config = self.config.map(|config| base.join(config));
+        self.sign_key = self.sign_key.map(|key| base.join(key));
+        self
+    }
+
+    // Overlays `overrides` on top of these settings. Set fields win; lists
+    // replace rather than extend.
+    pub fn merge(self, overrides: ProjectManifest) -> ProjectManifest {
+        // An input given on the command line replaces the manifest's entries too
+        let (input, entries) = if overrides.input.is_some() || !overrides.entries.is_empty() {
+            (overrides.input, overrides.entries)
+        } else {
+            (self.input, self.entries)
+        };
+
+        ProjectManifest {
+            input,
+            entries,
+            output: overrides.output.or(self.output),
+            targets: if overrides.targets.is_empty() { self.targets } else { overrides.targets },
+            out_name: overrides.out_name.or(self.out_name),
+            features: if overrides.features.is_empty() { self.features } else { overrides.features },
+            no_default_features: overrides.no_default_features.or(self.no_default_features),
+            typescript: overrides.typescript.or(self.typescript),
+            profile: overrides.profile.or(self.profile),
+            preset: overrides.preset.or(self.preset),
+            debug_info: overrides.debug_info.or(self.debug_info),
+            opt_level: overrides.opt_level.or(self.opt_level),
+            strip_names: overrides.strip_names.or(self.strip_names),
+            strip_custom_sections: overrides.strip_custom_sections.or(self.strip_custom_sections),
+            budget: overrides.budget.or(self.budget),
+            config: overrides.config.or(self.config),
+            config_secrets: overrides.config_secrets.or(self.config_secrets),
+            lint: overrides.lint.or(self.lint),
+            shim: overrides.shim.or(self.shim),
+            emit_shimmed_source: overrides.emit_shimmed_source.or(self.emit_shimmed_source),
+            cache: overrides.cache.or(self.cache),
+            reproducible: overrides.reproducible.or(self.reproducible),
+            sign_key: overrides.sign_key.or(self.sign_key),
+            emit_headers: overrides.emit_headers.or(self.emit_headers),
+            emit_worker: overrides.emit_worker.or(self.emit_worker),
+            npm: overrides.npm.or(self.npm),
+        }
+    }
+
+    // The inputs to build, with the module name each is written under
+    pub fn entries(&self) -> Vec<(String, Option<String>)> {
+        if self.entries.is_empty() {
+            let input = self.input.clone().unwrap_or_else(|| ".".to_string());
+            return vec![(input, self.out_name.clone())];
+        }
+        self.entries
+            .iter()
+            .map(|entry| (entry.input.clone(), Some(entry.out_name())))
+            .collect()
+    }
+
+    // Rejects entry lists that cannot share one package
+    pub fn validate(&self) -> Result<(), CompileError> {
+        if self.entries.len() < 2 {
+            return Ok(());
+        }
+        if let Some(out_name) = &self.out_name {
+            return Err(CompileError::InvalidInput(format!(
+                "out-name {} would name every one of {} entries; set out-name on each entry instead",
+                out_name,
+                self.entries.len()
+            )));
+        }
+        if self.targets.contains(&Target::NoModules) {
+            return Err(CompileError::InvalidInput(
+                "no-modules output defines a global `wasm_bindgen` per module, so several entries cannot share a package"
+                    .to_string(),
+            ));
+        }
+        let mut names: Vec<String> = self.entries.iter().map(Entry::out_name).collect();
+        names.sort();
+        if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(CompileError::InvalidInput(format!(
+                "Several entries are named {}; set out-name on them to tell them apart",
+                pair[0]
+            )));
+        }
+        Ok(())
+    }
+
+    // One set of options per target and entry. The input defaults to the current
+    // crate and the output to `pkg`, like wasm-pack. Entries of the same target
+    // share an output directory.
+    pub fn compile_options(&self) -> Vec<CompileOptions> {
+        let entries = self.entries();
+        let output = self.output.clone().unwrap_or_else(|| PathBuf::from("pkg"));
+        let targets = if self.targets.is_empty() { vec![Target::default()] } else { self.targets.clone() };
+        // A preset picks the wasm-opt level and cargo profile unless they are set explicitly
+        let settings = self.preset.map(|preset| preset.settings());
+        let opt_level = self.opt_level.or(settings.as_ref().map(|s| s.wasm_opt)).unwrap_or_default();
+
+        // Optimization level 0 builds with the dev profile unless a profile is given
+        let default_profile = if opt_level == OptLevel::O0 { Profile::Dev } else { Profile::Release };
+        let profile = self.profile.or(settings.as_ref().map(|s| s.cargo_profile)).unwrap_or(default_profile);
+
+        let package_entries: Vec<String> = match entries.len() {
+            1 => Vec::new(),
+            _ => entries.iter().filter_map(|(_, out_name)| out_name.clone()).collect(),
+        };
+
+        targets
+            .iter()
+            .flat_map(|&target| entries.iter().map(move |entry| (target, entry)))
+            .map(|(target, (input, out_name))| {
+                let output_dir = if targets.len() > 1 { output.join(target.as_str()) } else { output.clone() };
+                let mut options = CompileOptions::new(input.clone(), output_dir)
+                    .with_target(target)
+                    .with_profile(profile)
+                    .with_opt_level(opt_level)
+                    .with_features(self.features.iter().cloned())
+                    .with_no_default_features(self.no_default_features.unwrap_or(false))
+                    .with_typescript(self.typescript.unwrap_or(false))
+                    .with_strip_names(self.strip_names.unwrap_or(false))
+                    .with_strip_custom_sections(self.strip_custom_sections.unwrap_or(false))
+                    .with_budget(self.budget.clone().unwrap_or_default())
+                    .with_config_secrets(self.config_secrets.unwrap_or_default())
+                    .with_lint(self.lint.unwrap_or_default())
+                    .with_shim(self.shim.unwrap_or(false))
+                    .with_emit_shimmed_source(self.emit_shimmed_source.unwrap_or(false))
+                    .with_cache(self.cache.unwrap_or(true))
+                    .with_reproducible(self.reproducible.unwrap_or(false))
+                    .with_emit_headers(self.emit_headers.unwrap_or(false))
+                    .with_emit_worker(self.emit_worker.unwrap_or(false))
+                    .with_package_entries(package_entries.iter().cloned())
+                    .with_debug_info(self.debug_info.unwrap_or(self.preset == Some(Preset::Debug)));
+                if let Some(preset) = self.preset {
+                    options = options.with_preset(preset);
+                }
+                if let Some(out_name) = out_name {
+                    options = options.with_out_name(out_name.clone());
+                }
+                if let Some(config) = &self.config {
+                    options = options.with_config(config.clone());
+                }
+                if let Some(sign_key) = &self.sign_key {
+                    options = options.with_sign_key(sign_key.clone());
+                }
+                options
+            })
+            .collect()
+    }
+}
+
+// Where the build settings for `input` are looked for: its crate directory, or
+// for a bare .rs file the nearest directory above it with rig-wasm.toml or a
+// Cargo.toml. Workspace member names, and no input, use the current directory.
+pub fn project_dir(input: Option<&str>) -> PathBuf {
+    let path = match input {
+        Some(input) => Path::new(input),
+        None => return PathBuf::from("."),
+    };
+    if path.is_dir() {
+        return path.to_path_buf();
+    }
+    if !path.is_file() {
+        return PathBuf::from(".");
+    }
+
+    let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or_else(|| Path::new("."));
+    if path.extension().is_some_and(|ext| ext == "rs") {
+        for dir in parent.ancestors() {
+            let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+            if dir.join(MANIFEST_FILE).is_file() || dir.join("Cargo.toml").is_file() {
+                return dir.to_path_buf();
+            }
+        }
+    }
+    parent.to_path_buf()
+}
+
+// Discovers the project manifest in `dir` (or reads `manifest_path`) and
+// merges `overrides` on top
+pub fn load_manifest(
+    dir: &Path,
+    manifest_path: Option<&Path>,
+    overrides: ProjectManifest,
+) -> Result<ProjectManifest, CompileError> {
+    let manifest = match manifest_path {
+        Some(path) if !path.is_file() => return Err(CompileError::InputNotFound(path.display().to_string())),
+        Some(path) => ProjectManifest::from_file(path)?,
+        None => ProjectManifest::discover(dir)?.unwrap_or_default(),
+    };
+    let manifest = manifest.merge(overrides);
+    manifest.validate()?;
+    Ok(manifest)
+}
+
+// The options for every target and entry of the resolved manifest. The CLI
+// goes through here, and embedders should too.
+pub fn resolve_options(
+    dir: &Path,
+    manifest_path: Option<&Path>,
+    overrides: ProjectManifest,
+) -> Result<Vec<CompileOptions>, CompileError> {
+    load_manifest(dir, manifest_path, overrides).map(|manifest| manifest.compile_options())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest: ProjectManifest = toml::from_str(
+            r#"
+            input = "examples/rag_agent.rs"
+            targets = ["web", "nodejs"]
+            out-name = "rag_agent"
+            features = ["openai"]
+            opt-level = "z"
+            config = "rig.json"
+
+            [budget.total]
+            gzip = "250 KiB"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.targets, vec![Target::Web, Target::Nodejs]);
+        assert_eq!(manifest.opt_level, Some(OptLevel::Oz));
+        assert_eq!(manifest.budget.unwrap().total.gzip, Some(250 * 1024));
+
+        assert!(toml::from_str::<ProjectManifest>("unknown-key = 1").is_err());
+    }
+
+    #[test]
+    fn test_overrides_win() {
+        let file = ProjectManifest {
+            input: Some("examples/rag_agent.rs".into()),
+            targets: vec![Target::Web, Target::Bundler],
+            out_name: Some("rag_agent".into()),
+            typescript: Some(true),
+            ..Default::default()
+        };
+        let cli = ProjectManifest {
+            targets: vec![Target::Nodejs],
+            opt_level: Some(OptLevel::O0),
+            ..Default::default()
+        };
+
+        let options = file.merge(cli).compile_options();
+        assert_eq!(options.len(), 1);
+        assert_eq!(options[0].target, Target::Nodejs);
+        assert_eq!(options[0].out_name, "rag_agent");
+        assert_eq!(options[0].profile, Profile::Dev);
+        assert!(options[0].typescript);
+    }
+
+    #[test]
+    fn test_preset_yields_to_explicit_settings() {
+        let manifest = ProjectManifest { preset: Some(Preset::Size), ..Default::default() };
+        let options = manifest.compile_options();
+        assert_eq!(options[0].preset, Some(Preset::Size));
+        assert_eq!(options[0].optimize.level, OptLevel::Oz);
+        assert_eq!(options[0].profile, Profile::Release);
+
+        let manifest = ProjectManifest {
+            preset: Some(Preset::Debug),
+            opt_level: Some(OptLevel::O1),
+            ..Default::default()
+        };
+        let options = manifest.compile_options();
+        assert_eq!(options[0].optimize.level, OptLevel::O1);
+        assert_eq!(options[0].profile, Profile::Dev);
+    }
+
+    #[test]
+    fn test_multiple_targets_get_subdirectories() {
+        let manifest = ProjectManifest {
+            output: Some("dist".into()),
+            targets: vec![Target::Web, Target::Nodejs],
+            ..Default::default()
+        };
+        let dirs: Vec<PathBuf> = manifest.compile_options().into_iter().map(|o| o.output_dir).collect();
+        assert_eq!(dirs, vec![PathBuf::from("dist/web"), PathBuf::from("dist/nodejs")]);
+    }
+
+    #[test]
+    fn test_entries_share_output_dir() {
+        let manifest: ProjectManifest = toml::from_str(
+            r#"
+            output = "dist"
+            targets = ["web", "nodejs"]
+
+            [[entries]]
+            input = "examples/simple_agent.rs"
+
+            [[entries]]
+            input = "examples/rag_agent.rs"
+            out-name = "rag"
+            "#,
+        )
+        .unwrap();
+        assert!(manifest.validate().is_ok());
+
+        let options = manifest.compile_options();
+        let built: Vec<(&str, &str)> = options.iter().map(|o| (o.target.as_str(), o.out_name.as_str())).collect();
+        assert_eq!(
+            built,
+            vec![("web", "simple_agent"), ("web", "rag"), ("nodejs", "simple_agent"), ("nodejs", "rag")]
+        );
+        assert_eq!(options[0].output_dir, options[1].output_dir);
+        assert_eq!(options[3].package_entries, vec!["simple_agent", "rag"]);
+
+        // A single input from the command line replaces the manifest's entries
+        let cli = ProjectManifest {
+            input: Some("examples/simple_agent.rs".into()),
+            ..Default::default()
+        };
+        let single = manifest.merge(cli).compile_options();
+        assert_eq!(single.len(), 2);
+        assert!(single[0].package_entries.is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_entry_names_rejected() {
+        let manifest = ProjectManifest {
+            entries: vec![Entry::new("a/agent.rs"), Entry::new("b/agent.rs")],
+            ..Default::default()
+        };
+        assert_eq!(manifest.validate().unwrap_err().kind(), "invalid-input");
+
+        // One out-name cannot name several entries
+        let manifest = ProjectManifest {
+            entries: vec![Entry::new("a/simple.rs"), Entry::new("b/rag.rs")],
+            out_name: Some("agent".into()),
+            ..Default::default()
+        };
+        assert_eq!(manifest.validate().unwrap_err().kind(), "invalid-input");
+    }
+
+    #[test]
+    fn test_cargo_metadata_table() {
+        let dir = std::env::temp_dir().join("rig-wasm-compiler-manifest-test");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[package.metadata.rig-wasm]\noutput = \"dist\"\ntypescript = true\n",
+        )
+        .unwrap();
+
+        let manifest = ProjectManifest::discover(&dir).unwrap().unwrap();
+        assert_eq!(manifest.output, Some(dir.join("dist")));
+        assert_eq!(manifest.typescript, Some(true));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_project_dir() {
+        let dir = std::env::temp_dir().join("rig-wasm-compiler-project-dir-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("examples")).unwrap();
+        fs::write(dir.join(MANIFEST_FILE), "typescript = true\n").unwrap();
+        let example = dir.join("examples/agent.rs");
+        fs::write(&example, "").unwrap();
+
+        // A bare .rs file finds the settings of the project it sits in
+        assert_eq!(project_dir(example.to_str()), dir);
+        assert_eq!(project_dir(dir.to_str()), dir);
+        assert_eq!(project_dir(Some("some-member")), PathBuf::from("."));
+        assert_eq!(project_dir(None), PathBuf::from("."));
+
+        // A setting switched on in the file can be switched off by an override
+        let overrides = ProjectManifest { typescript: Some(false), ..Default::default() };
+        let manifest = load_manifest(&project_dir(example.to_str()), None, overrides).unwrap();
+        assert_eq!(manifest.typescript, Some(false));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
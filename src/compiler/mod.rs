@@ -0,0 +1,859 @@
+pub mod budget;
+pub mod cache;
+pub mod csp;
+pub mod diagnostics;
+pub mod error;
+pub mod input;
+pub mod integrity;
+pub mod inspect;
+pub mod lint;
+pub mod manifest;
+pub mod optimize;
+pub mod package;
+pub mod preset;
+pub mod progress;
+pub mod report;
+pub mod secrets;
+pub mod shim;
+pub mod signing;
+pub mod symbols;
+pub mod target;
+pub mod toolchain;
+pub mod wasm_module;
+pub mod worker;
+
+use crate::compiler::budget::SizeBudget;
+use crate::compiler::error::CompileError;
+use crate::compiler::input::ResolvedInput;
+use crate::compiler::integrity::IntegrityReport;
+use crate::compiler::lint::LintLevel;
+use crate::compiler::manifest::ProjectManifest;
+use crate::compiler::optimize::{OptLevel, OptimizeOptions, OptimizeReport};
+use crate::compiler::package::PackageOutput;
+use crate::compiler::preset::{Preset, PresetReport};
+use crate::compiler::progress::{CompileObserver, NoopObserver, Phase};
+use crate::compiler::report::{collect_artifacts, CompileReport};
+use crate::compiler::secrets::{EmbeddedConfig, SecretPolicy};
+use crate::compiler::shim::ShimReport;
+use crate::compiler::target::Target;
+use clap::ArgEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+
+// The WIT world WASI preview 2 components export, written next to components
+// that export its interface
+pub const AGENT_WIT: &str = include_str!("../../wit/world.wit");
+pub const AGENT_INTERFACE: &str = "rig:agent/agent";
+
+// The cargo profile wasm-pack builds with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ArgEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Profile {
+    Dev,
+    Profiling,
+    #[default]
+    Release,
+}
+
+impl Profile {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Profile::Dev => "dev",
+            Profile::Profiling => "profiling",
+            Profile::Release => "release",
+        }
+    }
+
+    fn wasm_pack_flag(&self) -> &'static str {
+        match self {
+            Profile::Dev => "--dev",
+            Profile::Profiling => "--profiling",
+            Profile::Release => "--release",
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Everything that controls a compilation. Start from `CompileOptions::new` and
+// adjust with the `with_*` methods.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompileOptions {
+    pub input: String,
+    pub output_dir: PathBuf,
+    pub target: Target,
+    pub profile: Profile,
+    pub features: Vec<String>,
+    pub no_default_features: bool,
+    pub out_name: String,
+    pub typescript: bool,
+    // Run the optimization stage over the emitted module
+    pub post_process: bool,
+    pub optimize: OptimizeOptions,
+    pub budget: SizeBudget,
+    // WasmConfig file to embed into the module
+    pub config: Option<PathBuf>,
+    // What to do with API keys in `config`
+    pub config_secrets: SecretPolicy,
+    pub lint: LintLevel,
+    // Rewrite tokio and native HTTP clients in a copy of the sources before building
+    pub shim: bool,
+    // Write the shimmed sources to `<output_dir>/shimmed-src/<out_name>`; implies `shim`
+    pub emit_shimmed_source: bool,
+    // Reuse the output of an identical earlier build from the build cache
+    pub cache: bool,
+    // Cargo profile settings, wasm-opt level and panic hook preset
+    pub preset: Option<Preset>,
+    // Keep DWARF and the name section and write a symbol map next to the module
+    pub debug_info: bool,
+    // Remap build paths, pin file times and write manifest.json with SRI hashes
+    pub reproducible: bool,
+    // ed25519 key to sign manifest.json with; implies writing the manifest
+    pub sign_key: Option<PathBuf>,
+    // Write a Content-Security-Policy as _headers and an nginx snippet
+    pub emit_headers: bool,
+    // Write a Web Worker entry and a main-thread proxy for the exports
+    pub emit_worker: bool,
+    // Out names of every entry built into `output_dir`. With several, each
+    // build links them into one package behind an index module.
+    pub package_entries: Vec<String>,
+}
+
+impl CompileOptions {
+    pub fn new(input: impl Into<String>, output_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            input: input.into(),
+            output_dir: output_dir.into(),
+            target: Target::default(),
+            profile: Profile::default(),
+            features: Vec::new(),
+            no_default_features: false,
+            out_name: "rig_wasm".to_string(),
+            typescript: false,
+            post_process: true,
+            optimize: OptimizeOptions::default(),
+            budget: SizeBudget::default(),
+            config: None,
+            config_secrets: SecretPolicy::default(),
+            lint: LintLevel::default(),
+            shim: false,
+            emit_shimmed_source: false,
+            cache: true,
+            preset: None,
+            debug_info: false,
+            reproducible: false,
+            sign_key: None,
+            emit_headers: false,
+            emit_worker: false,
+            package_entries: Vec::new(),
+        }
+    }
+
+    // The options for every target and entry in the build settings of `input`
+    // (rig-wasm.toml or `[package.metadata.rig-wasm]`, found as the CLI finds
+    // them), or of `manifest_path` when given. `overrides` win over the file.
+    pub fn from_manifest(
+        input: Option<&str>,
+        manifest_path: Option<&Path>,
+        overrides: ProjectManifest,
+    ) -> Result<Vec<Self>, CompileError> {
+        manifest::resolve_options(&manifest::project_dir(input), manifest_path, overrides)
+    }
+
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn with_profile(mut self, profile: Profile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    pub fn with_feature(mut self, feature: impl Into<String>) -> Self {
+        self.features.push(feature.into());
+        self
+    }
+
+    pub fn with_features<I, S>(mut self, features: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.features.extend(features.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn with_no_default_features(mut self, no_default_features: bool) -> Self {
+        self.no_default_features = no_default_features;
+        self
+    }
+
+    pub fn with_out_name(mut self, out_name: impl Into<String>) -> Self {
+        self.out_name = out_name.into();
+        self
+    }
+
+    pub fn with_typescript(mut self, typescript: bool) -> Self {
+        self.typescript = typescript;
+        self
+    }
+
+    pub fn with_post_process(mut self, post_process: bool) -> Self {
+        self.post_process = post_process;
+        self
+    }
+
+    pub fn with_opt_level(mut self, level: OptLevel) -> Self {
+        self.optimize.level = level;
+        self
+    }
+
+    pub fn with_strip_names(mut self, strip_names: bool) -> Self {
+        self.optimize.strip_names = strip_names;
+        self
+    }
+
+    pub fn with_strip_custom_sections(mut self, strip_custom_sections: bool) -> Self {
+        self.optimize.strip_custom_sections = strip_custom_sections;
+        self
+    }
+
+    pub fn with_budget(mut self, budget: SizeBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    pub fn with_config(mut self, config: impl Into<PathBuf>) -> Self {
+        self.config = Some(config.into());
+        self
+    }
+
+    pub fn with_config_secrets(mut self, policy: SecretPolicy) -> Self {
+        self.config_secrets = policy;
+        self
+    }
+
+    pub fn with_lint(mut self, lint: LintLevel) -> Self {
+        self.lint = lint;
+        self
+    }
+
+    pub fn with_shim(mut self, shim: bool) -> Self {
+        self.shim = shim;
+        self
+    }
+
+    pub fn with_emit_shimmed_source(mut self, emit_shimmed_source: bool) -> Self {
+        self.emit_shimmed_source = emit_shimmed_source;
+        self
+    }
+
+    pub fn with_cache(mut self, cache: bool) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    pub fn with_preset(mut self, preset: Preset) -> Self {
+        self.preset = Some(preset);
+        self
+    }
+
+    pub fn with_debug_info(mut self, debug_info: bool) -> Self {
+        self.debug_info = debug_info;
+        self
+    }
+
+    pub fn with_reproducible(mut self, reproducible: bool) -> Self {
+        self.reproducible = reproducible;
+        self
+    }
+
+    pub fn with_sign_key(mut self, sign_key: impl Into<PathBuf>) -> Self {
+        self.sign_key = Some(sign_key.into());
+        self
+    }
+
+    pub fn with_emit_headers(mut self, emit_headers: bool) -> Self {
+        self.emit_headers = emit_headers;
+        self
+    }
+
+    pub fn with_package_entries<I, S>(mut self, entries: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.package_entries = entries.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_emit_worker(mut self, emit_worker: bool) -> Self {
+        self.emit_worker = emit_worker;
+        self
+    }
+}
+
+// Runs a compilation described by `CompileOptions`, reporting progress to an observer
+pub struct Compiler {
+    options: CompileOptions,
+    observer: Box<dyn CompileObserver>,
+}
+
+impl Compiler {
+    pub fn new(options: CompileOptions) -> Self {
+        Self {
+            options,
+            observer: Box::new(NoopObserver),
+        }
+    }
+
+    pub fn with_observer(mut self, observer: impl CompileObserver + 'static) -> Self {
+        self.observer = Box::new(observer);
+        self
+    }
+
+    pub fn options(&self) -> &CompileOptions {
+        &self.options
+    }
+
+    pub fn compile(&mut self) -> Result<CompileReport, CompileError> {
+        let mut options = self.options.clone();
+
+        // Resolve the input to a buildable crate (directory, manifest, workspace member or .rs file)
+        // and make sure the tools the pipeline needs are installed
+        let (resolved, embedded_config, signing_key) = self.phase(Phase::Resolve, |_| {
+            let resolved = input::resolve_input(&options.input)?;
+            let embedded_config = match &options.config {
+                Some(config) => Some(secrets::embed_config(config, options.config_secrets)?),
+                None => None,
+            };
+            if options.target.is_wasi() && (options.shim || options.emit_shimmed_source) {
+                return Err(CompileError::InvalidInput(
+                    "--shim rewrites code for the browser and cannot be used with WASI targets".to_string(),
+                ));
+            }
+            if options.emit_headers {
+                csp::check_target(options.target)?;
+            }
+            if options.emit_worker {
+                worker::check_target(options.target)?;
+            }
+            toolchain::preflight(options.target)?;
+            if options.debug_info && !options.target.is_wasi() {
+                toolchain::preflight_bindgen(&resolved.crate_dir)?;
+            }
+            // A bad key fails before the build rather than after it
+            let signing_key = match &options.sign_key {
+                Some(path) => Some(signing::read_signing_key(path)?),
+                None => None,
+            };
+            Ok((resolved, embedded_config, signing_key))
+        })?;
+        // The config reaches user crates as a generated module (see `build`); this
+        // crate's own initialize_rig_wasm reads it with option_env!
+        let mut build_env: Vec<(&str, String)> = embedded_config
+            .iter()
+            .map(|config| (secrets::CONFIG_ENV, config.json.clone()))
+            .collect();
+
+        // Presets reach cargo as CARGO_PROFILE_* variables, and switch the panic
+        // hook through the crate's features
+        let preset = match options.preset {
+            Some(preset) => {
+                let settings = preset.settings();
+                let panic_hook_status = preset::apply_panic_hook(
+                    &resolved.manifest_path,
+                    settings.panic_hook,
+                    &mut options.features,
+                    &mut options.no_default_features,
+                )?;
+                build_env.extend(settings.cargo_env(options.profile));
+                Some(PresetReport {
+                    settings,
+                    profile: options.profile,
+                    wasm_opt_level: options.optimize.level,
+                    panic_hook_status,
+                })
+            }
+            None => None,
+        };
+
+        // Debug builds keep what debuggers and the panic hook's backtrace need
+        if options.debug_info {
+            options.optimize.strip_names = false;
+            options.optimize.debug_info = true;
+            let debug_var = match options.profile {
+                Profile::Dev => "CARGO_PROFILE_DEV_DEBUG",
+                Profile::Profiling | Profile::Release => "CARGO_PROFILE_RELEASE_DEBUG",
+            };
+            build_env.retain(|(name, _)| *name != debug_var);
+            build_env.push((debug_var, "2".to_string()));
+        }
+
+        // wasm-pack resolves --out-dir relative to the crate, so hand it an absolute path
+        fs::create_dir_all(&options.output_dir)?;
+        let output_dir = fs::canonicalize(&options.output_dir)?;
+
+        // An identical earlier build can be copied back instead of rebuilt. Shimmed
+        // sources are only written by a real build, so emitting them skips the cache.
+        let use_cache = options.cache && !options.emit_shimmed_source;
+        let cache_dir = cache::cache_dir();
+        let (cache_key, cached_entry) = if use_cache {
+            let (key, entry) = self.phase(Phase::Cache, |_| {
+                let key = cache::cache_key(&options, &resolved, &output_dir)?;
+                let entry = cache::restore(&cache_dir, &key, &output_dir)?;
+                Ok((key, entry))
+            })?;
+            (Some(key), entry)
+        } else {
+            (None, None)
+        };
+
+        let (shim, optimization) = match &cached_entry {
+            Some(entry) => (None, entry.optimization.clone()),
+            None => self.build(&options, resolved.clone(), embedded_config.as_ref(), &output_dir, &build_env)?,
+        };
+
+        let wasm_file = output_dir.join(options.target.wasm_file(&options.out_name));
+        let report = self.phase(Phase::Package, |_| {
+            // Build paths left in the glue would differ between machines
+            if options.reproducible {
+                integrity::normalize_outputs(&output_dir, &integrity::path_prefixes(&resolved))?;
+            }
+
+            // Make sure the glue for the requested target was actually emitted
+            let missing = options.target.missing_files(&output_dir, &options.out_name, options.typescript);
+            if !missing.is_empty() {
+                return Err(CompileError::PostProcess(format!(
+                    "wasm-pack did not produce the expected {} output: {}",
+                    options.target,
+                    missing.join(", ")
+                )));
+            }
+
+            // Refuse to ship a module with an API key in it
+            let glue: Vec<PathBuf> = options.target
+                .glue_files(&options.out_name)
+                .into_iter()
+                .map(|file| output_dir.join(file))
+                .collect();
+            let leaked = secrets::scan_output(&glue, embedded_config.as_ref())?;
+            if !leaked.is_empty() {
+                return Err(CompileError::SecretFound { secrets: leaked });
+            }
+
+            // JS glue is whatever .js wasm-bindgen emitted for the target
+            let budget = if options.budget.is_empty() {
+                None
+            } else {
+                let js_files: Vec<PathBuf> = options.target
+                    .glue_files(&options.out_name)
+                    .into_iter()
+                    .filter(|file| file.ends_with(".js"))
+                    .map(|file| output_dir.join(file))
+                    .collect();
+                Some(budget::check_budget(&options.budget, &wasm_file, &js_files)?)
+            };
+
+            // Generated from the glue, so cached builds get them too
+            let worker = if options.emit_worker {
+                Some(worker::write_worker(&output_dir, options.target, &options.out_name, options.typescript)?)
+            } else {
+                None
+            };
+
+            // The policy allows the provider origins of the embedded config
+            let headers = if options.emit_headers {
+                Some(csp::write_headers(&output_dir, embedded_config.as_ref())?)
+            } else {
+                None
+            };
+
+            // Entries sharing the output directory are re-linked after each one is
+            // built, since wasm-pack rewrites package.json for every entry. Entries
+            // not built yet are picked up when they are.
+            let index = if options.package_entries.len() > 1 && !options.target.is_wasi() {
+                package::link_entries(&output_dir, options.target, &options.package_entries, options.typescript)?;
+                Some(output_dir.join(format!("{}.js", package::INDEX_NAME)))
+            } else {
+                None
+            };
+
+            // Hashed and signed last, once every file is final
+            let integrity = if options.reproducible || signing_key.is_some() {
+                let manifest = integrity::write_manifest(&output_dir)?;
+                let signature = match &signing_key {
+                    Some(key) => Some(signing::sign_manifest(&output_dir, key)?),
+                    None => None,
+                };
+                let source_date_epoch = if options.reproducible {
+                    let epoch = integrity::source_date_epoch()?;
+                    integrity::set_timestamps(&output_dir, epoch)?;
+                    Some(epoch)
+                } else {
+                    None
+                };
+                Some(IntegrityReport {
+                    manifest: output_dir.join(integrity::MANIFEST_FILE),
+                    files: manifest.files.len(),
+                    source_date_epoch,
+                    signature,
+                    snippets: integrity::script_snippets(&manifest, options.target, &options.out_name),
+                })
+            } else {
+                None
+            };
+
+            Ok(CompileReport {
+                package_name: resolved.package_name.clone(),
+                target: options.target,
+                profile: options.profile,
+                output_dir: output_dir.clone(),
+                artifacts: collect_artifacts(&output_dir)?,
+                optimization,
+                budget,
+                shim: shim.clone(),
+                embedded_config: embedded_config.as_ref().map(|config| config.report()),
+                preset: preset.clone(),
+                symbol_map: Some(output_dir.join(symbols::symbols_file(&options.out_name)))
+                    .filter(|file| options.debug_info && file.is_file()),
+                worker,
+                headers,
+                index,
+                integrity,
+                cache_key: cache_key.clone(),
+                cached: cached_entry.is_some(),
+            })
+        })?;
+
+        // Only builds that passed every check are stored. A cache that cannot be
+        // written never fails the build.
+        if let (Some(key), None) = (&cache_key, &cached_entry) {
+            if let Err(e) = cache::store(
+                &cache_dir,
+                key,
+                &options,
+                &resolved.package_name,
+                &output_dir,
+                report.optimization.as_ref(),
+            ) {
+                self.observer.warning(&format!("Could not store the build in the cache: {}", e));
+            }
+        }
+        Ok(report)
+    }
+
+    // Shim, lint, cargo build, bindgen and optimize: everything a cache hit skips
+    fn build(
+        &mut self,
+        options: &CompileOptions,
+        resolved: ResolvedInput,
+        embedded_config: Option<&EmbeddedConfig>,
+        output_dir: &Path,
+        build_env: &[(&str, String)],
+    ) -> Result<(Option<ShimReport>, Option<OptimizeReport>), CompileError> {
+        // Build a copy of the crate with tokio and native HTTP clients swapped for wasm ones
+        let (resolved, shim) = if options.shim || options.emit_shimmed_source {
+            // Entries can share an output directory, so each gets its own folder
+            let emit = options.emit_shimmed_source.then(|| output_dir.join("shimmed-src").join(&options.out_name));
+            let (shimmed, report) = self.phase(Phase::Shim, |_| shim::apply(&resolved, output_dir, emit.as_deref()))?;
+            (shimmed, Some(report))
+        } else {
+            (resolved, None)
+        };
+
+        // The embedded config is generated into the crate, so it is built from a
+        // copy too. `copy` maps lint findings back to the original files.
+        let (resolved, copy) = match (embedded_config, shim.is_some()) {
+            (Some(config), true) => {
+                shim::embed_config(&resolved, &config.json)?;
+                (resolved, None)
+            }
+            (Some(config), false) => {
+                let (copy, report) = shim::copy(&resolved, output_dir)?;
+                shim::embed_config(&copy, &config.json)?;
+                (copy, Some(report))
+            }
+            (None, _) => (resolved, None),
+        };
+
+        // Remap the paths of the crate actually built, which is the shimmed copy with --shim
+        let mut build_env = build_env.to_vec();
+        let mut cargo_args = Vec::new();
+        if options.reproducible {
+            let prefixes = integrity::path_prefixes(&resolved);
+            let flags = integrity::remap_flags(&resolved.crate_dir, options.target.rust_target(), &prefixes)?;
+            build_env.extend(flags.env());
+            cargo_args = flags.cargo_args();
+        }
+
+        // Catch APIs and crates that only fail at link time or in the browser. The
+        // rules describe wasm32-unknown-unknown, so WASI builds are not linted.
+        if options.lint != LintLevel::Allow && !options.target.is_wasi() {
+            self.phase(Phase::Lint, |observer| {
+                let mut findings = lint::lint_sources(&resolved)?;
+                match lint::scan_dependencies(&resolved) {
+                    Ok(crate_findings) => findings.extend(crate_findings),
+                    Err(e) => observer.warning(&format!("Skipped the dependency scan: {}", e)),
+                }
+                // Point findings in the shimmed copy back at the files the user edits
+                if let Some(copy) = shim.as_ref().or(copy.as_ref()) {
+                    for finding in &mut findings {
+                        finding.file = copy.original_path(&finding.file);
+                    }
+                }
+
+                let level = if options.lint == LintLevel::Deny { "error" } else { "warning" };
+                let diagnostics: Vec<_> = findings.iter().map(|finding| finding.to_diagnostic(level)).collect();
+                for diagnostic in &diagnostics {
+                    observer.diagnostic(diagnostic);
+                }
+
+                if options.lint == LintLevel::Deny && !diagnostics.is_empty() {
+                    return Err(CompileError::Incompatible { diagnostics });
+                }
+                Ok(())
+            })?;
+        }
+
+        // Build with cargo first so rustc diagnostics are reported as they arrive.
+        // wasm-pack then finds the crate already built.
+        let cargo_artifacts = self.phase(Phase::CargoBuild, |observer| {
+            diagnostics::cargo_build(
+                &resolved.manifest_path,
+                options.target.rust_target(),
+                options.profile,
+                &options.features,
+                options.no_default_features,
+                &build_env,
+                &cargo_args,
+                &mut |diagnostic| observer.diagnostic(diagnostic),
+            )
+        })?;
+
+        if options.target.is_wasi() {
+            // No JS glue for WASI: the module cargo built is the output
+            let built = cargo_artifacts.last().ok_or_else(|| {
+                CompileError::InvalidInput(format!(
+                    "cargo did not build a .wasm for {}; is crate-type = [\"cdylib\"] set?",
+                    resolved.package_name
+                ))
+            })?;
+            let wasm_file = output_dir.join(options.target.wasm_file(&options.out_name));
+            fs::copy(built, &wasm_file)?;
+            let wit_file = output_dir.join(format!("{}.wit", options.out_name));
+            let exports_agent = options.target == Target::WasiP2
+                && wasm_module::exports_interface(&fs::read(&wasm_file)?, AGENT_INTERFACE);
+            if exports_agent {
+                fs::write(&wit_file, AGENT_WIT)?;
+            } else if wit_file.is_file() {
+                // Left over from an earlier build of a component that exported it
+                fs::remove_file(&wit_file)?;
+            }
+        } else if options.debug_info {
+            self.bindgen_keep_debug(options, &resolved, &cargo_artifacts, output_dir)?;
+        } else {
+            self.bindgen(options, &resolved, output_dir, &build_env, &cargo_args)?;
+        }
+
+        let wasm_file = output_dir.join(options.target.wasm_file(&options.out_name));
+        // wasm-opt and the section parser read core modules, not components
+        let optimize = options.post_process && options.optimize.level != OptLevel::O0 && wasm_file.is_file();
+        if optimize && options.target == Target::WasiP2 {
+            self.observer.warning("Skipped optimization: wasm-opt cannot read WASI components");
+        }
+        let optimization = if optimize && options.target != Target::WasiP2 {
+            Some(self.phase(Phase::Optimize, |observer| {
+                optimize::optimize(&wasm_file, &options.optimize, &mut |message| observer.warning(message))
+            })?)
+        } else {
+            None
+        };
+
+        // Offsets in the symbol map refer to the final module, so it is written last
+        if options.debug_info {
+            let symbols_file = output_dir.join(symbols::symbols_file(&options.out_name));
+            if let Err(e) = symbols::write_symbol_map(&wasm_file, &symbols_file) {
+                self.observer.warning(&format!("No symbol map written: {}", e));
+            }
+        }
+
+        Ok((shim, optimization))
+    }
+
+    // wasm-pack cannot be told to keep DWARF from the command line, so debug
+    // builds run wasm-bindgen on cargo's output directly
+    fn bindgen_keep_debug(
+        &mut self,
+        options: &CompileOptions,
+        resolved: &ResolvedInput,
+        cargo_artifacts: &[PathBuf],
+        output_dir: &Path,
+    ) -> Result<(), CompileError> {
+        self.phase(Phase::Bindgen, |_| {
+            let built = cargo_artifacts.last().ok_or_else(|| {
+                CompileError::InvalidInput("cargo did not build a .wasm; is crate-type = [\"cdylib\"] set?".to_string())
+            })?;
+            // The build just wrote Cargo.lock if there was none before preflight
+            toolchain::preflight_bindgen(&resolved.crate_dir)?;
+            let mut command = Command::new("wasm-bindgen");
+            command
+                .arg(built)
+                .arg("--target").arg(options.target.as_str())
+                .arg("--out-dir").arg(output_dir)
+                .arg("--out-name").arg(&options.out_name)
+                .arg("--keep-debug")
+                .arg("--debug");
+            if !options.typescript {
+                command.arg("--no-typescript");
+            }
+
+            let output = command
+                .output()
+                .map_err(|e| CompileError::spawn("wasm-bindgen", "cargo install wasm-bindgen-cli", e))?;
+            if !output.status.success() {
+                return Err(CompileError::WasmPack(format!(
+                    "wasm-bindgen --keep-debug: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                )));
+            }
+
+            // wasm-pack writes package.json; wasm-bindgen does not
+            package::write_entry_package_json(
+                &resolved.manifest_path,
+                &resolved.package_name,
+                &PackageOutput {
+                    output_dir: output_dir.to_path_buf(),
+                    target: options.target,
+                    typescript: options.typescript,
+                    out_names: vec![options.out_name.clone()],
+                },
+            )?;
+            Ok(())
+        })
+    }
+
+    // wasm-bindgen via wasm-pack, for the browser and Node targets
+    fn bindgen(
+        &mut self,
+        options: &CompileOptions,
+        resolved: &ResolvedInput,
+        output_dir: &Path,
+        build_env: &[(&str, String)],
+        cargo_args: &[String],
+    ) -> Result<(), CompileError> {
+        self.phase(Phase::Bindgen, |_| {
+            let mut command = Command::new("wasm-pack");
+            command
+                .envs(build_env.iter().cloned())
+                .arg("build")
+                .arg(&resolved.crate_dir)
+                .arg("--target").arg(options.target.as_str())
+                .arg("--out-dir").arg(output_dir)
+                .arg("--out-name").arg(&options.out_name)
+                .arg(options.profile.wasm_pack_flag())
+                // Optimization is our own stage, so wasm-pack's built-in wasm-opt run is skipped
+                .arg("--no-opt");
+
+            // wasm-pack emits .d.ts files unless told otherwise
+            if !options.typescript {
+                command.arg("--no-typescript");
+            }
+
+            // Everything after `--` is forwarded to cargo build
+            command.arg("--");
+            if !options.features.is_empty() {
+                command.arg("--features").arg(options.features.join(","));
+            }
+            if options.no_default_features {
+                command.arg("--no-default-features");
+            }
+            command.args(cargo_args);
+
+            let output = command
+                .output()
+                .map_err(|e| CompileError::spawn("wasm-pack", "cargo install wasm-pack", e))?;
+
+            if !output.status.success() {
+                return Err(CompileError::WasmPack(
+                    String::from_utf8_lossy(&output.stderr).into_owned(),
+                ));
+            }
+            Ok(())
+        })
+    }
+
+    // Runs one phase, notifying the observer before and after
+    fn phase<T>(
+        &mut self,
+        phase: Phase,
+        f: impl FnOnce(&mut dyn CompileObserver) -> Result<T, CompileError>,
+    ) -> Result<T, CompileError> {
+        self.observer.phase_started(phase);
+        let start = Instant::now();
+        let result = f(self.observer.as_mut());
+        self.observer.phase_finished(phase, start.elapsed());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile_options_builder() {
+        let options = CompileOptions::new("examples/rag_agent.rs", "dist")
+            .with_target(Target::Nodejs)
+            .with_profile(Profile::Dev)
+            .with_feature("openai")
+            .with_features(["qdrant"])
+            .with_out_name("rag_agent")
+            .with_typescript(true)
+            .with_post_process(false)
+            .with_opt_level(OptLevel::Oz)
+            .with_strip_names(true);
+
+        assert_eq!(options.target, Target::Nodejs);
+        assert_eq!(options.profile, Profile::Dev);
+        assert_eq!(options.features, vec!["openai", "qdrant"]);
+        assert_eq!(options.out_name, "rag_agent");
+        assert!(options.typescript);
+        assert!(!options.post_process);
+        assert_eq!(options.optimize.level, OptLevel::Oz);
+        assert!(options.optimize.strip_names);
+    }
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        phases: std::rc::Rc<std::cell::RefCell<Vec<Phase>>>,
+    }
+
+    impl CompileObserver for RecordingObserver {
+        fn phase_started(&mut self, phase: Phase) {
+            self.phases.borrow_mut().push(phase);
+        }
+    }
+
+    #[test]
+    fn test_observer_sees_failed_phase() {
+        let observer = RecordingObserver::default();
+        let phases = observer.phases.clone();
+
+        let mut compiler = Compiler::new(CompileOptions::new("does/not/exist.rs", "test_output/none"))
+            .with_observer(observer);
+        let err = compiler.compile().unwrap_err();
+
+        assert_eq!(err.kind(), "input-not-found");
+        assert_eq!(*phases.borrow(), vec![Phase::Resolve]);
+    }
+}
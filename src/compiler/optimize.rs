@@ -0,0 +1,254 @@
+use crate::compiler::error::CompileError;
+use crate::compiler::wasm_module::strip_custom_sections;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+
+// Features rustc enables by default for wasm32-unknown-unknown. wasm-opt
+// rejects modules that use them unless they are switched on explicitly.
+const WASM_FEATURES: &[&str] = &[
+    "--enable-bulk-memory",
+    "--enable-mutable-globals",
+    "--enable-nontrapping-float-to-int",
+    "--enable-sign-ext",
+    "--enable-reference-types",
+    "--enable-multivalue",
+];
+
+// wasm-opt optimization levels. O0 skips the stage entirely.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub enum OptLevel {
+    O0,
+    O1,
+    #[default]
+    O2,
+    O3,
+    O4,
+    Os,
+    Oz,
+}
+
+impl OptLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OptLevel::O0 => "O0",
+            OptLevel::O1 => "O1",
+            OptLevel::O2 => "O2",
+            OptLevel::O3 => "O3",
+            OptLevel::O4 => "O4",
+            OptLevel::Os => "Os",
+            OptLevel::Oz => "Oz",
+        }
+    }
+
+    // Maps the numeric levels accepted by `compile_to_wasm`
+    pub fn from_level(level: u8) -> Self {
+        match level {
+            0 => OptLevel::O0,
+            1 => OptLevel::O1,
+            2 => OptLevel::O2,
+            3 => OptLevel::O3,
+            _ => OptLevel::O4,
+        }
+    }
+}
+
+impl fmt::Display for OptLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Accepts `2`, `O2`, `s`, `Oz`, ...
+impl FromStr for OptLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('O').unwrap_or(s) {
+            "0" => Ok(OptLevel::O0),
+            "1" => Ok(OptLevel::O1),
+            "2" => Ok(OptLevel::O2),
+            "3" => Ok(OptLevel::O3),
+            "4" => Ok(OptLevel::O4),
+            "s" => Ok(OptLevel::Os),
+            "z" => Ok(OptLevel::Oz),
+            _ => Err(format!("Unknown optimization level: {} (expected 0-4, s or z)", s)),
+        }
+    }
+}
+
+// Accepts the same spellings as the CLI, plus bare integers in config files
+impl<'de> Deserialize<'de> for OptLevel {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Level {
+            Number(u8),
+            Text(String),
+        }
+
+        match Level::deserialize(deserializer)? {
+            Level::Number(level) => level.to_string().parse(),
+            Level::Text(level) => level.parse(),
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+// Settings for the post-bindgen optimization stage
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OptimizeOptions {
+    pub level: OptLevel,
+    // Drop the name section and DWARF
+    pub strip_names: bool,
+    // Drop every other custom section (producers, target_features, ...)
+    pub strip_custom_sections: bool,
+    // Keep DWARF and the name section through wasm-opt (debug builds)
+    #[serde(default)]
+    pub debug_info: bool,
+}
+
+// Size of the module before and after the stage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizeReport {
+    pub file: PathBuf,
+    pub level: OptLevel,
+    pub before: u64,
+    pub after: u64,
+    // Set when wasm-opt was not installed and only section stripping ran
+    pub wasm_opt_skipped: bool,
+}
+
+impl OptimizeReport {
+    pub fn change_percent(&self) -> f64 {
+        if self.before == 0 {
+            return 0.0;
+        }
+        (self.after as f64 - self.before as f64) / self.before as f64 * 100.0
+    }
+}
+
+// Returns the wasm-opt version string, or None if Binaryen isn't installed
+pub fn wasm_opt_version() -> Option<String> {
+    let output = Command::new("wasm-opt").arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// Optimizes `wasm_file` in place. `warn` is called when wasm-opt is missing.
+pub fn optimize(
+    wasm_file: &Path,
+    options: &OptimizeOptions,
+    warn: &mut dyn FnMut(&str),
+) -> Result<OptimizeReport, CompileError> {
+    let before = fs::metadata(wasm_file)?.len();
+
+    let wasm_opt_skipped = wasm_opt_version().is_none();
+    if wasm_opt_skipped {
+        warn("wasm-opt (Binaryen) is not installed; skipping wasm-opt. Install it from https://github.com/WebAssembly/binaryen");
+    } else {
+        run_wasm_opt(wasm_file, options)?;
+    }
+
+    if options.strip_names || options.strip_custom_sections {
+        let bytes = fs::read(wasm_file)?;
+        let stripped = strip_custom_sections(&bytes, |name| {
+            if name == "name" || name.starts_with(".debug_") {
+                options.strip_names
+            } else {
+                options.strip_custom_sections
+            }
+        })
+        .map_err(|e| CompileError::PostProcess(format!("{}: {}", wasm_file.display(), e)))?;
+        fs::write(wasm_file, stripped)?;
+    }
+
+    Ok(OptimizeReport {
+        file: wasm_file.to_path_buf(),
+        level: options.level,
+        before,
+        after: fs::metadata(wasm_file)?.len(),
+        wasm_opt_skipped,
+    })
+}
+
+fn wasm_opt_command(wasm_file: &Path, options: &OptimizeOptions) -> Command {
+    let mut command = Command::new("wasm-opt");
+    command
+        .arg(wasm_file)
+        .arg(format!("-{}", options.level))
+        .args(WASM_FEATURES)
+        .arg("-o").arg(wasm_file);
+    // wasm-opt drops the name section unless told to keep debug info
+    if options.debug_info || !options.strip_names {
+        command.arg("--debuginfo");
+    } else {
+        command.arg("--strip-debug");
+    }
+    if options.strip_custom_sections {
+        command.arg("--strip-producers").arg("--strip-target-features");
+    }
+    command
+}
+
+fn run_wasm_opt(wasm_file: &Path, options: &OptimizeOptions) -> Result<(), CompileError> {
+    let output = wasm_opt_command(wasm_file, options)
+        .output()
+        .map_err(|e| CompileError::spawn("wasm-opt", "cargo install wasm-opt", e))?;
+    if !output.status.success() {
+        return Err(CompileError::PostProcess(format!(
+            "wasm-opt failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_opt_level() {
+        assert_eq!("2".parse::<OptLevel>().unwrap(), OptLevel::O2);
+        assert_eq!("Oz".parse::<OptLevel>().unwrap(), OptLevel::Oz);
+        assert_eq!("s".parse::<OptLevel>().unwrap(), OptLevel::Os);
+        assert!("5".parse::<OptLevel>().is_err());
+        assert!("OO2".parse::<OptLevel>().is_err());
+    }
+
+    #[test]
+    fn test_wasm_opt_keeps_names_unless_stripping() {
+        let args = |options: &OptimizeOptions| -> Vec<String> {
+            wasm_opt_command(Path::new("m.wasm"), options)
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect()
+        };
+
+        let keep = args(&OptimizeOptions::default());
+        assert!(keep.contains(&"--debuginfo".to_string()));
+        assert!(!keep.contains(&"--strip-debug".to_string()));
+
+        let strip = args(&OptimizeOptions { strip_names: true, ..Default::default() });
+        assert!(strip.contains(&"--strip-debug".to_string()));
+        assert!(!strip.contains(&"--debuginfo".to_string()));
+    }
+
+    #[test]
+    fn test_deserialize_opt_level() {
+        let level: OptLevel = serde_json::from_str("3").unwrap();
+        assert_eq!(level, OptLevel::O3);
+        let level: OptLevel = serde_json::from_str("\"Oz\"").unwrap();
+        assert_eq!(level, OptLevel::Oz);
+    }
+}
@@ -0,0 +1,688 @@
+use crate::compiler::error::CompileError;
+use crate::compiler::input::resolve_input;
+use crate::compiler::manifest::PackageMetadata;
+use crate::compiler::report::{format_size, Artifact};
+use crate::compiler::target::Target;
+use crate::compiler::CompileOptions;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// The module that re-exports every entry of a multi-entry package
+pub const INDEX_NAME: &str = "index";
+
+// The entries built into one output directory for one target
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageOutput {
+    pub output_dir: PathBuf,
+    pub target: Target,
+    pub typescript: bool,
+    pub out_names: Vec<String>,
+}
+
+impl PackageOutput {
+    // The module a consumer imports: the index when there are several entries
+    pub fn module(&self) -> String {
+        self.entry_module(None)
+    }
+
+    fn entry_module(&self, entry: Option<&str>) -> String {
+        match entry {
+            Some(name) => format!("{}.js", name),
+            None if self.out_names.len() > 1 => format!("{}.js", INDEX_NAME),
+            None => format!("{}.js", self.out_names[0]),
+        }
+    }
+
+    fn entry_types(&self, entry: Option<&str>) -> String {
+        match entry {
+            Some(name) => format!("{}.d.ts", name),
+            None if self.out_names.len() > 1 => format!("{}.d.ts", INDEX_NAME),
+            None => format!("{}.d.ts", self.out_names[0]),
+        }
+    }
+}
+
+// Groups options by output directory, in build order
+pub fn group_outputs(options: &[CompileOptions]) -> Vec<PackageOutput> {
+    let mut outputs: Vec<PackageOutput> = Vec::new();
+    for options in options {
+        match outputs.iter_mut().find(|output| output.output_dir == options.output_dir) {
+            Some(output) => output.out_names.push(options.out_name.clone()),
+            None => outputs.push(PackageOutput {
+                output_dir: options.output_dir.clone(),
+                target: options.target,
+                typescript: options.typescript,
+                out_names: vec![options.out_name.clone()],
+            }),
+        }
+    }
+    outputs
+}
+
+// The index script for `target`, exposing each entry as a namespace named after
+// its module. Web output also gets a default export that initializes every entry.
+pub fn index_js(target: Target, out_names: &[String]) -> String {
+    let mut js = String::new();
+    if target == Target::Nodejs {
+        js.push_str("module.exports = {\n");
+        for name in out_names {
+            js.push_str(&format!("  {name}: require('./{name}.js'),\n", name = name));
+        }
+        js.push_str("};\n");
+        return js;
+    }
+
+    for name in out_names {
+        js.push_str(&format!("import * as {name} from './{name}.js';\n", name = name));
+    }
+    js.push_str(&format!("\nexport {{ {} }};\n", out_names.join(", ")));
+
+    if target == Target::Web {
+        let inits: Vec<String> = out_names.iter().map(|name| format!("{}.default()", name)).collect();
+        js.push_str(&format!(
+            "\nexport default async function init() {{\n  await Promise.all([{}]);\n}}\n",
+            inits.join(", ")
+        ));
+    }
+    js
+}
+
+pub fn index_d_ts(target: Target, out_names: &[String]) -> String {
+    let mut ts = String::new();
+    for name in out_names {
+        ts.push_str(&format!("import * as {name} from './{name}';\n", name = name));
+    }
+    ts.push_str(&format!("\nexport {{ {} }};\n", out_names.join(", ")));
+    if target == Target::Web {
+        ts.push_str("\nexport default function init(): Promise<void>;\n");
+    }
+    ts
+}
+
+// Points the package.json wasm-pack wrote for the last entry at the index and
+// lists the files of every entry. wasm-pack rewrites it for each entry, so only
+// the last one survives otherwise.
+pub fn merge_package_json(
+    package_json: &mut Value,
+    target: Target,
+    out_names: &[String],
+    typescript: bool,
+) {
+    let mut files: Vec<String> = out_names
+        .iter()
+        .flat_map(|name| {
+            let mut files = target.glue_files(name);
+            if typescript {
+                files.extend(target.typescript_files(name));
+            }
+            files
+        })
+        .collect();
+    files.push(format!("{}.js", INDEX_NAME));
+    if typescript {
+        files.push(format!("{}.d.ts", INDEX_NAME));
+    }
+
+    let main = if target == Target::Nodejs { "main" } else { "module" };
+    if let Some(object) = package_json.as_object_mut() {
+        object.insert("files".to_string(), json!(files));
+        object.insert(main.to_string(), json!(format!("{}.js", INDEX_NAME)));
+        if typescript {
+            object.insert("types".to_string(), json!(format!("{}.d.ts", INDEX_NAME)));
+        }
+    }
+}
+
+// Writes the index (and its .d.ts) for entries built into `output_dir` and
+// updates package.json to match. Returns the files written.
+pub fn link_entries(
+    output_dir: &Path,
+    target: Target,
+    out_names: &[String],
+    typescript: bool,
+) -> Result<Vec<PathBuf>, CompileError> {
+    if out_names.iter().any(|name| name == INDEX_NAME) {
+        return Err(CompileError::InvalidInput(format!(
+            "An entry cannot be named {} since the package index uses that name",
+            INDEX_NAME
+        )));
+    }
+
+    // Entries that are not built yet are left out until their own build relinks
+    let built: Vec<String> = out_names
+        .iter()
+        .filter(|name| target.missing_files(output_dir, name, typescript).is_empty())
+        .cloned()
+        .collect();
+
+    let mut written = Vec::new();
+    let index = output_dir.join(format!("{}.js", INDEX_NAME));
+    fs::write(&index, index_js(target, &built))?;
+    written.push(index);
+
+    if typescript {
+        let index_types = output_dir.join(format!("{}.d.ts", INDEX_NAME));
+        fs::write(&index_types, index_d_ts(target, &built))?;
+        written.push(index_types);
+    }
+
+    let package_json_path = output_dir.join("package.json");
+    if package_json_path.is_file() {
+        let mut package_json: Value = serde_json::from_str(&fs::read_to_string(&package_json_path)?)
+            .map_err(|e| CompileError::PostProcess(format!("Invalid package.json from wasm-pack: {}", e)))?;
+        merge_package_json(&mut package_json, target, &built, typescript);
+        fs::write(&package_json_path, serde_json::to_string_pretty(&package_json).unwrap() + "\n")?;
+        written.push(package_json_path);
+    }
+
+    Ok(written)
+}
+
+// Writes the package.json wasm-pack would for one entry, for debug builds that
+// run wasm-bindgen directly
+pub fn write_entry_package_json(
+    manifest_path: &Path,
+    package_name: &str,
+    output: &PackageOutput,
+) -> Result<PathBuf, CompileError> {
+    let mut metadata = crate_metadata(manifest_path)?;
+    metadata.name.get_or_insert_with(|| package_name.to_string());
+    metadata.version.get_or_insert_with(|| "0.1.0".to_string());
+    let package = package_json(&metadata, &[(PathBuf::new(), output)], &[]);
+    let path = output.output_dir.join("package.json");
+    let json = serde_json::to_string_pretty(&package).map_err(|e| CompileError::PostProcess(e.to_string()))?;
+    fs::write(&path, json + "\n")?;
+    Ok(path)
+}
+
+// Fields from the `[package]` table of a crate's Cargo.toml. Values inherited
+// from the workspace (`version.workspace = true`) are left unset.
+pub fn crate_metadata(manifest_path: &Path) -> Result<PackageMetadata, CompileError> {
+    let contents = fs::read_to_string(manifest_path)?;
+    let cargo: toml::Value = toml::from_str(&contents)
+        .map_err(|e| CompileError::InvalidInput(format!("Invalid {}: {}", manifest_path.display(), e)))?;
+    let package = cargo.get("package");
+    let field = |key: &str| package.and_then(|p| p.get(key)).and_then(|v| v.as_str()).map(str::to_string);
+
+    Ok(PackageMetadata {
+        name: field("name"),
+        version: field("version"),
+        description: field("description"),
+        license: field("license"),
+        repository: field("repository"),
+        homepage: field("homepage"),
+        keywords: package
+            .and_then(|p| p.get("keywords"))
+            .and_then(|k| k.as_array())
+            .map(|k| k.iter().filter_map(|k| k.as_str().map(str::to_string)).collect())
+            .unwrap_or_default(),
+    })
+}
+
+// What `pack` wrote
+#[derive(Debug, Clone, Serialize)]
+pub struct PackReport {
+    pub name: String,
+    pub version: String,
+    pub package_json: PathBuf,
+    pub tarball: PathBuf,
+    // Paths inside the package, with their sizes
+    pub files: Vec<Artifact>,
+    pub size: u64,
+}
+
+impl fmt::Display for PackReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Package: {}@{}", self.name, self.version)?;
+        for file in &self.files {
+            writeln!(f, "  {:<32} {:>12}", file.path.display(), format_size(file.size))?;
+        }
+        write!(f, "Tarball: {} ({})", self.tarball.display(), format_size(self.size))
+    }
+}
+
+// The deepest directory containing every output directory
+fn common_root(dirs: &[PathBuf]) -> PathBuf {
+    let mut root = dirs[0].clone();
+    while !dirs.iter().all(|dir| dir.starts_with(&root)) {
+        if !root.pop() {
+            break;
+        }
+    }
+    root
+}
+
+// A path inside the package, always with forward slashes
+fn package_path(dir: &Path, file: &str) -> String {
+    let mut parts: Vec<String> = dir.iter().map(|part| part.to_string_lossy().into_owned()).collect();
+    parts.push(file.to_string());
+    parts.join("/")
+}
+
+// The conditional export for `entry` (or the package's main module). Conditions
+// are ordered most specific first, since resolvers take the first match.
+fn export_conditions(outputs: &[(PathBuf, &PackageOutput)], entry: Option<&str>) -> Value {
+    let mut conditions = Map::new();
+
+    let typed = outputs
+        .iter()
+        .filter(|(_, output)| output.typescript)
+        .min_by_key(|(_, output)| output.target == Target::Nodejs);
+    if let Some((dir, output)) = typed {
+        conditions.insert("types".to_string(), json!(format!("./{}", package_path(dir, &output.entry_types(entry)))));
+    }
+
+    let module = |target: Target| {
+        outputs
+            .iter()
+            .find(|(_, output)| output.target == target)
+            .map(|(dir, output)| format!("./{}", package_path(dir, &output.entry_module(entry))))
+    };
+    for target in [Target::Deno, Target::Nodejs, Target::Bundler, Target::Web] {
+        if let (Some(condition), Some(path)) = (target.export_condition(), module(target)) {
+            conditions.insert(condition.to_string(), json!(path));
+        }
+    }
+    let default = [Target::Bundler, Target::Web, Target::Nodejs, Target::Deno]
+        .into_iter()
+        .find_map(module);
+    if let Some(path) = default {
+        conditions.insert("default".to_string(), json!(path));
+    }
+
+    Value::Object(conditions)
+}
+
+// Builds package.json for the outputs in `dirs` (relative to the package root)
+pub fn package_json(metadata: &PackageMetadata, outputs: &[(PathBuf, &PackageOutput)], extra_files: &[String]) -> Value {
+    let mut files = Vec::new();
+    let mut side_effects = Vec::new();
+    for (dir, output) in outputs {
+        for name in &output.out_names {
+            files.extend(output.target.glue_files(name).iter().map(|file| package_path(dir, file)));
+            if output.typescript {
+                files.extend(output.target.typescript_files(name).iter().map(|file| package_path(dir, file)));
+            }
+        }
+        if output.out_names.len() > 1 {
+            files.push(package_path(dir, &format!("{}.js", INDEX_NAME)));
+            if output.typescript {
+                files.push(package_path(dir, &format!("{}.d.ts", INDEX_NAME)));
+            }
+        }
+        files.push(package_path(dir, "snippets"));
+
+        // wasm-bindgen snippets run on import, and bundler glue instantiates the module
+        side_effects.push(format!("./{}", package_path(dir, "snippets/*")));
+        if output.target == Target::Bundler {
+            side_effects.push(format!("./{}", package_path(dir, &output.module())));
+            for name in &output.out_names {
+                side_effects.push(format!("./{}", package_path(dir, &format!("{}.js", name))));
+            }
+        }
+    }
+    files.extend(extra_files.iter().cloned());
+    side_effects.dedup();
+
+    let mut package = Map::new();
+    package.insert("name".to_string(), json!(metadata.name));
+    package.insert("version".to_string(), json!(metadata.version));
+    if let Some(description) = &metadata.description {
+        package.insert("description".to_string(), json!(description));
+    }
+    if let Some(license) = &metadata.license {
+        package.insert("license".to_string(), json!(license));
+    }
+    if let Some(repository) = &metadata.repository {
+        package.insert("repository".to_string(), json!({ "type": "git", "url": repository }));
+    }
+    if let Some(homepage) = &metadata.homepage {
+        package.insert("homepage".to_string(), json!(homepage));
+    }
+    if !metadata.keywords.is_empty() {
+        package.insert("keywords".to_string(), json!(metadata.keywords));
+    }
+    package.insert("files".to_string(), json!(files));
+
+    let main_module = |targets: &[Target]| {
+        targets.iter().find_map(|&target| {
+            outputs
+                .iter()
+                .find(|(_, output)| output.target == target)
+                .map(|(dir, output)| package_path(dir, &output.module()))
+        })
+    };
+    if let Some(main) = main_module(&[Target::Nodejs, Target::Bundler, Target::Web, Target::Deno, Target::NoModules]) {
+        package.insert("main".to_string(), json!(main));
+    }
+    if let Some(module) = main_module(&[Target::Bundler, Target::Web]) {
+        package.insert("module".to_string(), json!(module));
+    }
+    if let Some(Value::String(types)) = export_conditions(outputs, None).get("types") {
+        package.insert("types".to_string(), json!(types.trim_start_matches("./")));
+    }
+
+    let importable: Vec<(PathBuf, &PackageOutput)> = outputs
+        .iter()
+        .filter(|(_, output)| output.target.export_condition().is_some())
+        .cloned()
+        .collect();
+    if !importable.is_empty() {
+        let mut exports = Map::new();
+        exports.insert(".".to_string(), export_conditions(&importable, None));
+        if importable[0].1.out_names.len() > 1 {
+            for name in &importable[0].1.out_names {
+                exports.insert(format!("./{}", name), export_conditions(&importable, Some(name)));
+            }
+        }
+        package.insert("exports".to_string(), Value::Object(exports));
+    }
+
+    package.insert("sideEffects".to_string(), json!(side_effects));
+    Value::Object(package)
+}
+
+// npm pack stamps every entry with this date (1985-10-26T08:15:00Z) so
+// tarballs of the same files are byte-identical
+const NPM_MTIME: u64 = 499_162_500;
+
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = format!("{:0width$o}\0", value, width = field.len() - 1);
+    field.copy_from_slice(digits.as_bytes());
+}
+
+// A ustar header. Paths over 100 bytes are split into the prefix field.
+fn tar_header(path: &str, size: u64) -> Result<[u8; 512], CompileError> {
+    let (prefix, name) = if path.len() <= 100 {
+        ("", path)
+    } else {
+        path.char_indices()
+            .filter(|&(i, c)| c == '/' && i <= 155 && path.len() - i - 1 <= 100)
+            .map(|(i, _)| (&path[..i], &path[i + 1..]))
+            .next()
+            .ok_or_else(|| CompileError::InvalidInput(format!("Path is too long for a tarball: {}", path)))?
+    };
+
+    let mut header = [0u8; 512];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    write_octal(&mut header[100..108], 0o644);
+    write_octal(&mut header[108..116], 0);
+    write_octal(&mut header[116..124], 0);
+    write_octal(&mut header[124..136], size);
+    write_octal(&mut header[136..148], NPM_MTIME);
+    header[148..156].fill(b' ');
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+    let checksum: u32 = header.iter().map(|&b| b as u32).sum();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+    Ok(header)
+}
+
+// Writes a gzipped tarball laid out like `npm pack`: every file under `package/`
+pub fn write_tarball(path: &Path, files: &[(String, Vec<u8>)]) -> Result<(), CompileError> {
+    let mut tarball = GzEncoder::new(fs::File::create(path)?, Compression::best());
+    for (name, contents) in files {
+        tarball.write_all(&tar_header(&format!("package/{}", name), contents.len() as u64)?)?;
+        tarball.write_all(contents)?;
+        let padding = (512 - contents.len() % 512) % 512;
+        tarball.write_all(&vec![0u8; padding])?;
+    }
+    tarball.write_all(&[0u8; 1024])?;
+    tarball.finish()?;
+    Ok(())
+}
+
+// Every file under `path`, as package paths relative to `root`
+fn expand(root: &Path, path: &Path, files: &mut Vec<String>) -> Result<(), CompileError> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            expand(root, &entry?.path(), files)?;
+        }
+    } else if path.is_file() {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        let parts: Vec<String> = relative.iter().map(|part| part.to_string_lossy().into_owned()).collect();
+        files.push(parts.join("/"));
+    }
+    Ok(())
+}
+
+// Writes package.json over built output and packs it into `destination` as
+// `<name>-<version>.tgz`. `metadata` wins over the entry crate's Cargo.toml.
+pub fn pack(options: &[CompileOptions], metadata: PackageMetadata, destination: &Path) -> Result<PackReport, CompileError> {
+    // npm packages carry the JS targets; WASI components are deployed on their own
+    let options: Vec<CompileOptions> = options.iter().filter(|o| !o.target.is_wasi()).cloned().collect();
+    let options = options.as_slice();
+    let first = options
+        .first()
+        .ok_or_else(|| CompileError::InvalidInput("Nothing to pack".to_string()))?;
+    let resolved = resolve_input(&first.input)?;
+
+    // Several crates have no single name to publish under
+    let several_crates = options.iter().any(|o| o.input != first.input);
+    if several_crates && metadata.name.is_none() {
+        return Err(CompileError::InvalidInput(
+            "A package with several entries needs a name; set `name` under [npm] in rig-wasm.toml".to_string(),
+        ));
+    }
+    let metadata = metadata.or(crate_metadata(&resolved.manifest_path)?);
+    let (name, version) = match (&metadata.name, &metadata.version) {
+        (Some(name), Some(version)) => (name.clone(), version.clone()),
+        _ => {
+            return Err(CompileError::InvalidInput(format!(
+                "No package name or version in {}; set them under [npm] in rig-wasm.toml",
+                resolved.manifest_path.display()
+            )))
+        }
+    };
+
+    let outputs = group_outputs(options);
+    let mut dirs = Vec::new();
+    for output in &outputs {
+        let dir = fs::canonicalize(&output.output_dir)
+            .map_err(|_| CompileError::InputNotFound(format!("{} (build before packing)", output.output_dir.display())))?;
+        for name in &output.out_names {
+            let missing = output.target.missing_files(&dir, name, output.typescript);
+            if !missing.is_empty() {
+                return Err(CompileError::InvalidInput(format!(
+                    "{} is missing {}; build before packing",
+                    dir.display(),
+                    missing.join(", ")
+                )));
+            }
+        }
+        dirs.push(dir);
+    }
+    let root = common_root(&dirs);
+    let relative: Vec<(PathBuf, &PackageOutput)> = dirs
+        .iter()
+        .zip(&outputs)
+        .map(|(dir, output)| (dir.strip_prefix(&root).unwrap_or(dir).to_path_buf(), output))
+        .collect();
+
+    // npm always ships the readme and license next to package.json
+    let mut extra_files = Vec::new();
+    if !resolved.generated {
+        for entry in fs::read_dir(&resolved.crate_dir)? {
+            let path = entry?.path();
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            let upper = file_name.to_uppercase();
+            if path.is_file() && (upper.starts_with("README") || upper.starts_with("LICENSE") || upper.starts_with("LICENCE")) {
+                fs::copy(&path, root.join(&file_name))?;
+                extra_files.push(file_name);
+            }
+        }
+    }
+
+    let package = package_json(&metadata, &relative, &extra_files);
+    let package_json_path = root.join("package.json");
+    let package_json = serde_json::to_string_pretty(&package).unwrap() + "\n";
+    fs::write(&package_json_path, &package_json)?;
+
+    let mut paths = Vec::new();
+    for file in package["files"].as_array().into_iter().flatten().filter_map(|f| f.as_str()) {
+        expand(&root, &root.join(file), &mut paths)?;
+    }
+    paths.sort();
+    paths.dedup();
+
+    let mut contents = vec![("package.json".to_string(), package_json.into_bytes())];
+    for path in paths {
+        let data = fs::read(root.join(&path))?;
+        contents.push((path, data));
+    }
+
+    fs::create_dir_all(destination)?;
+    let tarball = destination.join(format!("{}-{}.tgz", name.trim_start_matches('@').replace('/', "-"), version));
+    write_tarball(&tarball, &contents)?;
+
+    Ok(PackReport {
+        name,
+        version,
+        package_json: package_json_path,
+        size: fs::metadata(&tarball)?.len(),
+        tarball,
+        files: contents
+            .iter()
+            .map(|(path, data)| Artifact {
+                path: PathBuf::from(path),
+                size: data.len() as u64,
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Vec<String> {
+        vec!["simple_agent".to_string(), "rag_agent".to_string()]
+    }
+
+    #[test]
+    fn test_index_js() {
+        let web = index_js(Target::Web, &names());
+        assert!(web.contains("import * as rag_agent from './rag_agent.js';"));
+        assert!(web.contains("export { simple_agent, rag_agent };"));
+        assert!(web.contains("Promise.all([simple_agent.default(), rag_agent.default()])"));
+
+        assert!(!index_js(Target::Bundler, &names()).contains("export default"));
+        assert!(index_js(Target::Nodejs, &names()).contains("simple_agent: require('./simple_agent.js'),"));
+    }
+
+    #[test]
+    fn test_link_entries_skips_unbuilt() {
+        let dir = std::env::temp_dir().join("rig-wasm-compiler-link-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("simple_agent.js"), "").unwrap();
+        fs::write(dir.join("simple_agent_bg.wasm"), "").unwrap();
+
+        link_entries(&dir, Target::Web, &names(), false).unwrap();
+        let index = fs::read_to_string(dir.join("index.js")).unwrap();
+        assert!(index.contains("./simple_agent.js"));
+        assert!(!index.contains("rag_agent"));
+
+        fs::write(dir.join("rag_agent.js"), "").unwrap();
+        fs::write(dir.join("rag_agent_bg.wasm"), "").unwrap();
+        link_entries(&dir, Target::Web, &names(), false).unwrap();
+        assert!(fs::read_to_string(dir.join("index.js")).unwrap().contains("./rag_agent.js"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_merge_package_json() {
+        let mut package_json = json!({
+            "name": "rag_agent",
+            "files": ["rag_agent_bg.wasm", "rag_agent.js"],
+            "module": "rag_agent.js",
+        });
+        merge_package_json(&mut package_json, Target::Web, &names(), true);
+
+        assert_eq!(package_json["module"], "index.js");
+        assert_eq!(package_json["types"], "index.d.ts");
+        let files = package_json["files"].as_array().unwrap();
+        assert!(files.contains(&json!("simple_agent_bg.wasm")));
+        assert!(files.contains(&json!("index.d.ts")));
+    }
+
+    #[test]
+    fn test_package_json_exports() {
+        let web = PackageOutput {
+            output_dir: "dist/web".into(),
+            target: Target::Web,
+            typescript: true,
+            out_names: vec!["rig_wasm".to_string()],
+        };
+        let node = PackageOutput {
+            target: Target::Nodejs,
+            output_dir: "dist/nodejs".into(),
+            ..web.clone()
+        };
+        let metadata = PackageMetadata {
+            name: Some("@acme/agent".to_string()),
+            version: Some("1.2.0".to_string()),
+            ..Default::default()
+        };
+        let outputs = vec![(PathBuf::from("web"), &web), (PathBuf::from("nodejs"), &node)];
+        let package = package_json(&metadata, &outputs, &["README.md".to_string()]);
+
+        let conditions: Vec<&String> = package["exports"]["."].as_object().unwrap().keys().collect();
+        assert_eq!(conditions, vec!["types", "node", "browser", "default"]);
+        assert_eq!(package["exports"]["."]["types"], "./web/rig_wasm.d.ts");
+        assert_eq!(package["exports"]["."]["default"], "./web/rig_wasm.js");
+        assert_eq!(package["main"], "nodejs/rig_wasm.js");
+        assert!(package["files"].as_array().unwrap().contains(&json!("README.md")));
+    }
+
+    #[test]
+    fn test_tar_header() {
+        let header = tar_header("package/rig_wasm.js", 10).unwrap();
+        assert_eq!(&header[..19], b"package/rig_wasm.js");
+        assert_eq!(&header[124..136], b"00000000012\0");
+        assert_eq!(&header[257..262], b"ustar");
+
+        let checksum = u32::from_str_radix(std::str::from_utf8(&header[148..154]).unwrap(), 8).unwrap();
+        let mut unsummed = header;
+        unsummed[148..156].fill(b' ');
+        assert_eq!(checksum, unsummed.iter().map(|&b| b as u32).sum::<u32>());
+
+        let long = format!("package/{}/rig_wasm.js", "nested".repeat(20));
+        let header = tar_header(&long, 0).unwrap();
+        assert_eq!(&header[..11], b"rig_wasm.js");
+        assert!(tar_header(&"x".repeat(300), 0).is_err());
+    }
+
+    #[test]
+    fn test_pack() {
+        let dir = std::env::temp_dir().join("rig-wasm-compiler-pack-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("pkg")).unwrap();
+        fs::write(dir.join("pkg/simple_agent.js"), "export {}").unwrap();
+        fs::write(dir.join("pkg/simple_agent_bg.wasm"), b"\0asm\x01\0\0\0").unwrap();
+
+        let options = CompileOptions::new("examples/simple_agent.rs", dir.join("pkg")).with_out_name("simple_agent");
+        let report = pack(&[options], PackageMetadata::default(), &dir).unwrap();
+
+        assert_eq!(report.tarball, dir.join("simple_agent-0.1.0.tgz"));
+        let paths: Vec<String> = report.files.iter().map(|f| f.path.display().to_string()).collect();
+        assert_eq!(paths, vec!["package.json", "simple_agent.js", "simple_agent_bg.wasm"]);
+
+        let mut tar = Vec::new();
+        std::io::Read::read_to_end(&mut flate2::read::GzDecoder::new(fs::File::open(&report.tarball).unwrap()), &mut tar)
+            .unwrap();
+        assert_eq!(&tar[..20], b"package/package.json");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,268 @@
+// Named build presets. Each one sets the cargo profile options that matter for
+// wasm through CARGO_PROFILE_* variables, so the user's Cargo.toml is never
+// edited, and picks a wasm-opt level and whether the panic hook is compiled in.
+use crate::compiler::error::CompileError;
+use crate::compiler::optimize::OptLevel;
+use crate::compiler::Profile;
+use clap::ArgEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+// The cargo feature that installs console_error_panic_hook
+pub const PANIC_HOOK_FEATURE: &str = "console_error_panic_hook";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    // Smallest module: opt-level z, fat LTO, one codegen unit, wasm-opt -Oz
+    Size,
+    // Fastest module: opt-level 3, fat LTO, one codegen unit, wasm-opt -O3
+    Speed,
+    // Quick builds with debug info and readable panics
+    Debug,
+}
+
+impl Preset {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Preset::Size => "size",
+            Preset::Speed => "speed",
+            Preset::Debug => "debug",
+        }
+    }
+
+    pub fn settings(&self) -> PresetSettings {
+        match self {
+            Preset::Size => PresetSettings {
+                preset: *self,
+                cargo_profile: Profile::Release,
+                opt_level: "z".to_string(),
+                lto: "fat".to_string(),
+                codegen_units: 1,
+                // wasm32 has no unwinding; abort also drops the unwind tables
+                panic: "abort".to_string(),
+                debug_info: false,
+                wasm_opt: OptLevel::Oz,
+                panic_hook: false,
+            },
+            Preset::Speed => PresetSettings {
+                preset: *self,
+                cargo_profile: Profile::Release,
+                opt_level: "3".to_string(),
+                lto: "fat".to_string(),
+                codegen_units: 1,
+                panic: "abort".to_string(),
+                debug_info: false,
+                wasm_opt: OptLevel::O3,
+                panic_hook: false,
+            },
+            Preset::Debug => PresetSettings {
+                preset: *self,
+                cargo_profile: Profile::Dev,
+                opt_level: "0".to_string(),
+                lto: "off".to_string(),
+                codegen_units: 256,
+                panic: "abort".to_string(),
+                debug_info: true,
+                wasm_opt: OptLevel::O0,
+                panic_hook: true,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// What a preset sets. Explicit --opt-level and profile settings win over
+// `cargo_profile` and `wasm_opt`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PresetSettings {
+    pub preset: Preset,
+    pub cargo_profile: Profile,
+    // rustc opt-level
+    pub opt_level: String,
+    pub lto: String,
+    pub codegen_units: u32,
+    pub panic: String,
+    pub debug_info: bool,
+    pub wasm_opt: OptLevel,
+    pub panic_hook: bool,
+}
+
+impl PresetSettings {
+    // CARGO_PROFILE_* overrides for the cargo profile `profile` builds with.
+    // wasm-pack's profiling profile is cargo's release profile.
+    pub fn cargo_env(&self, profile: Profile) -> Vec<(&'static str, String)> {
+        let names = match profile {
+            Profile::Dev => [
+                "CARGO_PROFILE_DEV_OPT_LEVEL",
+                "CARGO_PROFILE_DEV_LTO",
+                "CARGO_PROFILE_DEV_CODEGEN_UNITS",
+                "CARGO_PROFILE_DEV_PANIC",
+                "CARGO_PROFILE_DEV_DEBUG",
+            ],
+            Profile::Profiling | Profile::Release => [
+                "CARGO_PROFILE_RELEASE_OPT_LEVEL",
+                "CARGO_PROFILE_RELEASE_LTO",
+                "CARGO_PROFILE_RELEASE_CODEGEN_UNITS",
+                "CARGO_PROFILE_RELEASE_PANIC",
+                "CARGO_PROFILE_RELEASE_DEBUG",
+            ],
+        };
+        let values = [
+            self.opt_level.clone(),
+            self.lto.clone(),
+            self.codegen_units.to_string(),
+            self.panic.clone(),
+            self.debug_info.to_string(),
+        ];
+        names.into_iter().zip(values).collect()
+    }
+}
+
+// Whether the panic hook made it into the build
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PanicHook {
+    Included,
+    Excluded,
+    // The crate has no console_error_panic_hook feature to switch
+    Unavailable,
+}
+
+// The settings a build used, for the compile report
+#[derive(Debug, Clone, Serialize)]
+pub struct PresetReport {
+    #[serde(flatten)]
+    pub settings: PresetSettings,
+    // The cargo profile and wasm-opt level actually used, after explicit settings
+    pub profile: Profile,
+    pub wasm_opt_level: OptLevel,
+    pub panic_hook_status: PanicHook,
+}
+
+impl fmt::Display for PresetReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let settings = &self.settings;
+        writeln!(f, "Preset: {}", settings.preset)?;
+        writeln!(
+            f,
+            "  cargo {} profile: opt-level={} lto={} codegen-units={} panic={} debug={}",
+            self.profile,
+            settings.opt_level,
+            settings.lto,
+            settings.codegen_units,
+            settings.panic,
+            settings.debug_info
+        )?;
+        writeln!(f, "  wasm-opt: -{}", self.wasm_opt_level)?;
+        let hook = match self.panic_hook_status {
+            PanicHook::Included => "included",
+            PanicHook::Excluded => "excluded",
+            PanicHook::Unavailable => "unavailable (no console_error_panic_hook feature)",
+        };
+        write!(f, "  {}: {}", PANIC_HOOK_FEATURE, hook)
+    }
+}
+
+// Switches the crate's console_error_panic_hook feature on or off by adjusting
+// the feature list. Turning off a default feature means building without
+// default features and re-enabling the others. A feature the user asked for
+// explicitly is never removed.
+pub fn apply_panic_hook(
+    manifest_path: &Path,
+    include: bool,
+    features: &mut Vec<String>,
+    no_default_features: &mut bool,
+) -> Result<PanicHook, CompileError> {
+    let contents = fs::read_to_string(manifest_path)?;
+    let manifest: toml::Value = toml::from_str(&contents)
+        .map_err(|e| CompileError::InvalidInput(format!("{}: {}", manifest_path.display(), e)))?;
+
+    let declared = manifest.get("features").and_then(|f| f.as_table());
+    // An optional dependency is an implicit feature of the same name
+    let optional_dependency = manifest
+        .get("dependencies")
+        .and_then(|deps| deps.get(PANIC_HOOK_FEATURE))
+        .and_then(|dep| dep.get("optional"))
+        .and_then(|optional| optional.as_bool())
+        .unwrap_or(false);
+    if !declared.is_some_and(|f| f.contains_key(PANIC_HOOK_FEATURE)) && !optional_dependency {
+        return Ok(PanicHook::Unavailable);
+    }
+
+    if features.iter().any(|f| f == PANIC_HOOK_FEATURE) {
+        return Ok(PanicHook::Included);
+    }
+    let defaults: Vec<String> = declared
+        .and_then(|f| f.get("default"))
+        .and_then(|d| d.as_array())
+        .map(|d| d.iter().filter_map(|f| f.as_str().map(str::to_string)).collect())
+        .unwrap_or_default();
+    let enabled_by_default = !*no_default_features && defaults.iter().any(|f| f == PANIC_HOOK_FEATURE);
+
+    if include {
+        if !enabled_by_default {
+            features.push(PANIC_HOOK_FEATURE.to_string());
+        }
+        Ok(PanicHook::Included)
+    } else {
+        if enabled_by_default {
+            *no_default_features = true;
+            features.extend(defaults.into_iter().filter(|f| f != PANIC_HOOK_FEATURE));
+        }
+        Ok(PanicHook::Excluded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cargo_env() {
+        let env = Preset::Size.settings().cargo_env(Profile::Release);
+        assert!(env.contains(&("CARGO_PROFILE_RELEASE_OPT_LEVEL", "z".to_string())));
+        assert!(env.contains(&("CARGO_PROFILE_RELEASE_CODEGEN_UNITS", "1".to_string())));
+
+        let env = Preset::Debug.settings().cargo_env(Profile::Dev);
+        assert!(env.contains(&("CARGO_PROFILE_DEV_DEBUG", "true".to_string())));
+    }
+
+    #[test]
+    fn test_apply_panic_hook() {
+        let dir = std::env::temp_dir().join("rig-wasm-compiler-preset-test");
+        fs::create_dir_all(&dir).unwrap();
+        let manifest = dir.join("Cargo.toml");
+        fs::write(
+            &manifest,
+            "[package]\nname = \"demo\"\n\n[features]\ndefault = [\"console_error_panic_hook\", \"openai\"]\nopenai = []\n\n\
+             [dependencies]\nconsole_error_panic_hook = { version = \"0.1\", optional = true }\n",
+        )
+        .unwrap();
+
+        // Excluding a default feature rebuilds the default set without it
+        let (mut features, mut no_default) = (Vec::new(), false);
+        let status = apply_panic_hook(&manifest, false, &mut features, &mut no_default).unwrap();
+        assert_eq!(status, PanicHook::Excluded);
+        assert!(no_default);
+        assert_eq!(features, vec!["openai"]);
+
+        let (mut features, mut no_default) = (Vec::new(), true);
+        let status = apply_panic_hook(&manifest, true, &mut features, &mut no_default).unwrap();
+        assert_eq!(status, PanicHook::Included);
+        assert_eq!(features, vec![PANIC_HOOK_FEATURE]);
+
+        fs::write(&manifest, "[package]\nname = \"demo\"\n").unwrap();
+        let status = apply_panic_hook(&manifest, true, &mut Vec::new(), &mut false).unwrap();
+        assert_eq!(status, PanicHook::Unavailable);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+use crate::compiler::diagnostics::{emit_diagnostic, emit_diagnostic_stderr, Diagnostic, MessageFormat};
+use serde::Serialize;
+use std::fmt;
+use std::time::Duration;
+
+// The stages a compilation goes through, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Phase {
+    // Turning the input into a buildable crate
+    Resolve,
+    // Looking up the build in the build cache
+    Cache,
+    // Rewriting a copy of the sources for wasm32 (with --shim)
+    Shim,
+    // Static analysis for APIs and crates that do not work on wasm32
+    Lint,
+    // `cargo build --target wasm32-unknown-unknown`
+    CargoBuild,
+    // wasm-bindgen via wasm-pack
+    Bindgen,
+    // Post-processing of the emitted .wasm
+    Optimize,
+    // Checking the output directory and collecting artifacts
+    Package,
+}
+
+impl Phase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Phase::Resolve => "resolve",
+            Phase::Cache => "cache",
+            Phase::Shim => "shim",
+            Phase::Lint => "lint",
+            Phase::CargoBuild => "cargo-build",
+            Phase::Bindgen => "bindgen",
+            Phase::Optimize => "optimize",
+            Phase::Package => "package",
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Receives progress from a running `Compiler`. Every method has a no-op default
+// so embedders only implement what they care about.
+pub trait CompileObserver {
+    fn phase_started(&mut self, _phase: Phase) {}
+
+    fn phase_finished(&mut self, _phase: Phase, _elapsed: Duration) {}
+
+    fn diagnostic(&mut self, _diagnostic: &Diagnostic) {}
+
+    fn warning(&mut self, _message: &str) {}
+}
+
+// Observer that ignores everything
+pub struct NoopObserver;
+
+impl CompileObserver for NoopObserver {}
+
+// Observer used by the CLI: human-readable progress on stderr, or JSON records on stdout
+pub struct ConsoleObserver {
+    format: MessageFormat,
+    // JSON records go to stderr too, for commands whose stdout carries something else
+    to_stderr: bool,
+}
+
+impl ConsoleObserver {
+    pub fn new(format: MessageFormat) -> Self {
+        ConsoleObserver { format, to_stderr: false }
+    }
+
+    pub fn with_stderr(mut self, to_stderr: bool) -> Self {
+        self.to_stderr = to_stderr;
+        self
+    }
+
+    fn record(&self, record: serde_json::Value) {
+        if self.to_stderr {
+            eprintln!("{}", record);
+        } else {
+            println!("{}", record);
+        }
+    }
+}
+
+impl CompileObserver for ConsoleObserver {
+    fn phase_started(&mut self, phase: Phase) {
+        match self.format {
+            MessageFormat::Human => eprintln!("==> {}", phase),
+            MessageFormat::Json => self.record(serde_json::json!({ "reason": "phase-started", "phase": phase })),
+        }
+    }
+
+    fn phase_finished(&mut self, phase: Phase, elapsed: Duration) {
+        if self.format == MessageFormat::Json {
+            let record = serde_json::json!({
+                "reason": "phase-finished",
+                "phase": phase,
+                "elapsed_ms": elapsed.as_millis() as u64,
+            });
+            self.record(record);
+        }
+    }
+
+    fn diagnostic(&mut self, diagnostic: &Diagnostic) {
+        if self.to_stderr {
+            emit_diagnostic_stderr(self.format, diagnostic);
+        } else {
+            emit_diagnostic(self.format, diagnostic);
+        }
+    }
+
+    fn warning(&mut self, message: &str) {
+        match self.format {
+            MessageFormat::Human => eprintln!("warning: {}", message),
+            MessageFormat::Json => self.record(serde_json::json!({ "reason": "warning", "message": message })),
+        }
+    }
+}
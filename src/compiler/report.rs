@@ -0,0 +1,209 @@
+use crate::compiler::budget::BudgetReport;
+use crate::compiler::csp::HeadersReport;
+use crate::compiler::integrity::IntegrityReport;
+use crate::compiler::optimize::OptimizeReport;
+use crate::compiler::preset::PresetReport;
+use crate::compiler::secrets::ConfigReport;
+use crate::compiler::shim::ShimReport;
+use crate::compiler::target::Target;
+use crate::compiler::worker::WorkerReport;
+use crate::compiler::Profile;
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// A file written to the output directory
+#[derive(Debug, Clone, Serialize)]
+pub struct Artifact {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+// Summary of a successful compilation
+#[derive(Debug, Clone, Serialize)]
+pub struct CompileReport {
+    pub package_name: String,
+    pub target: Target,
+    pub profile: Profile,
+    pub output_dir: PathBuf,
+    pub artifacts: Vec<Artifact>,
+    pub optimization: Option<OptimizeReport>,
+    pub budget: Option<BudgetReport>,
+    pub shim: Option<ShimReport>,
+    pub embedded_config: Option<ConfigReport>,
+    // Settings injected by --profile size|speed|debug
+    pub preset: Option<PresetReport>,
+    // Function symbols of a debug build, for symbolizing stack traces
+    pub symbol_map: Option<PathBuf>,
+    // Worker entry and main-thread proxy written with --emit-worker
+    pub worker: Option<WorkerReport>,
+    // Content-Security-Policy written with --emit-headers
+    pub headers: Option<HeadersReport>,
+    // Index module re-exporting the entries that share the output directory
+    pub index: Option<PathBuf>,
+    // SRI manifest of a reproducible build
+    pub integrity: Option<IntegrityReport>,
+    // Build cache key; None when the cache was off
+    pub cache_key: Option<String>,
+    // The output was copied from the build cache instead of being built
+    pub cached: bool,
+}
+
+impl CompileReport {
+    pub fn artifact(&self, file_name: &str) -> Option<&Artifact> {
+        self.artifacts
+            .iter()
+            .find(|a| a.path.file_name().is_some_and(|n| n == file_name))
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.artifacts.iter().map(|a| a.size).sum()
+    }
+}
+
+// Lists the files in `output_dir`, sorted by name
+pub fn collect_artifacts(output_dir: &Path) -> io::Result<Vec<Artifact>> {
+    let mut artifacts = Vec::new();
+    for entry in fs::read_dir(output_dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            artifacts.push(Artifact {
+                path: entry.path(),
+                size: metadata.len(),
+            });
+        }
+    }
+    artifacts.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(artifacts)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.2} MiB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+impl fmt::Display for CompileReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Built crate: {}", self.package_name)?;
+        writeln!(f, "Target: {}", self.target)?;
+        writeln!(f, "Profile: {}", self.profile)?;
+        if let Some(preset) = &self.preset {
+            writeln!(f, "{}", preset)?;
+        }
+        writeln!(f, "Output directory: {}", self.output_dir.display())?;
+        if let Some(key) = &self.cache_key {
+            let status = if self.cached { "hit" } else { "miss" };
+            writeln!(f, "Cache: {} ({})", status, &key[..12.min(key.len())])?;
+        }
+        if let Some(symbol_map) = &self.symbol_map {
+            writeln!(f, "Debug info: DWARF and name section kept, symbols in {}", symbol_map.display())?;
+        }
+        if let Some(shim) = &self.shim {
+            writeln!(f, "{}", shim)?;
+        }
+        if let Some(config) = &self.embedded_config {
+            writeln!(f, "{}", config)?;
+        }
+        for artifact in &self.artifacts {
+            let name = artifact.path.file_name().unwrap_or_default().to_string_lossy();
+            writeln!(f, "  {:<32} {:>12}", name, format_size(artifact.size))?;
+        }
+        write!(f, "  {:<32} {:>12}", "total", format_size(self.total_size()))?;
+
+        if let Some(optimization) = &self.optimization {
+            let name = optimization.file.file_name().unwrap_or_default().to_string_lossy();
+            let stage = if optimization.wasm_opt_skipped {
+                "strip only, wasm-opt not installed".to_string()
+            } else {
+                format!("wasm-opt -{}", optimization.level)
+            };
+            writeln!(f)?;
+            writeln!(f, "Optimization ({}):", stage)?;
+            writeln!(f, "  {:<32} {:>12} {:>12} {:>9}", "file", "before", "after", "change")?;
+            write!(
+                f,
+                "  {:<32} {:>12} {:>12} {:>8.1}%",
+                name,
+                format_size(optimization.before),
+                format_size(optimization.after),
+                optimization.change_percent()
+            )?;
+        }
+
+        if let Some(budget) = &self.budget {
+            writeln!(f)?;
+            writeln!(f, "Size budget (within limits):")?;
+            writeln!(f, "  {:<8} {:>12} {:>12} {:>12}", "", "raw", "gzip", "brotli")?;
+            let rows = [("wasm", &budget.wasm), ("js", &budget.js), ("total", &budget.total)];
+            for (i, (name, size)) in rows.iter().enumerate() {
+                write!(
+                    f,
+                    "  {:<8} {:>12} {:>12} {:>12}",
+                    name,
+                    format_size(size.raw),
+                    format_size(size.gzip),
+                    format_size(size.brotli)
+                )?;
+                if i + 1 < rows.len() {
+                    writeln!(f)?;
+                }
+            }
+        }
+
+        if let Some(worker) = &self.worker {
+            writeln!(f)?;
+            write!(f, "{}", worker)?;
+        }
+
+        if let Some(headers) = &self.headers {
+            writeln!(f)?;
+            write!(f, "{}", headers)?;
+        }
+
+        if let Some(index) = &self.index {
+            writeln!(f)?;
+            write!(f, "Package index: {}", index.display())?;
+        }
+
+        if let Some(integrity) = &self.integrity {
+            writeln!(f)?;
+            write!(f, "{}", integrity)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(2048), "2.0 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.00 MiB");
+    }
+
+    #[test]
+    fn test_collect_artifacts() {
+        let dir = std::env::temp_dir().join("rig-wasm-compiler-report-test");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("b.js"), "export {}").unwrap();
+        fs::write(dir.join("a_bg.wasm"), [0u8; 16]).unwrap();
+
+        let artifacts = collect_artifacts(&dir).unwrap();
+        assert_eq!(artifacts[0].path.file_name().unwrap(), "a_bg.wasm");
+        assert_eq!(artifacts[0].size, 16);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
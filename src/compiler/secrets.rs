@@ -0,0 +1,341 @@
+use crate::compiler::error::CompileError;
+use crate::compiler::shim::CONFIG_MODULE;
+use crate::config::{WasmConfig, SECRET_PLACEHOLDER};
+use clap::ArgEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Environment variable the embedded config is passed to rustc in, for this
+// crate's initialize_rig_wasm. User crates get it as a generated module instead,
+// see `shim::embed_config`.
+pub const CONFIG_ENV: &str = "RIG_WASM_CONFIG";
+
+// What to do with secret fields (such as `api_key`) in a config given to --config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ArgEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SecretPolicy {
+    // Fail the build
+    #[default]
+    Reject,
+    // Replace the value with a placeholder that must be filled in at runtime
+    Placeholder,
+}
+
+// Shapes of well-known API keys: a prefix followed by at least `min_len` key characters
+struct KeyPattern {
+    prefix: &'static str,
+    min_len: usize,
+    name: &'static str,
+}
+
+// More specific prefixes come first
+const KEY_PATTERNS: &[KeyPattern] = &[
+    KeyPattern { prefix: "sk-ant-", min_len: 32, name: "Anthropic API key" },
+    KeyPattern { prefix: "sk-", min_len: 20, name: "OpenAI API key" },
+    KeyPattern { prefix: "AIza", min_len: 35, name: "Google API key" },
+    KeyPattern { prefix: "xai-", min_len: 20, name: "xAI API key" },
+    KeyPattern { prefix: "gsk_", min_len: 20, name: "Groq API key" },
+    KeyPattern { prefix: "pplx-", min_len: 20, name: "Perplexity API key" },
+    KeyPattern { prefix: "hf_", min_len: 30, name: "Hugging Face token" },
+    KeyPattern { prefix: "r8_", min_len: 30, name: "Replicate API token" },
+];
+
+// Secret values shorter than this are not searched for, to avoid matching by chance
+const MIN_SECRET_LEN: usize = 8;
+
+// A secret found in a config file or in the build output
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SecretMatch {
+    pub file: PathBuf,
+    // The config field, or the byte offset in an emitted file
+    pub location: String,
+    pub kind: String,
+    // The first few characters, never the whole secret
+    pub preview: String,
+}
+
+impl fmt::Display for SecretMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {} ({})", self.file.display(), self.location, self.kind, self.preview)
+    }
+}
+
+fn redact(secret: &str) -> String {
+    let shown: String = secret.chars().take(8.min(secret.chars().count() / 2)).collect();
+    format!("{}…", shown)
+}
+
+// A config ready to be baked into the module
+#[derive(Debug, Clone)]
+pub struct EmbeddedConfig {
+    pub path: PathBuf,
+    // The config as JSON, with secrets replaced by placeholders
+    pub json: String,
+    // Fields the page has to supply to initialize_rig_wasm
+    pub placeholders: Vec<String>,
+    // The secrets that were taken out, so the output can be checked for them
+    secret_values: Vec<String>,
+}
+
+impl EmbeddedConfig {
+    pub fn report(&self) -> ConfigReport {
+        ConfigReport {
+            path: self.path.clone(),
+            placeholders: self.placeholders.clone(),
+        }
+    }
+}
+
+// What the build report shows about the embedded config
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigReport {
+    pub path: PathBuf,
+    pub placeholders: Vec<String>,
+}
+
+impl fmt::Display for ConfigReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Embedded config: {} (rigWasmConfig() in JS, crate::{}::CONFIG in Rust)", self.path.display(), CONFIG_MODULE)?;
+        if !self.placeholders.is_empty() {
+            write!(f, " (supply at runtime: {})", self.placeholders.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// Keys in `additional_params` that hold credentials
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    key.ends_with("api_key")
+        || key.ends_with("apikey")
+        || key.ends_with("_token")
+        || key == "token"
+        || key.contains("secret")
+        || key.contains("password")
+        || key == "authorization"
+}
+
+// The secret fields of a config: every provider's `api_key`, and credential-like
+// keys in its `additional_params`
+fn secret_fields(config: &mut Value) -> Vec<(String, &mut Value)> {
+    let mut fields = Vec::new();
+    let providers = match config.get_mut("providers").and_then(Value::as_object_mut) {
+        Some(providers) => providers,
+        None => return fields,
+    };
+    for (name, provider) in providers.iter_mut() {
+        let provider = match provider.as_object_mut() {
+            Some(provider) => provider,
+            None => continue,
+        };
+        for (key, value) in provider.iter_mut() {
+            if key == "api_key" {
+                fields.push((format!("providers.{}.api_key", name), value));
+            } else if key == "additional_params" {
+                if let Some(params) = value.as_object_mut() {
+                    for (param, value) in params.iter_mut() {
+                        if is_secret_key(param) {
+                            fields.push((format!("providers.{}.additional_params.{}", name, param), value));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    fields
+}
+
+// Reads a WasmConfig (JSON, or TOML by extension) and prepares it for embedding.
+// Empty secrets and existing placeholders pass; any other secret is rejected
+// or replaced by a placeholder according to `policy`.
+pub fn embed_config(path: &Path, policy: SecretPolicy) -> Result<EmbeddedConfig, CompileError> {
+    if !path.is_file() {
+        return Err(CompileError::InputNotFound(path.display().to_string()));
+    }
+    let contents = fs::read_to_string(path)?;
+    let invalid = |e: &dyn fmt::Display| CompileError::InvalidInput(format!("Invalid config {}: {}", path.display(), e));
+
+    let mut config: Value = if path.extension().is_some_and(|ext| ext == "toml") {
+        toml::from_str(&contents).map_err(|e| invalid(&e))?
+    } else {
+        serde_json::from_str(&contents).map_err(|e| invalid(&e))?
+    };
+    serde_json::from_value::<WasmConfig>(config.clone()).map_err(|e| invalid(&e))?;
+
+    let mut rejected = Vec::new();
+    let mut placeholders = Vec::new();
+    let mut secret_values = Vec::new();
+    for (field, value) in secret_fields(&mut config) {
+        let secret = match value.as_str() {
+            Some(secret) if !secret.is_empty() && !secret.starts_with(SECRET_PLACEHOLDER) => secret.to_string(),
+            Some(secret) if secret.starts_with(SECRET_PLACEHOLDER) => {
+                placeholders.push(field);
+                continue;
+            }
+            _ => continue,
+        };
+        match policy {
+            SecretPolicy::Reject => rejected.push(SecretMatch {
+                file: path.to_path_buf(),
+                location: field,
+                kind: "secret config field".to_string(),
+                preview: redact(&secret),
+            }),
+            SecretPolicy::Placeholder => {
+                *value = Value::String(format!("{}{}", SECRET_PLACEHOLDER, field));
+                placeholders.push(field);
+            }
+        }
+        secret_values.push(secret);
+    }
+
+    if !rejected.is_empty() {
+        return Err(CompileError::SecretFound { secrets: rejected });
+    }
+
+    Ok(EmbeddedConfig {
+        path: path.to_path_buf(),
+        json: config.to_string(),
+        placeholders,
+        secret_values,
+    })
+}
+
+fn is_key_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_'
+}
+
+// Finds strings shaped like API keys in `bytes`, plus any of `secrets` verbatim
+pub fn scan_bytes(bytes: &[u8], secrets: &[String]) -> Vec<(usize, String, String)> {
+    let mut found = Vec::new();
+
+    let mut i = 0;
+    while i < bytes.len() {
+        // Keys start at a word boundary
+        if i > 0 && is_key_char(bytes[i - 1]) {
+            i += 1;
+            continue;
+        }
+        let pattern = KEY_PATTERNS.iter().find(|p| bytes[i..].starts_with(p.prefix.as_bytes()));
+        if let Some(pattern) = pattern {
+            let body_start = i + pattern.prefix.len();
+            let body_len = bytes[body_start..].iter().take_while(|&&b| is_key_char(b)).count();
+            let body = &bytes[body_start..body_start + body_len];
+            // Real keys are random: long enough and a mix of letters and digits
+            if body_len >= pattern.min_len
+                && body.iter().any(u8::is_ascii_digit)
+                && body.iter().any(u8::is_ascii_alphabetic)
+            {
+                let key = String::from_utf8_lossy(&bytes[i..body_start + body_len]).into_owned();
+                found.push((i, pattern.name.to_string(), redact(&key)));
+                i = body_start + body_len;
+                continue;
+            }
+        }
+        i += 1;
+    }
+
+    for secret in secrets.iter().filter(|secret| secret.len() >= MIN_SECRET_LEN) {
+        let needle = secret.as_bytes();
+        if let Some(offset) = bytes.windows(needle.len()).position(|window| window == needle) {
+            if !found.iter().any(|(at, _, _)| *at == offset) {
+                found.push((offset, "secret from the config file".to_string(), redact(secret)));
+            }
+        }
+    }
+
+    found.sort_by_key(|(offset, _, _)| *offset);
+    found
+}
+
+// Scans the emitted .wasm and .js files for API keys, including the secrets
+// taken out of the embedded config
+pub fn scan_output(files: &[PathBuf], config: Option<&EmbeddedConfig>) -> Result<Vec<SecretMatch>, CompileError> {
+    let secrets = config.map(|config| config.secret_values.as_slice()).unwrap_or_default();
+    let mut matches = Vec::new();
+    for file in files.iter().filter(|file| file.is_file()) {
+        let bytes = fs::read(file)?;
+        for (offset, kind, preview) in scan_bytes(&bytes, secrets) {
+            matches.push(SecretMatch {
+                file: file.clone(),
+                location: format!("at byte {}", offset),
+                kind,
+                preview,
+            });
+        }
+    }
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(name: &str, contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("rig-wasm-compiler-secrets-test");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    const CONFIG: &str = r#"{
+        "providers": {
+            "openai": {
+                "api_key": "sk-test-0123456789abcdefghij",
+                "model": "gpt-4o",
+                "api_base_url": null,
+                "additional_params": { "organization": "org-1", "max_tokens": 100 }
+            },
+            "local": { "api_key": "", "model": "llama3", "api_base_url": "http://localhost:11434" }
+        },
+        "max_tokens": 256,
+        "temperature": 0.2,
+        "request_timeout": null,
+        "vector_store": null
+    }"#;
+
+    #[test]
+    fn test_embed_config_rejects_secrets() {
+        let path = write_config("reject.json", CONFIG);
+        match embed_config(&path, SecretPolicy::Reject) {
+            Err(CompileError::SecretFound { secrets }) => {
+                assert_eq!(secrets.len(), 1);
+                assert_eq!(secrets[0].location, "providers.openai.api_key");
+                assert!(!secrets[0].preview.contains("0123456789"));
+            }
+            other => panic!("expected SecretFound, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_embed_config_placeholders() {
+        let path = write_config("placeholder.json", CONFIG);
+        let embedded = embed_config(&path, SecretPolicy::Placeholder).unwrap();
+        assert_eq!(embedded.placeholders, vec!["providers.openai.api_key"]);
+        assert!(!embedded.json.contains("sk-test"));
+        assert!(embedded.json.contains("$secret:providers.openai.api_key"));
+
+        // The page fills the placeholder in at runtime
+        let runtime = r#"{"providers": {"openai": {"api_key": "sk-runtime"}}}"#;
+        let config = WasmConfig::from_embedded(&embedded.json, runtime).unwrap();
+        assert_eq!(config.providers["openai"].api_key, "sk-runtime");
+        assert_eq!(config.providers["openai"].model, "gpt-4o");
+        assert!(WasmConfig::from_embedded(&embedded.json, "").is_err());
+    }
+
+    #[test]
+    fn test_scan_bytes() {
+        let bytes = b"\0\0const key = \"sk-proj-Ab3dEf6hIj9kLmN0pQrStUvWx\";\0task-manager-component-name-here\0";
+        let found = scan_bytes(bytes, &[]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].1, "OpenAI API key");
+        assert_eq!(found[0].0, 15);
+
+        let found = scan_bytes(b"xx my-custom-secret-value xx", &["my-custom-secret-value".to_string()]);
+        assert_eq!(found, vec![(3, "secret from the config file".to_string(), "my-custo…".to_string())]);
+    }
+}
@@ -0,0 +1,1116 @@
+use crate::compiler::error::CompileError;
+use crate::compiler::input::ResolvedInput;
+use crate::compiler::lint::compiled_out;
+use crate::compiler::toolchain::find_lock_file;
+use proc_macro2::{LineColumn, Span};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+// Module added to the shimmed crate for the HTTP client and start helpers
+pub const SHIM_MODULE: &str = "__rig_wasm_shim";
+
+// Module added to a copy of the crate for the config given to --config
+pub const CONFIG_MODULE: &str = "__rig_wasm_config";
+
+const WASM_HTTP_CLIENT: &str = include_str!("../adapters/http_client.rs");
+const SHIM_SUPPORT: &str = include_str!("templates/shim_support.rs");
+const CONFIG_SUPPORT: &str = include_str!("templates/config_support.rs");
+const TOKIO_ALIAS: &str = "tokio_with_wasm::alias";
+const WASM_CLIENT_TYPE: &str = "crate::__rig_wasm_shim::WasmHttpClient";
+const WASM_CLIENT_EXPR: &str = "crate::__rig_wasm_shim::WasmHttpClient::new()";
+
+// Directories at the crate root never copied into the shimmed crate
+const SKIPPED_DIRS: &[&str] = &["target", ".git", "node_modules", "pkg"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RewriteKind {
+    // `tokio::` paths pointed at `tokio_with_wasm::alias`
+    TokioPath,
+    // `#[tokio::main]` replaced with a wasm_bindgen start function
+    TokioMain,
+    // A native HTTP client handed to a Rig provider, replaced with WasmHttpClient
+    HttpClient,
+}
+
+impl fmt::Display for RewriteKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RewriteKind::TokioPath => "tokio-path",
+            RewriteKind::TokioMain => "tokio-main",
+            RewriteKind::HttpClient => "http-client",
+        })
+    }
+}
+
+// One change the shim made, located in the original source
+#[derive(Debug, Clone, Serialize)]
+pub struct Rewrite {
+    pub kind: RewriteKind,
+    pub file: PathBuf,
+    pub line: usize,
+    pub original: String,
+    pub replacement: String,
+}
+
+// What the shim did to the input crate
+#[derive(Debug, Clone, Serialize)]
+pub struct ShimReport {
+    // The shimmed copy that was built
+    pub crate_dir: PathBuf,
+    // The crate (or .rs file) it was copied from
+    pub source_dir: PathBuf,
+    pub source_file: Option<PathBuf>,
+    pub rewrites: Vec<Rewrite>,
+    // Where the shimmed sources were written for review, if requested
+    pub emitted: Option<PathBuf>,
+}
+
+impl ShimReport {
+    // Maps a file in the shimmed crate back to the file it was made from
+    pub fn original_path(&self, path: &Path) -> PathBuf {
+        if let Some(source_file) = &self.source_file {
+            let src_dir = self.crate_dir.join("src");
+            if path == src_dir.join("lib.rs") {
+                return source_file.clone();
+            }
+            // The modules of a wrapped .rs file are copied next to it in src/
+            if let (Ok(relative), Some(dir)) = (path.strip_prefix(&src_dir), source_file.parent()) {
+                return dir.join(relative);
+            }
+        }
+        match path.strip_prefix(&self.crate_dir) {
+            Ok(relative) => self.source_dir.join(relative),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+}
+
+impl fmt::Display for ShimReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Shim: {} rewrite(s)", self.rewrites.len())?;
+        for rewrite in &self.rewrites {
+            write!(
+                f,
+                "\n  {}:{:<6} {:<12} {} -> {}",
+                rewrite.file.display(),
+                rewrite.line,
+                rewrite.kind,
+                rewrite.original,
+                // Start functions span several lines
+                rewrite.replacement.replace('\n', "\n      ")
+            )?;
+        }
+        if let Some(emitted) = &self.emitted {
+            write!(f, "\nShimmed source: {}", emitted.display())?;
+        }
+        Ok(())
+    }
+}
+
+// What shimmed code needs from the support module and Cargo.toml
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Needs {
+    pub tokio_with_wasm: bool,
+    pub start: bool,
+    pub http_client: bool,
+}
+
+impl Needs {
+    fn merge(&mut self, other: Needs) {
+        self.tokio_with_wasm |= other.tokio_with_wasm;
+        self.start |= other.start;
+        self.http_client |= other.http_client;
+    }
+
+    fn support_module(&self) -> bool {
+        self.start || self.http_client
+    }
+}
+
+// A shimmed source file
+#[derive(Debug, Clone)]
+pub struct ShimmedSource {
+    pub source: String,
+    pub rewrites: Vec<Rewrite>,
+    pub needs: Needs,
+}
+
+// A text replacement; edits are applied to the original text so comments and
+// formatting survive
+struct Edit {
+    start: usize,
+    end: usize,
+    text: String,
+    // What to report; None for additions such as the start function
+    rewrite: Option<(RewriteKind, String)>,
+}
+
+// Converts proc-macro2 line/column locations into byte offsets
+struct LineIndex<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(source: &'a str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+        LineIndex { source, starts }
+    }
+
+    fn offset(&self, at: LineColumn) -> usize {
+        let start = match self.starts.get(at.line.saturating_sub(1)) {
+            Some(&start) => start,
+            None => return self.source.len(),
+        };
+        self.source[start..]
+            .char_indices()
+            .nth(at.column)
+            .map_or(self.source.len(), |(i, _)| start + i)
+    }
+
+    fn line(&self, offset: usize) -> usize {
+        self.starts.partition_point(|&start| start <= offset)
+    }
+}
+
+fn path_segments(path: &syn::Path) -> Vec<String> {
+    path.segments.iter().map(|segment| segment.ident.to_string()).collect()
+}
+
+fn is_tokio_main(attr: &syn::Attribute) -> bool {
+    path_segments(&attr.path) == ["tokio", "main"]
+}
+
+// Rig provider constructors: `OpenAIProvider::new(..)`, `providers::openai::Client::new(..)`
+fn is_provider_path(path: &syn::Path) -> bool {
+    let segments = path_segments(path);
+    segments[..segments.len().saturating_sub(1)]
+        .iter()
+        .any(|segment| segment.ends_with("Provider") || segment == "providers")
+}
+
+// Names a file binds with `use`: whether `tokio` is already an alias, and the
+// local names of reqwest's client types
+#[derive(Default)]
+struct Imports {
+    tokio_aliased: bool,
+    reqwest_types: HashSet<String>,
+}
+
+impl Imports {
+    fn collect(&mut self, prefix: &mut Vec<String>, tree: &syn::UseTree) {
+        match tree {
+            syn::UseTree::Path(path) => {
+                prefix.push(path.ident.to_string());
+                self.collect(prefix, &path.tree);
+                prefix.pop();
+            }
+            syn::UseTree::Name(name) => self.bind(prefix, &name.ident, &name.ident),
+            syn::UseTree::Rename(rename) => {
+                if rename.rename == "tokio" && !(prefix.is_empty() && rename.ident == "tokio") {
+                    self.tokio_aliased = true;
+                }
+                self.bind(prefix, &rename.ident, &rename.rename)
+            }
+            syn::UseTree::Glob(_) => {}
+            syn::UseTree::Group(group) => {
+                for tree in &group.items {
+                    self.collect(prefix, tree);
+                }
+            }
+        }
+    }
+
+    fn bind(&mut self, prefix: &[String], ident: &syn::Ident, local: &syn::Ident) {
+        let ident = ident.to_string();
+        if prefix == ["reqwest"] && (ident == "Client" || ident == "ClientBuilder") {
+            self.reqwest_types.insert(local.to_string());
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Imports {
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        self.collect(&mut Vec::new(), &item.tree);
+    }
+}
+
+// Finds reqwest client construction inside an expression
+struct NativeClient<'a> {
+    reqwest_types: &'a HashSet<String>,
+    found: bool,
+}
+
+impl<'ast> Visit<'ast> for NativeClient<'_> {
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        let segments = path_segments(path);
+        let native = match segments.as_slice() {
+            [krate, ty, ..] if krate == "reqwest" => ty == "Client" || ty == "ClientBuilder",
+            [first, ..] => self.reqwest_types.contains(first),
+            [] => false,
+        };
+        self.found |= native;
+        visit::visit_path(self, path);
+    }
+}
+
+struct ShimVisitor<'a> {
+    index: &'a LineIndex<'a>,
+    imports: Imports,
+    // `let client = reqwest::Client::new();` bindings in the current function:
+    // the initializer and, if annotated, the type
+    native_bindings: HashMap<String, (Span, Option<Span>)>,
+    edits: Vec<Edit>,
+    needs: Needs,
+}
+
+impl ShimVisitor<'_> {
+    fn replace(&mut self, span: Span, text: &str, kind: RewriteKind) {
+        let start = self.index.offset(span.start());
+        let end = self.index.offset(span.end());
+        self.edits.push(Edit {
+            start,
+            end,
+            text: text.to_string(),
+            rewrite: Some((kind, text.to_string())),
+        });
+    }
+
+    fn is_native_type(&self, path: &syn::Path) -> bool {
+        let segments = path_segments(path);
+        match segments.as_slice() {
+            [krate, ty] if krate == "reqwest" => ty == "Client",
+            [ty] => self.imports.reqwest_types.contains(ty),
+            _ => false,
+        }
+    }
+
+    fn is_native_expr(&self, expr: &syn::Expr) -> bool {
+        let mut finder = NativeClient {
+            reqwest_types: &self.imports.reqwest_types,
+            found: false,
+        };
+        finder.visit_expr(expr);
+        finder.found
+    }
+
+    // An argument handed to a provider: either a client built in place, or a
+    // local bound to one earlier in the function
+    fn substitute_client(&mut self, arg: &syn::Expr) {
+        let arg = match arg {
+            syn::Expr::Reference(reference) => &*reference.expr,
+            arg => arg,
+        };
+        if self.is_native_expr(arg) {
+            self.replace(arg.span(), WASM_CLIENT_EXPR, RewriteKind::HttpClient);
+            self.needs.http_client = true;
+            return;
+        }
+        if let syn::Expr::Path(path) = arg {
+            let binding = path.path.get_ident().and_then(|ident| self.native_bindings.remove(&ident.to_string()));
+            if let Some((init, ty)) = binding {
+                self.replace(init, WASM_CLIENT_EXPR, RewriteKind::HttpClient);
+                if let Some(ty) = ty {
+                    self.replace(ty, WASM_CLIENT_TYPE, RewriteKind::HttpClient);
+                }
+                self.needs.http_client = true;
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for ShimVisitor<'_> {
+    fn visit_item(&mut self, item: &'ast syn::Item) {
+        if !compiled_out(crate::compiler::lint::item_attrs(item)) {
+            visit::visit_item(self, item);
+        }
+    }
+
+    fn visit_impl_item_method(&mut self, method: &'ast syn::ImplItemMethod) {
+        if !compiled_out(&method.attrs) {
+            self.native_bindings.clear();
+            visit::visit_impl_item_method(self, method);
+        }
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast syn::ItemFn) {
+        self.native_bindings.clear();
+        visit::visit_item_fn(self, item);
+    }
+
+    // Attribute paths such as `#[tokio::main]` are handled separately
+    fn visit_attribute(&mut self, _attr: &'ast syn::Attribute) {}
+
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        if self.imports.tokio_aliased {
+            return;
+        }
+        let rewrite = match &item.tree {
+            syn::UseTree::Path(path) if path.ident == "tokio" => Some((path.ident.span(), TOKIO_ALIAS)),
+            syn::UseTree::Rename(rename) if rename.ident == "tokio" => Some((rename.ident.span(), TOKIO_ALIAS)),
+            syn::UseTree::Name(name) if name.ident == "tokio" => {
+                Some((name.ident.span(), "tokio_with_wasm::alias as tokio"))
+            }
+            _ => None,
+        };
+        if let Some((span, text)) = rewrite {
+            self.replace(span, text, RewriteKind::TokioPath);
+            self.needs.tokio_with_wasm = true;
+        }
+    }
+
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        if !self.imports.tokio_aliased && path.segments.len() > 1 && path.segments[0].ident == "tokio" {
+            self.replace(path.segments[0].ident.span(), TOKIO_ALIAS, RewriteKind::TokioPath);
+            self.needs.tokio_with_wasm = true;
+        }
+        visit::visit_path(self, path);
+    }
+
+    fn visit_type_path(&mut self, ty: &'ast syn::TypePath) {
+        // `OpenAIProvider<reqwest::Client>` becomes `OpenAIProvider<WasmHttpClient>`
+        if is_provider_path(&ty.path) || ty.path.segments.last().is_some_and(|s| s.ident.to_string().ends_with("Provider")) {
+            for segment in &ty.path.segments {
+                if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
+                    for arg in &args.args {
+                        if let syn::GenericArgument::Type(syn::Type::Path(inner)) = arg {
+                            if self.is_native_type(&inner.path) {
+                                self.replace(inner.span(), WASM_CLIENT_TYPE, RewriteKind::HttpClient);
+                                self.needs.http_client = true;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        visit::visit_type_path(self, ty);
+    }
+
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        if let Some((_, init)) = &local.init {
+            let (pat, ty) = match &local.pat {
+                syn::Pat::Type(typed) => (&*typed.pat, Some(&*typed.ty)),
+                pat => (pat, None),
+            };
+            if let syn::Pat::Ident(ident) = pat {
+                if self.is_native_expr(init) {
+                    let ty_span = match ty {
+                        Some(syn::Type::Path(path)) if self.is_native_type(&path.path) => Some(path.span()),
+                        _ => None,
+                    };
+                    self.native_bindings.insert(ident.ident.to_string(), (init.span(), ty_span));
+                }
+            }
+        }
+        visit::visit_local(self, local);
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if let syn::Expr::Path(func) = &*call.func {
+            if is_provider_path(&func.path) {
+                for arg in &call.args {
+                    self.substitute_client(arg);
+                }
+            }
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        // Builder-style providers: `.http_client(reqwest::Client::new())`
+        if call.method.to_string().contains("client") {
+            for arg in &call.args {
+                self.substitute_client(arg);
+            }
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+}
+
+fn start_function(name: &syn::Ident) -> String {
+    format!(
+        "\n\n// Added by rig-wasm-compiler: runs `{name}` when the module is instantiated\n\
+         #[wasm_bindgen::prelude::wasm_bindgen(start)]\n\
+         pub fn __rig_wasm_start_{name}() {{\n    \
+             wasm_bindgen_futures::spawn_local(async {{\n        \
+                 crate::{module}::report({name}().await);\n    \
+             }});\n\
+         }}\n",
+        name = name,
+        module = SHIM_MODULE,
+    )
+}
+
+fn apply_edits(file: &Path, source: &str, index: &LineIndex, mut edits: Vec<Edit>) -> (String, Vec<Rewrite>) {
+    edits.sort_by_key(|edit| (edit.start, edit.end));
+
+    let mut shimmed = String::with_capacity(source.len());
+    let mut rewrites = Vec::new();
+    let mut position = 0;
+    for edit in edits {
+        // An edit inside one already applied (e.g. a tokio path inside a replaced client)
+        if edit.start < position {
+            continue;
+        }
+        shimmed.push_str(&source[position..edit.start]);
+        shimmed.push_str(&edit.text);
+        if let Some((kind, replacement)) = edit.rewrite {
+            rewrites.push(Rewrite {
+                kind,
+                file: file.to_path_buf(),
+                line: index.line(edit.start),
+                original: source[edit.start..edit.end].to_string(),
+                replacement,
+            });
+        }
+        position = edit.end;
+    }
+    shimmed.push_str(&source[position..]);
+    (shimmed, rewrites)
+}
+
+// Shims one source file. Replacements keep to a single line, so line numbers
+// in the shimmed file match the original. Files that do not parse are left
+// alone for cargo to report.
+pub fn shim_source(file: &Path, source: &str) -> ShimmedSource {
+    let syntax = match syn::parse_file(source) {
+        Ok(syntax) => syntax,
+        Err(_) => {
+            return ShimmedSource {
+                source: source.to_string(),
+                rewrites: Vec::new(),
+                needs: Needs::default(),
+            }
+        }
+    };
+
+    let mut imports = Imports::default();
+    imports.visit_file(&syntax);
+
+    let index = LineIndex::new(source);
+    let mut visitor = ShimVisitor {
+        index: &index,
+        imports,
+        native_bindings: HashMap::new(),
+        edits: Vec::new(),
+        needs: Needs::default(),
+    };
+    visitor.visit_file(&syntax);
+
+    // `#[tokio::main]` on a top-level function becomes a start function that
+    // drives it on the browser's event loop
+    for item in &syntax.items {
+        if let syn::Item::Fn(function) = item {
+            if compiled_out(&function.attrs) {
+                continue;
+            }
+            if let Some(attr) = function.attrs.iter().find(|attr| is_tokio_main(attr)) {
+                let start = index.offset(attr.span().start());
+                let end = index.offset(attr.span().end());
+                // Reported exactly as appended
+                let start_fn = start_function(&function.sig.ident);
+                visitor.edits.push(Edit {
+                    start,
+                    end,
+                    text: String::new(),
+                    rewrite: Some((RewriteKind::TokioMain, start_fn.trim().to_string())),
+                });
+                visitor.edits.push(Edit {
+                    start: source.len(),
+                    end: source.len(),
+                    text: start_fn,
+                    rewrite: None,
+                });
+                visitor.needs.start = true;
+            }
+        }
+    }
+
+    let needs = visitor.needs;
+    let (source, rewrites) = apply_edits(file, source, &index, visitor.edits);
+    ShimmedSource { source, rewrites, needs }
+}
+
+// The name rig-core is imported under in `manifest`
+fn rig_crate_name(manifest: &toml::Value) -> String {
+    let dependencies = manifest.get("dependencies").and_then(|d| d.as_table());
+    dependencies
+        .and_then(|deps| {
+            deps.iter().find(|(name, spec)| {
+                *name == "rig-core" || spec.get("package").and_then(|p| p.as_str()) == Some("rig-core")
+            })
+        })
+        .map(|(name, _)| name.replace('-', "_"))
+        .unwrap_or_else(|| "rig_core".to_string())
+}
+
+fn has_dependency(manifest: &toml::Value, name: &str) -> bool {
+    let normalized = name.replace('-', "_");
+    manifest
+        .get("dependencies")
+        .and_then(|d| d.as_table())
+        .is_some_and(|deps| {
+            deps.iter().any(|(key, spec)| {
+                key.replace('-', "_") == normalized
+                    || spec.get("package").and_then(|p| p.as_str()).map(|p| p.replace('-', "_")) == Some(normalized.clone())
+            })
+        })
+}
+
+fn add_dependency(manifest: &mut toml::Value, name: &str, version: &str) {
+    if has_dependency(manifest, name) {
+        return;
+    }
+    if let Some(table) = manifest.as_table_mut() {
+        let dependencies = table
+            .entry("dependencies".to_string())
+            .or_insert_with(|| toml::Value::Table(Default::default()));
+        if let Some(dependencies) = dependencies.as_table_mut() {
+            dependencies.insert(name.to_string(), toml::Value::String(version.to_string()));
+        }
+    }
+}
+
+// `path` dependencies are made absolute so the copy still finds them
+fn absolutize_paths(dependencies: &mut toml::Value, crate_dir: &Path) {
+    if let Some(dependencies) = dependencies.as_table_mut() {
+        for spec in dependencies.values_mut() {
+            if let Some(path) = spec.get_mut("path") {
+                if let Some(relative) = path.as_str().filter(|p| Path::new(p).is_relative()) {
+                    *path = toml::Value::String(crate_dir.join(relative).to_string_lossy().into_owned());
+                }
+            }
+        }
+    }
+}
+
+fn inherits_from_workspace(value: &toml::Value) -> bool {
+    match value {
+        toml::Value::Table(table) => {
+            table.get("workspace").and_then(|w| w.as_bool()) == Some(true) || table.values().any(inherits_from_workspace)
+        }
+        _ => false,
+    }
+}
+
+// Cargo.toml for the shimmed copy: a standalone crate whose path
+// dependencies still point at the originals
+fn shim_manifest(contents: &str, crate_dir: &Path, lib_path: Option<&str>) -> Result<toml::Value, CompileError> {
+    let mut manifest: toml::Value = toml::from_str(contents)
+        .map_err(|e| CompileError::InvalidInput(format!("Invalid Cargo.toml in {}: {}", crate_dir.display(), e)))?;
+
+    if ["package", "dependencies"].iter().any(|key| manifest.get(key).is_some_and(inherits_from_workspace)) {
+        return Err(CompileError::InvalidInput(
+            "The shim builds a standalone copy of the crate and cannot resolve `workspace = true` fields; \
+             spell them out or build without --shim"
+                .to_string(),
+        ));
+    }
+
+    for key in ["dependencies", "dev-dependencies", "build-dependencies"] {
+        if let Some(dependencies) = manifest.get_mut(key) {
+            absolutize_paths(dependencies, crate_dir);
+        }
+    }
+    if let Some(targets) = manifest.get_mut("target").and_then(|t| t.as_table_mut()) {
+        for target in targets.values_mut() {
+            for key in ["dependencies", "dev-dependencies", "build-dependencies"] {
+                if let Some(dependencies) = target.get_mut(key) {
+                    absolutize_paths(dependencies, crate_dir);
+                }
+            }
+        }
+    }
+
+    if let Some(table) = manifest.as_table_mut() {
+        table.insert("workspace".to_string(), toml::Value::Table(Default::default()));
+        if let Some(lib_path) = lib_path {
+            if let Some(lib) = table.get_mut("lib").and_then(|lib| lib.as_table_mut()) {
+                lib.insert("path".to_string(), toml::Value::String(lib_path.to_string()));
+            }
+        }
+    }
+    Ok(manifest)
+}
+
+// Copies `from` into `to`. SKIPPED_DIRS are only left out at the top, so
+// modules such as src/target/ still come along.
+fn copy_tree(from: &Path, to: &Path, exclude: &Path, root: bool) -> Result<(), CompileError> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default();
+        if path == exclude || (root && SKIPPED_DIRS.iter().any(|skipped| name == *skipped)) {
+            continue;
+        }
+        if path.is_dir() {
+            copy_tree(&path, &to.join(name), exclude, false)?;
+        } else {
+            fs::copy(&path, to.join(name))?;
+        }
+    }
+    Ok(())
+}
+
+fn rust_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), CompileError> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            rust_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// A copy of the input crate under the temp directory, before it is written out
+struct CrateCopy {
+    crate_dir: PathBuf,
+    source_dir: PathBuf,
+    manifest: toml::Value,
+    // The library root, relative to `crate_dir`
+    lib_root: PathBuf,
+}
+
+// Copies `resolved` under the temp directory, leaving `output_dir` out
+fn copy_crate(resolved: &ResolvedInput, output_dir: &Path) -> Result<CrateCopy, CompileError> {
+    let source_dir = fs::canonicalize(&resolved.crate_dir)?;
+    let mut hasher = DefaultHasher::new();
+    resolved.source_file.as_ref().unwrap_or(&source_dir).hash(&mut hasher);
+    let crate_dir = std::env::temp_dir()
+        .join("rig-wasm-compiler")
+        .join("shim")
+        .join(format!("{}-{:016x}", resolved.package_name, hasher.finish()));
+
+    // Start from a clean copy, keeping cargo's target directory for incremental builds
+    fs::create_dir_all(&crate_dir)?;
+    for entry in fs::read_dir(&crate_dir)? {
+        let path = entry?.path();
+        if path.file_name().is_some_and(|name| name == "target") {
+            continue;
+        }
+        if path.is_dir() {
+            fs::remove_dir_all(&path)?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+
+    let manifest_contents = fs::read_to_string(&resolved.manifest_path)?;
+    let (manifest, lib_root) = match &resolved.source_file {
+        // Bare .rs inputs: the wrapped file becomes src/lib.rs of the copy
+        Some(source_file) => {
+            fs::create_dir_all(crate_dir.join("src"))?;
+            fs::copy(source_file, crate_dir.join("src").join("lib.rs"))?;
+            copy_declared_modules(source_file, &crate_dir.join("src"))?;
+            (shim_manifest(&manifest_contents, &source_dir, Some("src/lib.rs"))?, PathBuf::from("src/lib.rs"))
+        }
+        None => {
+            let exclude = fs::canonicalize(output_dir).unwrap_or_else(|_| output_dir.to_path_buf());
+            copy_tree(&source_dir, &crate_dir, &exclude, true)?;
+            if !crate_dir.join("Cargo.lock").is_file() {
+                if let Some(lock_file) = find_lock_file(&source_dir) {
+                    fs::copy(lock_file, crate_dir.join("Cargo.lock"))?;
+                }
+            }
+            let manifest = shim_manifest(&manifest_contents, &source_dir, None)?;
+            let lib_root = lib_root(&manifest);
+            (manifest, lib_root)
+        }
+    };
+
+    Ok(CrateCopy { crate_dir, source_dir, manifest, lib_root })
+}
+
+// Copies the files of the out-of-line modules (`mod agent;`) a wrapped .rs file
+// declares, which cargo would otherwise look for next to the copy
+fn copy_declared_modules(source_file: &Path, src_dir: &Path) -> Result<(), CompileError> {
+    // cargo reports syntax errors better than we can
+    let file = match syn::parse_file(&fs::read_to_string(source_file)?) {
+        Ok(file) => file,
+        Err(_) => return Ok(()),
+    };
+    let dir = source_file.parent().unwrap_or_else(|| Path::new("."));
+    for item in &file.items {
+        let module = match item {
+            syn::Item::Mod(module) if module.content.is_none() => module,
+            _ => continue,
+        };
+        if module.attrs.iter().any(|attr| attr.path.is_ident("path")) {
+            return Err(CompileError::InvalidInput(format!(
+                "{}:{}: `#[path]` on `mod {}` cannot follow the file into the copy built with --shim or --config; \
+                 turn the file into a crate instead",
+                source_file.display(),
+                module.span().start().line,
+                module.ident
+            )));
+        }
+        let name = module.ident.to_string();
+        let module_file = dir.join(format!("{}.rs", name));
+        if module_file.is_file() {
+            fs::copy(&module_file, src_dir.join(format!("{}.rs", name)))?;
+        }
+        // Submodules of `name.rs`, or `name/mod.rs`
+        let module_dir = dir.join(&name);
+        if module_dir.is_dir() {
+            copy_tree(&module_dir, &src_dir.join(&name), Path::new(""), false)?;
+        }
+    }
+    Ok(())
+}
+
+// The library root named in `manifest`, relative to its directory
+fn lib_root(manifest: &toml::Value) -> PathBuf {
+    let path = manifest
+        .get("lib")
+        .and_then(|lib| lib.get("path"))
+        .and_then(|path| path.as_str())
+        .unwrap_or("src/lib.rs");
+    PathBuf::from(path)
+}
+
+fn write_manifest(crate_dir: &Path, manifest: &toml::Value) -> Result<PathBuf, CompileError> {
+    let manifest_path = crate_dir.join("Cargo.toml");
+    fs::write(&manifest_path, toml::to_string(manifest).map_err(|e| CompileError::PostProcess(e.to_string()))?)?;
+    Ok(manifest_path)
+}
+
+// The copy as the crate to build. A wrapped .rs file is now the copy's
+// src/lib.rs, so the lint reads the copied text.
+fn copied_input(resolved: &ResolvedInput, crate_dir: PathBuf, manifest_path: PathBuf) -> ResolvedInput {
+    let source_file = resolved.source_file.as_ref().map(|_| crate_dir.join("src").join("lib.rs"));
+    ResolvedInput {
+        crate_dir,
+        manifest_path,
+        package_name: resolved.package_name.clone(),
+        generated: resolved.generated,
+        source_file,
+    }
+}
+
+// Builds an unchanged copy of `resolved`, for changes such as the embedded
+// config that must not touch the original. The report has no rewrites and
+// maps the copy's files back to the original.
+pub fn copy(resolved: &ResolvedInput, output_dir: &Path) -> Result<(ResolvedInput, ShimReport), CompileError> {
+    let copy = copy_crate(resolved, output_dir)?;
+    let manifest_path = write_manifest(&copy.crate_dir, &copy.manifest)?;
+    let report = ShimReport {
+        crate_dir: copy.crate_dir.clone(),
+        source_dir: copy.source_dir,
+        source_file: resolved.source_file.clone(),
+        rewrites: Vec::new(),
+        emitted: None,
+    };
+    Ok((copied_input(resolved, copy.crate_dir, manifest_path), report))
+}
+
+// Adds the config given to --config to a copy made by `apply` or `copy`, as
+// `crate::__rig_wasm_config::CONFIG` and, for JS targets, a `rigWasmConfig()` export
+pub fn embed_config(copy: &ResolvedInput, json: &str) -> Result<(), CompileError> {
+    let mut manifest: toml::Value = toml::from_str(&fs::read_to_string(&copy.manifest_path)?)
+        .map_err(|e| CompileError::InvalidInput(format!("{}: {}", copy.manifest_path.display(), e)))?;
+    let lib_root = copy.crate_dir.join(lib_root(&manifest));
+    let module_dir = lib_root.parent().unwrap_or(&copy.crate_dir);
+    fs::write(module_dir.join(format!("{}.json", CONFIG_MODULE)), json)?;
+    fs::write(module_dir.join(format!("{}.rs", CONFIG_MODULE)), CONFIG_SUPPORT.replace("{module}", CONFIG_MODULE))?;
+
+    // Appended rather than prepended so line numbers still match the original
+    let mut root = fs::read_to_string(&lib_root)?;
+    root.push_str(&format!("\n#[doc(hidden)]\npub mod {};\n", CONFIG_MODULE));
+    fs::write(&lib_root, root)?;
+
+    add_dependency(&mut manifest, "wasm-bindgen", "0.2");
+    write_manifest(&copy.crate_dir, &manifest)?;
+    Ok(())
+}
+
+// Builds a shimmed copy of `resolved` under the temp directory and returns it
+// in place of the original, which is never modified. `output_dir` is left out
+// of the copy. With `emit`, the shimmed sources and Cargo.toml are also written
+// there for review.
+pub fn apply(
+    resolved: &ResolvedInput,
+    output_dir: &Path,
+    emit: Option<&Path>,
+) -> Result<(ResolvedInput, ShimReport), CompileError> {
+    let CrateCopy { crate_dir, source_dir, mut manifest, lib_root } = copy_crate(resolved, output_dir)?;
+
+    let mut report = ShimReport {
+        crate_dir: crate_dir.clone(),
+        source_dir,
+        source_file: resolved.source_file.clone(),
+        rewrites: Vec::new(),
+        emitted: None,
+    };
+
+    let mut files = Vec::new();
+    if crate_dir.join("src").is_dir() {
+        rust_files(&crate_dir.join("src"), &mut files)?;
+    }
+    files.sort();
+
+    let mut needs = Needs::default();
+    for file in &files {
+        let source = fs::read_to_string(file)?;
+        let shimmed = shim_source(&report.original_path(file), &source);
+        if !shimmed.rewrites.is_empty() || shimmed.needs != Needs::default() {
+            fs::write(file, &shimmed.source)?;
+        }
+        needs.merge(shimmed.needs);
+        report.rewrites.extend(shimmed.rewrites);
+    }
+
+    // wasm-bindgen accepts a single start function per module
+    let mains: Vec<String> = report
+        .rewrites
+        .iter()
+        .filter(|rewrite| rewrite.kind == RewriteKind::TokioMain)
+        .map(|rewrite| format!("{}:{}", rewrite.file.display(), rewrite.line))
+        .collect();
+    if mains.len() > 1 {
+        return Err(CompileError::InvalidInput(format!(
+            "Only one `#[tokio::main]` function can become the module's start function, found {}: {}",
+            mains.len(),
+            mains.join(", ")
+        )));
+    }
+
+    if needs.support_module() {
+        let mut support = SHIM_SUPPORT.to_string();
+        if needs.http_client {
+            support.push('\n');
+            // The adapter's wasm_bindgen_test module is left out of the copy
+            let client = WASM_HTTP_CLIENT.split("#[cfg(test)]").next().unwrap_or_default();
+            support.push_str(&client.replace("rig_core::", &format!("{}::", rig_crate_name(&manifest))));
+        }
+        let lib_root = crate_dir.join(&lib_root);
+        let module_dir = lib_root.parent().unwrap_or(&crate_dir);
+        fs::write(module_dir.join(format!("{}.rs", SHIM_MODULE)), support)?;
+
+        // Appended rather than prepended so line numbers still match the original
+        let mut root = fs::read_to_string(&lib_root)?;
+        root.push_str(&format!("\n#[doc(hidden)]\nmod {};\n", SHIM_MODULE));
+        fs::write(&lib_root, root)?;
+    }
+
+    if needs.tokio_with_wasm {
+        add_dependency(&mut manifest, "tokio_with_wasm", "0.7.1");
+    }
+    if needs.start {
+        add_dependency(&mut manifest, "wasm-bindgen", "0.2");
+        add_dependency(&mut manifest, "wasm-bindgen-futures", "0.4");
+    }
+    if needs.http_client {
+        add_dependency(&mut manifest, "wasm-bindgen", "0.2");
+        add_dependency(&mut manifest, "gloo-net", "0.3");
+        add_dependency(&mut manifest, "async-trait", "0.1");
+        add_dependency(&mut manifest, "serde", "1.0");
+    }
+    let manifest_path = write_manifest(&crate_dir, &manifest)?;
+
+    if let Some(emitted) = emit {
+        if emitted.exists() {
+            fs::remove_dir_all(emitted)?;
+        }
+        copy_tree(&crate_dir.join("src"), &emitted.join("src"), &crate_dir.join("target"))?;
+        fs::copy(&manifest_path, emitted.join("Cargo.toml"))?;
+        report.emitted = Some(emitted.to_path_buf());
+    }
+
+    Ok((copied_input(resolved, crate_dir, manifest_path), report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shim(source: &str) -> ShimmedSource {
+        shim_source(Path::new("src/lib.rs"), source)
+    }
+
+    #[test]
+    fn test_tokio_paths() {
+        let shimmed = shim("use tokio::sync::Mutex;\n\nasync fn run() {\n    tokio::time::sleep(d).await; // wait\n}\n");
+        assert_eq!(
+            shimmed.source,
+            "use tokio_with_wasm::alias::sync::Mutex;\n\nasync fn run() {\n    tokio_with_wasm::alias::time::sleep(d).await; // wait\n}\n"
+        );
+        let lines: Vec<usize> = shimmed.rewrites.iter().map(|r| r.line).collect();
+        assert_eq!(lines, vec![1, 4]);
+        assert!(shimmed.needs.tokio_with_wasm);
+
+        // Already aliased files are left alone
+        let aliased = "use tokio_with_wasm::alias as tokio;\nfn f() { tokio::spawn(async {}); }\n";
+        assert_eq!(shim(aliased).source, aliased);
+    }
+
+    #[test]
+    fn test_tokio_main() {
+        let source = "#[tokio::main]\nasync fn main() -> Result<(), String> {\n    Ok(())\n}\n";
+        let shimmed = shim(source);
+        assert!(shimmed.source.starts_with("\nasync fn main()"));
+        assert!(shimmed.source.contains("#[wasm_bindgen::prelude::wasm_bindgen(start)]\npub fn __rig_wasm_start_main()"));
+        assert!(shimmed.source.contains("crate::__rig_wasm_shim::report(main().await);"));
+        assert_eq!(shimmed.rewrites[0].kind, RewriteKind::TokioMain);
+        assert_eq!(shimmed.rewrites[0].original, "#[tokio::main]");
+        // The report shows the start function that was appended
+        assert!(shimmed.source.ends_with(&format!("{}\n", shimmed.rewrites[0].replacement)));
+        assert!(shimmed.needs.start);
+
+        let native_only = "#[cfg(not(target_arch = \"wasm32\"))]\n#[tokio::main]\nasync fn main() {}\n";
+        assert_eq!(shim(native_only).source, native_only);
+    }
+
+    #[test]
+    fn test_http_client_substitution() {
+        let source = r#"use reqwest::Client;
+
+pub struct Agent {
+    provider: OpenAIProvider<reqwest::Client>,
+}
+
+fn build(key: &str) -> Agent {
+    let http_client: Client = Client::builder().build().unwrap();
+    let provider = OpenAIProvider::new(http_client, key.to_string());
+    let other = providers::anthropic::Client::new(reqwest::Client::new(), key);
+    let unrelated = reqwest::Client::new();
+    Agent { provider }
+}
+"#;
+        let shimmed = shim(source);
+        assert!(shimmed.source.contains("provider: OpenAIProvider<crate::__rig_wasm_shim::WasmHttpClient>,"));
+        assert!(shimmed.source.contains(
+            "let http_client: crate::__rig_wasm_shim::WasmHttpClient = crate::__rig_wasm_shim::WasmHttpClient::new();"
+        ));
+        assert!(shimmed.source.contains("Client::new(crate::__rig_wasm_shim::WasmHttpClient::new(), key)"));
+        // Clients not handed to a provider are not touched
+        assert!(shimmed.source.contains("let unrelated = reqwest::Client::new();"));
+        assert_eq!(shimmed.source.lines().count(), source.lines().count());
+        assert!(shimmed.rewrites.iter().all(|r| r.kind == RewriteKind::HttpClient));
+        assert!(shimmed.needs.http_client);
+    }
+
+    #[test]
+    fn test_shim_manifest() {
+        let manifest = shim_manifest(
+            "[package]\nname = \"agent\"\nversion = \"0.1.0\"\n\n[dependencies]\nrig = { package = \"rig-core\", version = \"0.1\" }\nutil = { path = \"../util\" }\n\n[workspace]\nmembers = [\"x\"]\n",
+            Path::new("/work/agent"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(manifest["dependencies"]["util"]["path"].as_str(), Some("/work/agent/../util"));
+        assert!(manifest["workspace"].as_table().unwrap().is_empty());
+        assert_eq!(rig_crate_name(&manifest), "rig");
+        assert!(has_dependency(&manifest, "rig_core") || has_dependency(&manifest, "rig-core"));
+
+        let inherited = "[package]\nname = \"agent\"\nversion.workspace = true\n";
+        assert!(shim_manifest(inherited, Path::new("/work/agent"), None).is_err());
+    }
+
+    #[test]
+    fn test_apply_to_source_file() {
+        let dir = std::env::temp_dir().join("rig-wasm-compiler-shim-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let source_file = dir.join("agent.rs");
+        fs::write(&source_file, "use tokio::sync::Mutex;\n").unwrap();
+
+        let resolved = crate::compiler::input::resolve_input(source_file.to_str().unwrap()).unwrap();
+        let emitted = dir.join("pkg/shimmed-src/agent");
+        let (shimmed, report) = apply(&resolved, &dir.join("pkg"), Some(&emitted)).unwrap();
+
+        let lib = fs::read_to_string(shimmed.crate_dir.join("src/lib.rs")).unwrap();
+        assert_eq!(lib, "use tokio_with_wasm::alias::sync::Mutex;\n");
+        assert_eq!(report.rewrites[0].file, fs::canonicalize(&source_file).unwrap());
+        assert!(emitted.join("src/lib.rs").is_file());
+        assert!(fs::read_to_string(&shimmed.manifest_path).unwrap().contains("tokio_with_wasm"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_embed_config_in_copy() {
+        let dir = std::env::temp_dir().join("rig-wasm-compiler-config-copy-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let source_file = dir.join("agent.rs");
+        fs::write(&source_file, "pub fn agent() {}\n").unwrap();
+
+        let resolved = crate::compiler::input::resolve_input(source_file.to_str().unwrap()).unwrap();
+        let (copy, report) = copy(&resolved, &dir.join("pkg")).unwrap();
+        embed_config(&copy, r#"{"providers":{}}"#).unwrap();
+
+        let lib = fs::read_to_string(copy.crate_dir.join("src/lib.rs")).unwrap();
+        assert_eq!(lib, "pub fn agent() {}\n\n#[doc(hidden)]\npub mod __rig_wasm_config;\n");
+        let module = fs::read_to_string(copy.crate_dir.join("src/__rig_wasm_config.rs")).unwrap();
+        assert!(module.contains("include_str!(\"__rig_wasm_config.json\")"));
+        assert!(module.contains("js_name = rigWasmConfig"));
+        assert_eq!(fs::read_to_string(copy.crate_dir.join("src/__rig_wasm_config.json")).unwrap(), r#"{"providers":{}}"#);
+        assert!(fs::read_to_string(&copy.manifest_path).unwrap().contains("wasm-bindgen"));
+
+        // The original is left alone, and findings map back to it
+        assert_eq!(fs::read_to_string(&source_file).unwrap(), "pub fn agent() {}\n");
+        assert!(report.rewrites.is_empty());
+        assert_eq!(report.original_path(&copy.crate_dir.join("src/lib.rs")), fs::canonicalize(&source_file).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_copy_keeps_modules() {
+        let dir = std::env::temp_dir().join("rig-wasm-compiler-copy-modules-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("tools/target")).unwrap();
+        fs::write(dir.join("tools/target/mod.rs"), "pub fn aim() {}\n").unwrap();
+        fs::write(dir.join("tools/mod.rs"), "pub mod target;\n").unwrap();
+        fs::write(dir.join("helper.rs"), "pub fn help() {}\n").unwrap();
+        fs::write(dir.join("unrelated.rs"), "pub fn other() {}\n").unwrap();
+        let source_file = dir.join("agent.rs");
+        fs::write(&source_file, "mod helper;\nmod tools;\n").unwrap();
+
+        let resolved = crate::compiler::input::resolve_input(source_file.to_str().unwrap()).unwrap();
+        let (copy, report) = copy(&resolved, &dir.join("pkg")).unwrap();
+
+        // Declared modules come along, including a nested directory named like a build dir
+        let src = copy.crate_dir.join("src");
+        assert!(src.join("helper.rs").is_file());
+        assert!(src.join("tools/target/mod.rs").is_file());
+        assert!(!src.join("unrelated.rs").exists());
+        assert_eq!(
+            report.original_path(&src.join("tools/target/mod.rs")),
+            fs::canonicalize(&dir).unwrap().join("tools/target/mod.rs")
+        );
+
+        fs::write(&source_file, "#[path = \"elsewhere.rs\"]\nmod helper;\n").unwrap();
+        let resolved = crate::compiler::input::resolve_input(source_file.to_str().unwrap()).unwrap();
+        assert_eq!(copy_crate(&resolved, &dir.join("pkg")).err().unwrap().kind(), "invalid-input");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_apply_rejects_two_mains() {
+        let dir = std::env::temp_dir().join("rig-wasm-compiler-shim-mains-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let source_file = dir.join("agent.rs");
+        fs::write(&source_file, "#[tokio::main]\nasync fn main() {}\n\n#[tokio::main]\nasync fn other() {}\n").unwrap();
+
+        let resolved = crate::compiler::input::resolve_input(source_file.to_str().unwrap()).unwrap();
+        let err = apply(&resolved, &dir.join("pkg"), None).unwrap_err();
+        assert_eq!(err.kind(), "invalid-input");
+        assert!(err.to_string().contains("agent.rs:1, "));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,233 @@
+// ed25519 signatures over manifest.json. Signing the manifest covers every
+// emitted file through its SRI hash, so a partner only has to check one
+// signature and then the hashes of the files it loads.
+use crate::compiler::error::CompileError;
+use crate::compiler::integrity::{self, IntegrityManifest};
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Written next to manifest.json
+pub const SIGNATURE_FILE: &str = "manifest.json.sig";
+
+const ALGORITHM: &str = "ed25519";
+
+// manifest.json.sig. The public key is informational: verifiers must use a
+// key they already trust, never the one shipped with the files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignatureFile {
+    pub algorithm: String,
+    // Hex-encoded 32-byte ed25519 public key
+    pub public_key: String,
+    // Hex-encoded 64-byte signature over the bytes of manifest.json
+    pub signature: String,
+}
+
+// The signing stage of a compile report
+#[derive(Debug, Clone, Serialize)]
+pub struct SignatureReport {
+    pub signature_file: PathBuf,
+    pub public_key: String,
+}
+
+impl fmt::Display for SignatureReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Signed: {} (ed25519 key {})", self.signature_file.display(), self.public_key)
+    }
+}
+
+// `verify`
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyReport {
+    pub dir: PathBuf,
+    pub public_key: String,
+    // Files whose size and hash matched the signed manifest
+    pub files: Vec<String>,
+}
+
+impl fmt::Display for VerifyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Signature: valid (ed25519 key {})", self.public_key)?;
+        write!(f, "Verified {} files in {}", self.files.len(), self.dir.display())
+    }
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim();
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| text.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect()
+}
+
+// A PKCS#8 PEM private key, as written by `openssl genpkey -algorithm ed25519`,
+// or a hex-encoded 32-byte seed
+pub fn parse_signing_key(text: &str) -> Result<SigningKey, String> {
+    if text.contains("-----BEGIN") {
+        return SigningKey::from_pkcs8_pem(text).map_err(|e| format!("not an ed25519 private key: {}", e));
+    }
+    let seed: [u8; 32] = from_hex(text)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("expected a PKCS#8 PEM file or 64 hex characters")?;
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+// A PEM public key, a PEM private key (its public half is used) or a
+// hex-encoded 32-byte public key
+pub fn parse_verifying_key(text: &str) -> Result<VerifyingKey, String> {
+    if text.contains("PRIVATE KEY-----") {
+        return parse_signing_key(text).map(|key| key.verifying_key());
+    }
+    if text.contains("-----BEGIN") {
+        return VerifyingKey::from_public_key_pem(text).map_err(|e| format!("not an ed25519 public key: {}", e));
+    }
+    let bytes: [u8; 32] = from_hex(text)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("expected a PEM file or 64 hex characters")?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| format!("invalid ed25519 public key: {}", e))
+}
+
+fn read_key<T>(path: &Path, parse: impl Fn(&str) -> Result<T, String>) -> Result<T, CompileError> {
+    if !path.is_file() {
+        return Err(CompileError::InputNotFound(path.display().to_string()));
+    }
+    let text = fs::read_to_string(path)?;
+    parse(&text).map_err(|e| CompileError::InvalidInput(format!("{}: {}", path.display(), e)))
+}
+
+pub fn read_signing_key(path: &Path) -> Result<SigningKey, CompileError> {
+    read_key(path, parse_signing_key)
+}
+
+pub fn read_verifying_key(path: &Path) -> Result<VerifyingKey, CompileError> {
+    read_key(path, parse_verifying_key)
+}
+
+pub fn public_key_hex(key: &VerifyingKey) -> String {
+    key.to_bytes().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn sign(manifest: &[u8], key: &SigningKey) -> SignatureFile {
+    SignatureFile {
+        algorithm: ALGORITHM.to_string(),
+        public_key: public_key_hex(&key.verifying_key()),
+        signature: key.sign(manifest).to_bytes().iter().map(|byte| format!("{:02x}", byte)).collect(),
+    }
+}
+
+// Signs the manifest.json in `output_dir` and writes manifest.json.sig
+pub fn sign_manifest(output_dir: &Path, key: &SigningKey) -> Result<SignatureReport, CompileError> {
+    let manifest = fs::read(output_dir.join(integrity::MANIFEST_FILE))?;
+    let signature = sign(&manifest, key);
+    let mut json = serde_json::to_vec_pretty(&signature).map_err(|e| CompileError::PostProcess(e.to_string()))?;
+    json.push(b'\n');
+    let signature_file = output_dir.join(SIGNATURE_FILE);
+    fs::write(&signature_file, json)?;
+    Ok(SignatureReport {
+        signature_file,
+        public_key: signature.public_key,
+    })
+}
+
+// Checks the signature over `manifest` and parses it. Shared by `verify` and
+// the in-browser helper.
+pub fn verify_manifest(manifest: &[u8], signature: &str, key: &VerifyingKey) -> Result<IntegrityManifest, String> {
+    let signature: SignatureFile =
+        serde_json::from_str(signature).map_err(|e| format!("unreadable {}: {}", SIGNATURE_FILE, e))?;
+    if signature.algorithm != ALGORITHM {
+        return Err(format!("unsupported signature algorithm {}", signature.algorithm));
+    }
+    let bytes: [u8; 64] = from_hex(&signature.signature)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("the signature is not 64 hex-encoded bytes")?;
+    key.verify_strict(manifest, &Signature::from_bytes(&bytes)).map_err(|_| {
+        format!(
+            "the signature does not match manifest.json and key {}; the files were modified or signed with another key",
+            public_key_hex(key)
+        )
+    })?;
+    serde_json::from_slice(manifest).map_err(|e| format!("unreadable {}: {}", integrity::MANIFEST_FILE, e))
+}
+
+// Checks `bytes` against the entry for `path` in a verified manifest
+pub fn check_file(manifest: &IntegrityManifest, path: &str, bytes: &[u8]) -> Result<(), String> {
+    let entry = manifest.file(path).ok_or_else(|| format!("{} is not listed in the signed manifest", path))?;
+    if entry.size != bytes.len() as u64 || entry.integrity != integrity::sri(bytes) {
+        return Err(format!("{} does not match its hash in the signed manifest", path));
+    }
+    Ok(())
+}
+
+// Verifies the signature in `dir` against `key`, then every file the manifest lists
+pub fn verify(dir: &Path, key: &VerifyingKey) -> Result<VerifyReport, CompileError> {
+    let failed = |reason: String| CompileError::VerificationFailed(format!("{}: {}", dir.display(), reason));
+    let read = |file: &str| {
+        fs::read(dir.join(file)).map_err(|e| failed(format!("cannot read {}: {}", file, e)))
+    };
+
+    let manifest_bytes = read(integrity::MANIFEST_FILE)?;
+    let signature = String::from_utf8_lossy(&read(SIGNATURE_FILE)?).into_owned();
+    let manifest = verify_manifest(&manifest_bytes, &signature, key).map_err(failed)?;
+
+    let mut files = Vec::new();
+    for entry in &manifest.files {
+        check_file(&manifest, &entry.path, &read(&entry.path)?).map_err(failed)?;
+        files.push(entry.path.clone());
+    }
+    Ok(VerifyReport {
+        dir: dir.to_path_buf(),
+        public_key: public_key_hex(key),
+        files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 8032 test vector 1
+    const SEED: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+    const PUBLIC_KEY: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+
+    #[test]
+    fn test_parse_keys() {
+        let key = parse_signing_key(SEED).unwrap();
+        assert_eq!(public_key_hex(&key.verifying_key()), PUBLIC_KEY);
+        assert_eq!(parse_verifying_key(PUBLIC_KEY).unwrap(), key.verifying_key());
+        assert!(parse_signing_key("not a key").is_err());
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let dir = std::env::temp_dir().join("rig-wasm-compiler-signing-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("agent.js"), "export {}").unwrap();
+        fs::write(dir.join("agent_bg.wasm"), [0u8, 97, 115, 109]).unwrap();
+
+        let key = parse_signing_key(SEED).unwrap();
+        integrity::write_manifest(&dir).unwrap();
+        let report = sign_manifest(&dir, &key).unwrap();
+        assert_eq!(report.public_key, PUBLIC_KEY);
+
+        let verified = verify(&dir, &key.verifying_key()).unwrap();
+        assert_eq!(verified.files, vec!["agent.js", "agent_bg.wasm"]);
+
+        // Another key is rejected
+        let other = SigningKey::from_bytes(&[7u8; 32]).verifying_key();
+        assert_eq!(verify(&dir, &other).unwrap_err().kind(), "verification-failed");
+
+        // So is a file changed after signing
+        fs::write(dir.join("agent.js"), "export const tampered = 1;").unwrap();
+        let err = verify(&dir, &key.verifying_key()).unwrap_err();
+        assert!(err.to_string().contains("agent.js does not match"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
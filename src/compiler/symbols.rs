@@ -0,0 +1,77 @@
+// Symbol map for debug builds: the name and code offsets of every function,
+// so stack frames such as `wasm-function[123]:0x4567` can be symbolized even
+// where the browser ignores the name section
+use crate::compiler::error::CompileError;
+use crate::compiler::wasm_module;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionSymbol {
+    pub index: u32,
+    pub name: String,
+    // Byte offsets of the function body within the module
+    pub start: u64,
+    pub end: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolMap {
+    // File name of the module the offsets refer to
+    pub module: String,
+    pub functions: Vec<FunctionSymbol>,
+}
+
+// The mapping file written next to the module
+pub fn symbols_file(out_name: &str) -> String {
+    format!("{}.symbols.json", out_name)
+}
+
+pub fn symbol_map(bytes: &[u8], module: &str) -> Result<SymbolMap, String> {
+    let names = wasm_module::function_names(bytes)?;
+    if names.is_empty() {
+        return Err("the module has no name section".to_string());
+    }
+    let functions = wasm_module::function_bodies(bytes)?
+        .into_iter()
+        .filter_map(|(index, body)| {
+            names.get(&index).map(|name| FunctionSymbol {
+                index,
+                name: name.clone(),
+                start: body.start as u64,
+                end: body.end as u64,
+            })
+        })
+        .collect();
+    Ok(SymbolMap { module: module.to_string(), functions })
+}
+
+// Reads `wasm_file` and writes its symbol map to `out_file`
+pub fn write_symbol_map(wasm_file: &Path, out_file: &Path) -> Result<SymbolMap, CompileError> {
+    let bytes = fs::read(wasm_file)?;
+    let module = wasm_file.file_name().unwrap_or_default().to_string_lossy();
+    let map = symbol_map(&bytes, &module)
+        .map_err(|e| CompileError::PostProcess(format!("{}: {}", wasm_file.display(), e)))?;
+    let json = serde_json::to_vec(&map).map_err(|e| CompileError::PostProcess(e.to_string()))?;
+    fs::write(out_file, json)?;
+    Ok(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::wasm_module::strip_custom_sections;
+    use crate::compiler::wasm_module::tests::sample_module;
+
+    #[test]
+    fn test_symbol_map() {
+        let map = symbol_map(&sample_module(), "sample.wasm").unwrap();
+        let names: Vec<(u32, &str)> = map.functions.iter().map(|f| (f.index, f.name.as_str())).collect();
+        assert_eq!(names, vec![(1, "small"), (2, "large")]);
+        assert_eq!(map.functions[1].end - map.functions[1].start, 6);
+
+        let stripped = strip_custom_sections(&sample_module(), |name| name == "name").unwrap();
+        assert!(symbol_map(&stripped, "sample.wasm").is_err());
+    }
+}
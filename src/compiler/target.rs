@@ -0,0 +1,179 @@
+use clap::ArgEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+// wasm-bindgen output targets, named the way wasm-pack spells them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, ArgEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Target {
+    // ES module loaded directly by the browser with `init()`
+    #[default]
+    Web,
+    // ES module for webpack and other bundlers that understand wasm imports
+    Bundler,
+    // CommonJS module that reads the .wasm synchronously from disk
+    Nodejs,
+    // Classic script that sets a `wasm_bindgen` global
+    NoModules,
+    // ES module using Deno's file and fetch APIs
+    Deno,
+    // Core module for WASI preview 1 runtimes; no JS glue
+    #[clap(name = "wasip1")]
+    #[serde(rename = "wasip1")]
+    WasiP1,
+    // WASI preview 2 component exporting the rig:agent world, for wasmtime and
+    // other component-model hosts
+    #[clap(name = "wasip2")]
+    #[serde(rename = "wasip2")]
+    WasiP2,
+}
+
+impl Target {
+    pub const ALL: [Target; 7] = [
+        Target::Web,
+        Target::Bundler,
+        Target::Nodejs,
+        Target::NoModules,
+        Target::Deno,
+        Target::WasiP1,
+        Target::WasiP2,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Target::Web => "web",
+            Target::Bundler => "bundler",
+            Target::Nodejs => "nodejs",
+            Target::NoModules => "no-modules",
+            Target::Deno => "deno",
+            Target::WasiP1 => "wasip1",
+            Target::WasiP2 => "wasip2",
+        }
+    }
+
+    // WASI targets are built by cargo alone; wasm-bindgen is not involved
+    pub fn is_wasi(&self) -> bool {
+        matches!(self, Target::WasiP1 | Target::WasiP2)
+    }
+
+    // The rustc target triple
+    pub fn rust_target(&self) -> &'static str {
+        match self {
+            Target::WasiP1 => "wasm32-wasip1",
+            Target::WasiP2 => "wasm32-wasip2",
+            _ => "wasm32-unknown-unknown",
+        }
+    }
+
+    // The module the optimizer and budget work on
+    pub fn wasm_file(&self, out_name: &str) -> String {
+        if self.is_wasi() {
+            format!("{}.wasm", out_name)
+        } else {
+            format!("{}_bg.wasm", out_name)
+        }
+    }
+
+    // Files wasm-bindgen writes for this target, excluding TypeScript definitions
+    pub fn glue_files(&self, out_name: &str) -> Vec<String> {
+        match self {
+            // The WIT world is only written when the component exports it
+            Target::WasiP1 | Target::WasiP2 => return vec![self.wasm_file(out_name)],
+            _ => {}
+        }
+        let mut files = vec![
+            format!("{}.js", out_name),
+            format!("{}_bg.wasm", out_name),
+        ];
+        if *self == Target::Bundler {
+            files.push(format!("{}_bg.js", out_name));
+        }
+        files
+    }
+
+    pub fn typescript_files(&self, out_name: &str) -> Vec<String> {
+        if self.is_wasi() {
+            return Vec::new();
+        }
+        vec![
+            format!("{}.d.ts", out_name),
+            format!("{}_bg.wasm.d.ts", out_name),
+        ]
+    }
+
+    // The package.json `exports` condition this target's glue is published under.
+    // no-modules output is a classic script and cannot be imported.
+    pub fn export_condition(&self) -> Option<&'static str> {
+        match self {
+            Target::Deno => Some("deno"),
+            Target::Nodejs => Some("node"),
+            Target::Bundler => Some("module"),
+            Target::Web => Some("browser"),
+            Target::NoModules | Target::WasiP1 | Target::WasiP2 => None,
+        }
+    }
+
+    // Checks that wasm-pack produced the glue this target needs
+    pub fn missing_files(&self, output_dir: &Path, out_name: &str, typescript: bool) -> Vec<String> {
+        let mut expected = self.glue_files(out_name);
+        if typescript {
+            expected.extend(self.typescript_files(out_name));
+        }
+        expected
+            .into_iter()
+            .filter(|file| !output_dir.join(file).is_file())
+            .collect()
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Target::ALL
+            .into_iter()
+            .find(|t| t.as_str() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Target::ALL.iter().map(Target::as_str).collect();
+                format!("Unknown target: {} (expected one of {})", s, names.join(", "))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_round_trip() {
+        for target in Target::ALL {
+            assert_eq!(target.as_str().parse::<Target>().unwrap(), target);
+        }
+        assert!("commonjs".parse::<Target>().is_err());
+    }
+
+    #[test]
+    fn test_bundler_glue_files() {
+        let files = Target::Bundler.glue_files("rig_wasm");
+        assert!(files.contains(&"rig_wasm_bg.js".to_string()));
+        assert!(!Target::Web.glue_files("rig_wasm").contains(&"rig_wasm_bg.js".to_string()));
+    }
+
+    #[test]
+    fn test_wasi_outputs() {
+        assert_eq!(Target::WasiP2.glue_files("agent"), vec!["agent.wasm"]);
+        assert_eq!(Target::WasiP1.wasm_file("agent"), "agent.wasm");
+        assert_eq!(Target::Web.wasm_file("agent"), "agent_bg.wasm");
+        assert!(Target::WasiP1.typescript_files("agent").is_empty());
+        assert_eq!(Target::WasiP2.rust_target(), "wasm32-wasip2");
+        assert_eq!("wasip1".parse::<Target>().unwrap(), Target::WasiP1);
+    }
+}
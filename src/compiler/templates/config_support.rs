@@ -0,0 +1,27 @@
+// Generated by rig-wasm-compiler from the file given to --config. Changes are
+// overwritten on the next build. Secret fields hold `$secret:` placeholders
+// that initialize_rig_wasm expects at runtime.
+#![allow(dead_code)]
+
+// The embedded config as JSON
+pub const CONFIG: &str = include_str!("{module}.json");
+
+// Hands the config to the page, which passes it on with the secrets filled in
+#[cfg(not(target_os = "wasi"))]
+#[wasm_bindgen::prelude::wasm_bindgen(js_name = rigWasmConfig)]
+pub fn config() -> String {
+    CONFIG.to_string()
+}
+
+// WASI hosts read the config from the module's memory
+#[cfg(target_os = "wasi")]
+#[no_mangle]
+pub extern "C" fn rig_wasm_config_ptr() -> *const u8 {
+    CONFIG.as_ptr()
+}
+
+#[cfg(target_os = "wasi")]
+#[no_mangle]
+pub extern "C" fn rig_wasm_config_len() -> usize {
+    CONFIG.len()
+}
@@ -0,0 +1,82 @@
+// Generated by rig-wasm-compiler --emit-worker. Mirrors the exports of
+// {{out_name}}.js on the main thread; every call is posted to
+// {{out_name}}.worker.js and resolves with its answer.
+let worker;
+let nextId = 1;
+const pending = new Map();
+
+function rejectAll(error) {
+  for (const { reject } of pending.values()) {
+    reject(error);
+  }
+  pending.clear();
+}
+
+// Starts the worker. Calls start it on first use; call this first to load it
+// from another URL.
+export function startWorker(url = new URL('./{{out_name}}.worker.js', import.meta.url)) {
+  if (worker) {
+    return worker;
+  }
+  worker = new Worker(url, { type: 'module' });
+  worker.onmessage = (event) => {
+    const { id, result, error } = event.data;
+    const request = pending.get(id);
+    if (!request) {
+      return;
+    }
+    pending.delete(id);
+    if (error) {
+      const rethrown = new Error(error.message);
+      rethrown.name = error.name;
+      if (error.stack) {
+        rethrown.stack = error.stack;
+      }
+      request.reject(rethrown);
+    } else {
+      request.resolve(result);
+    }
+  };
+  worker.onerror = (event) => {
+    rejectAll(new Error(`{{out_name}}.worker.js failed: ${event.message}`));
+  };
+  return worker;
+}
+
+// Stops the worker. Pending calls are rejected and instances are gone.
+export function terminateWorker() {
+  if (worker) {
+    worker.terminate();
+    worker = undefined;
+  }
+  rejectAll(new Error('The worker was terminated'));
+}
+
+function request(message) {
+  const target = startWorker();
+  const id = nextId++;
+  return new Promise((resolve, reject) => {
+    pending.set(id, { resolve, reject });
+    target.postMessage({ id, ...message });
+  });
+}
+
+// The worker-side instance behind a proxy object
+class Remote {
+  #handle;
+
+  constructor(className, args) {
+    this.#handle = request({ kind: 'new', class: className, args });
+    // A failed constructor is reported by the first call, not as an unhandled rejection
+    this.#handle.catch(() => {});
+  }
+
+  async call(method, args) {
+    return request({ kind: 'call', handle: await this.#handle, method, args });
+  }
+
+  async free() {
+    return request({ kind: 'free', handle: await this.#handle });
+  }
+}
+{{exports}}
\ No newline at end of file
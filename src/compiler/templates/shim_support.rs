@@ -0,0 +1,24 @@
+// Generated by rig-wasm-compiler for the wasm32 build. Changes are overwritten
+// on the next build; pass --emit-shimmed-source to review this file.
+#![allow(dead_code, unused_imports)]
+
+// Lets the generated start function accept mains returning `()` or a `Result`
+pub trait Outcome {
+    fn report(self);
+}
+
+impl Outcome for () {
+    fn report(self) {}
+}
+
+impl<T, E: std::fmt::Debug> Outcome for Result<T, E> {
+    fn report(self) {
+        if let Err(e) = self {
+            wasm_bindgen::throw_str(&format!("main returned an error: {:?}", e));
+        }
+    }
+}
+
+pub fn report(outcome: impl Outcome) {
+    outcome.report();
+}
@@ -0,0 +1,58 @@
+// Generated by rig-wasm-compiler --emit-worker. Runs {{out_name}}.js in a Web
+// Worker and answers the requests {{out_name}}.proxy.js posts to it.
+import {{import}} from './{{out_name}}.js';
+
+const ready = {{init}};
+const classes = { {{classes}} };
+const instances = new Map();
+let nextHandle = 1;
+
+function instance(handle) {
+  const found = instances.get(handle);
+  if (!found) {
+    throw new Error(`No instance ${handle}; it was freed or never created`);
+  }
+  return found;
+}
+
+async function handle(request) {
+  await ready;
+  switch (request.kind) {
+    case 'new': {
+      const created = new classes[request.class](...request.args);
+      const handle = nextHandle++;
+      instances.set(handle, created);
+      return handle;
+    }
+    case 'call':
+      return instance(request.handle)[request.method](...request.args);
+    case 'static':
+      return classes[request.class][request.method](...request.args);
+    case 'function':
+      return wasm[request.method](...request.args);
+    case 'free':
+      instance(request.handle).free();
+      instances.delete(request.handle);
+      return undefined;
+    default:
+      throw new Error(`Unknown request kind ${request.kind}`);
+  }
+}
+
+function serializeError(error) {
+  if (error instanceof Error) {
+    return { name: error.name, message: error.message, stack: error.stack };
+  }
+  return { name: 'Error', message: String(error) };
+}
+
+self.onmessage = async (event) => {
+  const { id } = event.data;
+  try {
+    const result = await handle(event.data);
+    self.postMessage({ id, result });
+  } catch (error) {
+    // Also reached when the result cannot be cloned, e.g. a wasm class instance
+    self.postMessage({ id, error: serializeError(error) });
+  }
+};
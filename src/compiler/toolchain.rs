@@ -0,0 +1,324 @@
+use crate::compiler::diagnostics::WASM_TARGET;
+use crate::compiler::error::CompileError;
+use crate::compiler::target::Target;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// A tool the compiler shells out to, and the version found on PATH
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolStatus {
+    pub name: String,
+    pub version: Option<String>,
+    // Builds fail without required tools; optional ones only disable a stage
+    pub required: bool,
+    pub hint: String,
+}
+
+impl ToolStatus {
+    fn detect(name: &str, args: &[&str], required: bool, hint: &str) -> Self {
+        let version = Command::new(name)
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| parse_version(&String::from_utf8_lossy(&output.stdout)));
+
+        ToolStatus {
+            name: name.to_string(),
+            version,
+            required,
+            hint: hint.to_string(),
+        }
+    }
+
+    pub fn is_missing(&self) -> bool {
+        self.version.is_none()
+    }
+}
+
+// Everything `doctor` reports. Built from local commands and files only.
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolchainReport {
+    pub tools: Vec<ToolStatus>,
+    pub wasm32_target: bool,
+    // wasm-bindgen version the project is locked to, if a Cargo.lock was found
+    pub wasm_bindgen_crate: Option<String>,
+    pub lock_file: Option<PathBuf>,
+    pub warnings: Vec<String>,
+}
+
+impl ToolchainReport {
+    pub fn tool(&self, name: &str) -> Option<&ToolStatus> {
+        self.tools.iter().find(|tool| tool.name == name)
+    }
+
+    // The first missing required tool, as the error a build would fail with
+    pub fn missing_required(&self) -> Option<CompileError> {
+        if let Some(tool) = self.tools.iter().find(|tool| tool.required && tool.is_missing()) {
+            return Some(CompileError::ToolchainMissing {
+                tool: tool.name.clone(),
+                hint: tool.hint.clone(),
+            });
+        }
+        if !self.wasm32_target {
+            return Some(rust_target_missing(WASM_TARGET));
+        }
+        None
+    }
+}
+
+impl fmt::Display for ToolchainReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for tool in &self.tools {
+            let status = match &tool.version {
+                Some(version) => version.clone(),
+                None if tool.required => format!("missing (required; install with `{}`)", tool.hint),
+                None => format!("missing (optional; install with `{}`)", tool.hint),
+            };
+            writeln!(f, "  {:<16} {}", tool.name, status)?;
+        }
+
+        let target = if self.wasm32_target {
+            "installed".to_string()
+        } else {
+            format!("missing (install with `rustup target add {}`)", WASM_TARGET)
+        };
+        writeln!(f, "  {:<16} {}", WASM_TARGET, target)?;
+
+        match (&self.wasm_bindgen_crate, &self.lock_file) {
+            (Some(version), Some(lock_file)) => {
+                writeln!(f, "  {:<16} {} ({})", "wasm-bindgen dep", version, lock_file.display())?
+            }
+            _ => writeln!(f, "  {:<16} not found in Cargo.lock", "wasm-bindgen dep")?,
+        }
+
+        for warning in &self.warnings {
+            writeln!(f, "warning: {}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+// Pulls the first dotted version number out of `--version` output, e.g.
+// "wasm-pack 0.12.1" or "wasm-opt version 116 (version_116)"
+pub fn parse_version(output: &str) -> Option<String> {
+    output
+        .split_whitespace()
+        .find(|word| word.chars().next().is_some_and(|c| c.is_ascii_digit()))
+        .map(|word| word.trim_end_matches(|c: char| !c.is_ascii_alphanumeric()).to_string())
+}
+
+fn rust_target_missing(target: &str) -> CompileError {
+    CompileError::ToolchainMissing {
+        tool: format!("The {} target", target),
+        hint: format!("rustup target add {}", target),
+    }
+}
+
+pub fn has_wasm_target() -> bool {
+    has_rust_target(WASM_TARGET)
+}
+
+// Checks the sysroot instead of asking rustup, so toolchains installed
+// without rustup are handled too
+pub fn has_rust_target(target: &str) -> bool {
+    let output = match Command::new("rustc").args(["--print", "sysroot"]).output() {
+        Ok(output) if output.status.success() => output,
+        _ => return false,
+    };
+    let sysroot = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    sysroot.join("lib").join("rustlib").join(target).is_dir()
+}
+
+// The `rustc --print cfg` lines for `target`, e.g. `target_arch="wasm32"`
+pub fn target_cfg(target: &str) -> Result<Vec<String>, CompileError> {
+    let output = Command::new("rustc")
+        .args(["--print", "cfg", "--target", target])
+        .output()
+        .map_err(|e| CompileError::spawn("rustc", "https://rustup.rs", e))?;
+    if !output.status.success() {
+        return Err(CompileError::InvalidInput(format!(
+            "rustc --print cfg --target {} failed: {}",
+            target,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().map(str::to_string).collect())
+}
+
+#[derive(Deserialize)]
+struct LockFile {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+#[derive(Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
+}
+
+// Finds the Cargo.lock that applies to `dir`, walking up into the workspace root
+pub fn find_lock_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|lock_file| lock_file.is_file())
+}
+
+pub fn locked_version(lock_file: &Path, crate_name: &str) -> Option<String> {
+    let contents = fs::read_to_string(lock_file).ok()?;
+    let lock: LockFile = toml::from_str(&contents).ok()?;
+    lock.package
+        .into_iter()
+        .find(|package| package.name == crate_name)
+        .map(|package| package.version)
+}
+
+// Inspects the local toolchain for a project rooted at `project_dir`
+pub fn detect(project_dir: &Path) -> ToolchainReport {
+    let tools = vec![
+        ToolStatus::detect("rustc", &["--version"], true, "https://rustup.rs"),
+        ToolStatus::detect("cargo", &["--version"], true, "https://rustup.rs"),
+        ToolStatus::detect("wasm-pack", &["--version"], true, "cargo install wasm-pack"),
+        ToolStatus::detect("wasm-bindgen", &["--version"], false, "cargo install wasm-bindgen-cli"),
+        ToolStatus::detect("wasm-opt", &["--version"], false, "cargo install wasm-opt"),
+    ];
+
+    let lock_file = find_lock_file(project_dir);
+    let wasm_bindgen_crate = lock_file
+        .as_deref()
+        .and_then(|lock_file| locked_version(lock_file, "wasm-bindgen"));
+
+    let mut report = ToolchainReport {
+        tools,
+        wasm32_target: has_wasm_target(),
+        wasm_bindgen_crate,
+        lock_file,
+        warnings: Vec::new(),
+    };
+
+    let cli_version = report.tool("wasm-bindgen").and_then(|tool| tool.version.clone());
+    if let (Some(crate_version), Some(cli_version)) = (&report.wasm_bindgen_crate, cli_version) {
+        if *crate_version != cli_version {
+            report.warnings.push(format!(
+                "wasm-bindgen {} in Cargo.lock does not match wasm-bindgen-cli {} on PATH. \
+                 Modules bound with a different CLI version fail to load; run \
+                 `cargo install wasm-bindgen-cli --version {}` or `cargo update -p wasm-bindgen --precise {}`",
+                crate_version, cli_version, crate_version, cli_version
+            ));
+        }
+    }
+
+    report
+}
+
+// Checked before every build: the tools the pipeline cannot run without
+pub fn preflight(target: Target) -> Result<(), CompileError> {
+    for (tool, hint) in [("cargo", "https://rustup.rs"), ("wasm-pack", "cargo install wasm-pack")] {
+        // WASI targets are built by cargo alone
+        if tool == "wasm-pack" && target.is_wasi() {
+            continue;
+        }
+        if ToolStatus::detect(tool, &["--version"], true, hint).is_missing() {
+            return Err(CompileError::ToolchainMissing {
+                tool: tool.to_string(),
+                hint: hint.to_string(),
+            });
+        }
+    }
+    if !has_rust_target(target.rust_target()) {
+        return Err(rust_target_missing(target.rust_target()));
+    }
+    Ok(())
+}
+
+// Checked before debug builds, which run the wasm-bindgen CLI directly: it has
+// to be installed, at the version of the crate's locked wasm-bindgen when
+// there is a Cargo.lock
+pub fn preflight_bindgen(crate_dir: &Path) -> Result<(), CompileError> {
+    let hint = "cargo install wasm-bindgen-cli";
+    let cli_version = ToolStatus::detect("wasm-bindgen", &["--version"], true, hint)
+        .version
+        .ok_or_else(|| CompileError::ToolchainMissing {
+            tool: "wasm-bindgen".to_string(),
+            hint: hint.to_string(),
+        })?;
+    let crate_version = find_lock_file(crate_dir).and_then(|lock_file| locked_version(&lock_file, "wasm-bindgen"));
+    check_bindgen_version(crate_version.as_deref(), &cli_version)
+}
+
+// wasm-bindgen-cli only reads modules built with the exact same crate version
+fn check_bindgen_version(crate_version: Option<&str>, cli_version: &str) -> Result<(), CompileError> {
+    match crate_version {
+        Some(crate_version) if crate_version != cli_version => Err(CompileError::ToolchainMissing {
+            tool: format!("wasm-bindgen-cli {} (found {})", crate_version, cli_version),
+            hint: format!("cargo install wasm-bindgen-cli --version {}", crate_version),
+        }),
+        _ => Ok(()),
+    }
+}
+
+// The full version output of every tool that shapes the output of a build for
+// `target`, for the build cache. Missing tools are recorded as such.
+pub fn fingerprint(target: Target, optimize: bool) -> String {
+    let mut tools = vec![("rustc", "-vV")];
+    if !target.is_wasi() {
+        tools.push(("wasm-pack", "--version"));
+        // Debug builds run wasm-bindgen directly
+        tools.push(("wasm-bindgen", "--version"));
+    }
+    if optimize {
+        tools.push(("wasm-opt", "--version"));
+    }
+    tools
+        .into_iter()
+        .map(|(tool, arg)| match Command::new(tool).arg(arg).output() {
+            Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            _ => format!("{} missing", tool),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("wasm-pack 0.12.1\n").as_deref(), Some("0.12.1"));
+        assert_eq!(parse_version("wasm-bindgen 0.2.92").as_deref(), Some("0.2.92"));
+        assert_eq!(parse_version("wasm-opt version 116 (version_116)").as_deref(), Some("116"));
+        assert_eq!(parse_version("rustc 1.78.0 (9b00956e5 2024-04-29)").as_deref(), Some("1.78.0"));
+        assert_eq!(parse_version("no version here"), None);
+    }
+
+    #[test]
+    fn test_check_bindgen_version() {
+        assert!(check_bindgen_version(Some("0.2.92"), "0.2.92").is_ok());
+        assert!(check_bindgen_version(None, "0.2.92").is_ok());
+        let err = check_bindgen_version(Some("0.2.92"), "0.2.93").unwrap_err();
+        assert!(err.to_string().contains("cargo install wasm-bindgen-cli --version 0.2.92"));
+    }
+
+    #[test]
+    fn test_locked_version() {
+        let dir = std::env::temp_dir().join("rig-wasm-compiler-toolchain-test");
+        fs::create_dir_all(dir.join("member")).unwrap();
+        fs::write(
+            dir.join("Cargo.lock"),
+            "version = 3\n\n[[package]]\nname = \"wasm-bindgen\"\nversion = \"0.2.92\"\n",
+        )
+        .unwrap();
+
+        let lock_file = find_lock_file(&dir.join("member")).unwrap();
+        assert_eq!(lock_file, dir.join("Cargo.lock"));
+        assert_eq!(locked_version(&lock_file, "wasm-bindgen").as_deref(), Some("0.2.92"));
+        assert_eq!(locked_version(&lock_file, "serde"), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
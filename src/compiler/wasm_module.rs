@@ -0,0 +1,634 @@
+// Minimal reader for the WebAssembly binary format: enough to walk sections,
+// drop custom sections, list imports and exports and attribute code size to
+// functions
+use serde::Serialize;
+use std::collections::HashMap;
+use std::ops::Range;
+
+const CUSTOM_SECTION: u8 = 0;
+const IMPORT_SECTION: u8 = 2;
+const MEMORY_SECTION: u8 = 5;
+const EXPORT_SECTION: u8 = 7;
+const CODE_SECTION: u8 = 10;
+// Import and export sections of the component layer
+const COMPONENT_IMPORT_SECTION: u8 = 10;
+const COMPONENT_EXPORT_SECTION: u8 = 11;
+
+// Import kinds
+const IMPORT_FUNC: u8 = 0;
+const IMPORT_TABLE: u8 = 1;
+const IMPORT_MEMORY: u8 = 2;
+const IMPORT_GLOBAL: u8 = 3;
+const IMPORT_TAG: u8 = 4;
+
+// What an import or export refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExternalKind {
+    Func,
+    Table,
+    Memory,
+    Global,
+    Tag,
+}
+
+impl ExternalKind {
+    fn from_byte(kind: u8) -> Result<Self, String> {
+        match kind {
+            IMPORT_FUNC => Ok(ExternalKind::Func),
+            IMPORT_TABLE => Ok(ExternalKind::Table),
+            IMPORT_MEMORY => Ok(ExternalKind::Memory),
+            IMPORT_GLOBAL => Ok(ExternalKind::Global),
+            IMPORT_TAG => Ok(ExternalKind::Tag),
+            kind => Err(format!("Unknown import kind {}", kind)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExternalKind::Func => "func",
+            ExternalKind::Table => "table",
+            ExternalKind::Memory => "memory",
+            ExternalKind::Global => "global",
+            ExternalKind::Tag => "tag",
+        }
+    }
+}
+
+// What a component-level import or export refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ComponentKind {
+    CoreModule,
+    Func,
+    Value,
+    Type,
+    Component,
+    Instance,
+}
+
+impl ComponentKind {
+    // Sorts and extern descriptors share these tags; 0x00 is the core module
+    fn from_byte(kind: u8) -> Result<Self, String> {
+        match kind {
+            0x00 => Ok(ComponentKind::CoreModule),
+            0x01 => Ok(ComponentKind::Func),
+            0x02 => Ok(ComponentKind::Value),
+            0x03 => Ok(ComponentKind::Type),
+            0x04 => Ok(ComponentKind::Component),
+            0x05 => Ok(ComponentKind::Instance),
+            kind => Err(format!("Unknown component extern kind {}", kind)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ComponentKind::CoreModule => "module",
+            ComponentKind::Func => "func",
+            ComponentKind::Value => "value",
+            ComponentKind::Type => "type",
+            ComponentKind::Component => "component",
+            ComponentKind::Instance => "instance",
+        }
+    }
+}
+
+// A top-level import or export of a component, e.g. the `rig:agent/agent` instance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ComponentExtern {
+    pub name: String,
+    pub kind: ComponentKind,
+}
+
+// Size limits of a memory, in 64 KiB pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Limits {
+    pub initial: u64,
+    pub maximum: Option<u64>,
+    pub shared: bool,
+    pub memory64: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Import {
+    pub module: String,
+    pub name: String,
+    pub kind: ExternalKind,
+    // Only set for imported memories
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<Limits>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Export {
+    pub name: String,
+    pub kind: ExternalKind,
+    pub index: u32,
+}
+
+// One top-level section of a module
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub id: u8,
+    // Only set for custom sections
+    pub name: Option<String>,
+    // The whole section, including its id and size header
+    pub range: Range<usize>,
+    // The section contents; for custom sections this starts after the name
+    pub payload: Range<usize>,
+}
+
+// Reads an unsigned LEB128 value, advancing `pos`
+pub fn read_leb128(bytes: &[u8], pos: &mut usize) -> Result<u32, String> {
+    let mut result: u32 = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.get(*pos).ok_or("Unexpected end of module")?;
+        *pos += 1;
+        result |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+        shift += 7;
+        if shift > 28 {
+            return Err("LEB128 value is too large".to_string());
+        }
+    }
+}
+
+fn read_byte(bytes: &[u8], pos: &mut usize) -> Result<u8, String> {
+    let byte = *bytes.get(*pos).ok_or("Unexpected end of module")?;
+    *pos += 1;
+    Ok(byte)
+}
+
+fn read_name(bytes: &[u8], pos: &mut usize) -> Result<String, String> {
+    let len = read_leb128(bytes, pos)? as usize;
+    let name = bytes
+        .get(*pos..*pos + len)
+        .and_then(|name| std::str::from_utf8(name).ok())
+        .ok_or("Invalid name")?;
+    *pos += len;
+    Ok(name.to_string())
+}
+
+// 64-bit LEB128, used by memory64 limits
+fn read_leb128_u64(bytes: &[u8], pos: &mut usize) -> Result<u64, String> {
+    let mut result: u64 = 0;
+    let mut shift = 0;
+    loop {
+        let byte = read_byte(bytes, pos)?;
+        result |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+        shift += 7;
+        if shift > 63 {
+            return Err("LEB128 value is too large".to_string());
+        }
+    }
+}
+
+fn read_limits(bytes: &[u8], pos: &mut usize) -> Result<Limits, String> {
+    let flags = read_byte(bytes, pos)?;
+    let initial = read_leb128_u64(bytes, pos)?;
+    let maximum = if flags & 0x01 != 0 {
+        Some(read_leb128_u64(bytes, pos)?)
+    } else {
+        None
+    };
+    Ok(Limits {
+        initial,
+        maximum,
+        shared: flags & 0x02 != 0,
+        memory64: flags & 0x04 != 0,
+    })
+}
+
+// Components share the preamble with core modules but have a different layer
+pub fn is_component(bytes: &[u8]) -> bool {
+    bytes.len() >= 8 && &bytes[0..4] == b"\0asm" && bytes[6..8] == [1, 0]
+}
+
+// Whether a component exports `interface`, with or without a version suffix.
+// Export names are matched in the component's export sections rather than
+// fully decoding the export descriptors.
+pub fn exports_interface(bytes: &[u8], interface: &str) -> bool {
+    if !is_component(bytes) {
+        return false;
+    }
+    let needle = interface.as_bytes();
+    sections(bytes).is_ok_and(|sections| {
+        sections
+            .iter()
+            .filter(|section| section.id == COMPONENT_EXPORT_SECTION)
+            .any(|section| bytes[section.payload.clone()].windows(needle.len()).any(|window| window == needle))
+    })
+}
+
+// An import or export name: plain, or followed by a version suffix
+fn read_component_name(bytes: &[u8], pos: &mut usize) -> Result<String, String> {
+    match read_byte(bytes, pos)? {
+        0x00 => read_name(bytes, pos),
+        0x01 => {
+            let name = read_name(bytes, pos)?;
+            read_name(bytes, pos)?;
+            Ok(name)
+        }
+        prefix => Err(format!("Unknown component name prefix {}", prefix)),
+    }
+}
+
+// Reads an extern descriptor, keeping only its kind
+fn read_extern_desc(bytes: &[u8], pos: &mut usize) -> Result<ComponentKind, String> {
+    let kind = ComponentKind::from_byte(read_byte(bytes, pos)?)?;
+    match kind {
+        ComponentKind::CoreModule => {
+            read_byte(bytes, pos)?;
+            read_leb128(bytes, pos)?;
+        }
+        ComponentKind::Value => {
+            read_byte(bytes, pos)?;
+            read_leb128(bytes, pos)?;
+        }
+        // `(sub resource)` has no index
+        ComponentKind::Type => {
+            if read_byte(bytes, pos)? == 0x00 {
+                read_leb128(bytes, pos)?;
+            }
+        }
+        ComponentKind::Func | ComponentKind::Component | ComponentKind::Instance => {
+            read_leb128(bytes, pos)?;
+        }
+    }
+    Ok(kind)
+}
+
+fn component_sections(bytes: &[u8], id: u8) -> Result<Vec<Section>, String> {
+    if !is_component(bytes) {
+        return Err("Core modules have no component import or export sections".to_string());
+    }
+    Ok(sections(bytes)?.into_iter().filter(|s| s.id == id).collect())
+}
+
+// Top-level imports of a component; nested components and core modules are not walked
+pub fn component_imports(bytes: &[u8]) -> Result<Vec<ComponentExtern>, String> {
+    let mut imports = Vec::new();
+    for section in component_sections(bytes, COMPONENT_IMPORT_SECTION)? {
+        let mut pos = section.payload.start;
+        let count = read_leb128(bytes, &mut pos)?;
+        for _ in 0..count {
+            let name = read_component_name(bytes, &mut pos)?;
+            let kind = read_extern_desc(bytes, &mut pos)?;
+            imports.push(ComponentExtern { name, kind });
+        }
+    }
+    Ok(imports)
+}
+
+// Top-level exports of a component
+pub fn component_exports(bytes: &[u8]) -> Result<Vec<ComponentExtern>, String> {
+    let mut exports = Vec::new();
+    for section in component_sections(bytes, COMPONENT_EXPORT_SECTION)? {
+        let mut pos = section.payload.start;
+        let count = read_leb128(bytes, &mut pos)?;
+        for _ in 0..count {
+            let name = read_component_name(bytes, &mut pos)?;
+            let sort = read_byte(bytes, &mut pos)?;
+            if sort == 0x00 {
+                // core sort
+                read_byte(bytes, &mut pos)?;
+            }
+            read_leb128(bytes, &mut pos)?;
+            // Optional ascribed type
+            if read_byte(bytes, &mut pos)? == 0x01 {
+                read_extern_desc(bytes, &mut pos)?;
+            }
+            exports.push(ComponentExtern { name, kind: ComponentKind::from_byte(sort)? });
+        }
+    }
+    Ok(exports)
+}
+
+pub fn sections(bytes: &[u8]) -> Result<Vec<Section>, String> {
+    if bytes.len() < 8 || &bytes[0..4] != b"\0asm" {
+        return Err("Not a WebAssembly module".to_string());
+    }
+
+    let mut sections = Vec::new();
+    let mut pos = 8;
+    while pos < bytes.len() {
+        let start = pos;
+        let id = read_byte(bytes, &mut pos)?;
+        let size = read_leb128(bytes, &mut pos)? as usize;
+        let end = pos
+            .checked_add(size)
+            .filter(|&end| end <= bytes.len())
+            .ok_or("Section runs past the end of the module")?;
+
+        let (name, payload_start) = if id == CUSTOM_SECTION {
+            let mut name_pos = pos;
+            let name = read_name(bytes, &mut name_pos)?;
+            (Some(name), name_pos)
+        } else {
+            (None, pos)
+        };
+
+        sections.push(Section {
+            id,
+            name,
+            range: start..end,
+            payload: payload_start..end,
+        });
+        pos = end;
+    }
+
+    Ok(sections)
+}
+
+// Copies a module, leaving out the custom sections for which `remove` returns true
+pub fn strip_custom_sections(bytes: &[u8], remove: impl Fn(&str) -> bool) -> Result<Vec<u8>, String> {
+    let mut output = bytes[0..8.min(bytes.len())].to_vec();
+    for section in sections(bytes)? {
+        let keep = section.name.as_deref().is_none_or(|name| !remove(name));
+        if keep {
+            output.extend_from_slice(&bytes[section.range]);
+        }
+    }
+    Ok(output)
+}
+
+fn find_section(bytes: &[u8], id: u8) -> Result<Option<Section>, String> {
+    if is_component(bytes) {
+        return Err("Components have no core import or export sections".to_string());
+    }
+    Ok(sections(bytes)?.into_iter().find(|s| s.id == id))
+}
+
+pub fn imports(bytes: &[u8]) -> Result<Vec<Import>, String> {
+    let section = match find_section(bytes, IMPORT_SECTION)? {
+        Some(section) => section,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut pos = section.payload.start;
+    let count = read_leb128(bytes, &mut pos)?;
+    let mut imports = Vec::new();
+    for _ in 0..count {
+        let module = read_name(bytes, &mut pos)?;
+        let name = read_name(bytes, &mut pos)?;
+        let kind = ExternalKind::from_byte(read_byte(bytes, &mut pos)?)?;
+        let mut memory = None;
+        match kind {
+            ExternalKind::Func => {
+                read_leb128(bytes, &mut pos)?;
+            }
+            ExternalKind::Table => {
+                read_byte(bytes, &mut pos)?;
+                read_limits(bytes, &mut pos)?;
+            }
+            ExternalKind::Memory => memory = Some(read_limits(bytes, &mut pos)?),
+            ExternalKind::Global => {
+                read_byte(bytes, &mut pos)?;
+                read_byte(bytes, &mut pos)?;
+            }
+            ExternalKind::Tag => {
+                read_byte(bytes, &mut pos)?;
+                read_leb128(bytes, &mut pos)?;
+            }
+        }
+        imports.push(Import { module, name, kind, memory });
+    }
+    Ok(imports)
+}
+
+pub fn exports(bytes: &[u8]) -> Result<Vec<Export>, String> {
+    let section = match find_section(bytes, EXPORT_SECTION)? {
+        Some(section) => section,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut pos = section.payload.start;
+    let count = read_leb128(bytes, &mut pos)?;
+    let mut exports = Vec::new();
+    for _ in 0..count {
+        let name = read_name(bytes, &mut pos)?;
+        let kind = ExternalKind::from_byte(read_byte(bytes, &mut pos)?)?;
+        let index = read_leb128(bytes, &mut pos)?;
+        exports.push(Export { name, kind, index });
+    }
+    Ok(exports)
+}
+
+// Memories defined by the module itself; imported memories are listed with the imports
+pub fn memories(bytes: &[u8]) -> Result<Vec<Limits>, String> {
+    let section = match find_section(bytes, MEMORY_SECTION)? {
+        Some(section) => section,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut pos = section.payload.start;
+    let count = read_leb128(bytes, &mut pos)?;
+    (0..count).map(|_| read_limits(bytes, &mut pos)).collect()
+}
+
+// Function names from the `name` custom section, keyed by function index
+pub fn function_names(bytes: &[u8]) -> Result<HashMap<u32, String>, String> {
+    let mut names = HashMap::new();
+    let section = match sections(bytes)?.into_iter().find(|s| s.name.as_deref() == Some("name")) {
+        Some(section) => section,
+        None => return Ok(names),
+    };
+
+    let mut pos = section.payload.start;
+    while pos < section.payload.end {
+        let subsection = read_byte(bytes, &mut pos)?;
+        let size = read_leb128(bytes, &mut pos)? as usize;
+        let end = pos + size;
+        // Subsection 1 holds function names
+        if subsection == 1 {
+            let mut entry = pos;
+            let count = read_leb128(bytes, &mut entry)?;
+            for _ in 0..count {
+                let index = read_leb128(bytes, &mut entry)?;
+                names.insert(index, read_name(bytes, &mut entry)?);
+            }
+        }
+        pos = end;
+    }
+
+    Ok(names)
+}
+
+// Function index and byte range (size prefix included) of every defined
+// function's body in the code section
+pub fn function_bodies(bytes: &[u8]) -> Result<Vec<(u32, Range<usize>)>, String> {
+    // Imported functions come first in the function index space
+    let imported = imports(bytes)?.iter().filter(|i| i.kind == ExternalKind::Func).count() as u32;
+
+    let mut bodies = Vec::new();
+    if let Some(code) = find_section(bytes, CODE_SECTION)? {
+        let mut pos = code.payload.start;
+        let count = read_leb128(bytes, &mut pos)?;
+        for i in 0..count {
+            let body_start = pos;
+            let size = read_leb128(bytes, &mut pos)? as usize;
+            pos += size;
+            bodies.push((imported + i, body_start..pos));
+        }
+    }
+    Ok(bodies)
+}
+
+// Body size of every defined function, largest first. Functions without an
+// entry in the name section are shown as `func[index]`.
+pub fn function_sizes(bytes: &[u8]) -> Result<Vec<(String, u64)>, String> {
+    let names = function_names(bytes)?;
+    let mut sizes: Vec<(String, u64)> = function_bodies(bytes)?
+        .into_iter()
+        .map(|(index, body)| {
+            let name = names
+                .get(&index)
+                .cloned()
+                .unwrap_or_else(|| format!("func[{}]", index));
+            (name, body.len() as u64)
+        })
+        .collect();
+
+    sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    Ok(sizes)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn custom_section(name: &str, payload: &[u8]) -> Vec<u8> {
+        let mut body = vec![name.len() as u8];
+        body.extend_from_slice(name.as_bytes());
+        body.extend_from_slice(payload);
+        let mut section = vec![CUSTOM_SECTION, body.len() as u8];
+        section.extend(body);
+        section
+    }
+
+    // A module with one imported function and two defined functions named
+    // `small` and `large`
+    pub(crate) fn sample_module() -> Vec<u8> {
+        let mut module = b"\0asm\x01\0\0\0".to_vec();
+        // type section: one type, () -> ()
+        module.extend_from_slice(&[1, 4, 1, 0x60, 0, 0]);
+        // import section: env.f as a function of type 0
+        module.extend_from_slice(&[2, 9, 1, 3, b'e', b'n', b'v', 1, b'f', IMPORT_FUNC, 0]);
+        // function section: two functions of type 0
+        module.extend_from_slice(&[3, 3, 2, 0, 0]);
+        // code section: a 2 byte body and a 5 byte body
+        module.extend_from_slice(&[CODE_SECTION, 10, 2, 2, 0, 0x0b, 5, 0, 0x01, 0x01, 0x01, 0x0b]);
+        // name section with function names for indices 1 and 2
+        let names = [1, 15, 2, 1, 5, b's', b'm', b'a', b'l', b'l', 2, 5, b'l', b'a', b'r', b'g', b'e'];
+        module.extend(custom_section("name", &names));
+        module.extend(custom_section("producers", &[0]));
+        module
+    }
+
+    // A component that imports instance 0 as `wasi:cli/environment@0.2.0`
+    // and exports instance 1 as `rig:agent/agent@0.1.0`
+    pub(crate) fn sample_component() -> Vec<u8> {
+        let import = b"wasi:cli/environment@0.2.0";
+        let export = b"rig:agent/agent@0.1.0";
+        let mut component = b"\0asm\x0d\0\x01\0".to_vec();
+        component.extend_from_slice(&[COMPONENT_IMPORT_SECTION, import.len() as u8 + 5, 1, 0, import.len() as u8]);
+        component.extend_from_slice(import);
+        component.extend_from_slice(&[5, 0]);
+        component.extend_from_slice(&[COMPONENT_EXPORT_SECTION, export.len() as u8 + 6, 1, 0, export.len() as u8]);
+        component.extend_from_slice(export);
+        component.extend_from_slice(&[5, 1, 0]);
+        component
+    }
+
+    #[test]
+    fn test_component_imports_and_exports() {
+        let component = sample_component();
+        assert_eq!(
+            component_imports(&component).unwrap(),
+            vec![ComponentExtern { name: "wasi:cli/environment@0.2.0".to_string(), kind: ComponentKind::Instance }]
+        );
+        assert_eq!(
+            component_exports(&component).unwrap(),
+            vec![ComponentExtern { name: "rig:agent/agent@0.1.0".to_string(), kind: ComponentKind::Instance }]
+        );
+        assert!(component_exports(&sample_module()).is_err());
+    }
+
+    #[test]
+    fn test_exports_interface() {
+        let name = b"rig:agent/agent@0.1.0";
+        let component = sample_component();
+        assert!(exports_interface(&component, "rig:agent/agent"));
+        assert!(!exports_interface(&component, "wasi:http/incoming-handler"));
+        // Imports are not exports
+        assert!(!exports_interface(&component, "wasi:cli/environment"));
+
+        // The same name in a custom section of a core module is not an export
+        let mut module = sample_module();
+        module.extend(custom_section("names", name));
+        assert!(!exports_interface(&module, "rig:agent/agent"));
+    }
+
+    #[test]
+    fn test_sections() {
+        let module = sample_module();
+        let sections = sections(&module).unwrap();
+        let ids: Vec<u8> = sections.iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 10, 0, 0]);
+        assert_eq!(sections[5].name.as_deref(), Some("producers"));
+    }
+
+    #[test]
+    fn test_strip_custom_sections() {
+        let module = sample_module();
+        let stripped = strip_custom_sections(&module, |name| name == "name").unwrap();
+        let names: Vec<_> = sections(&stripped).unwrap().into_iter().filter_map(|s| s.name).collect();
+        assert_eq!(names, vec!["producers"]);
+        assert!(strip_custom_sections(b"not wasm", |_| true).is_err());
+    }
+
+    #[test]
+    fn test_imports_exports_and_memory() {
+        let mut module = sample_module();
+        // Memory and export sections go between the function and code sections
+        let code_start = sections(&module).unwrap()[3].range.start;
+        let rest = module.split_off(code_start);
+        // memory section: one memory of 17 pages, at most 256
+        module.extend_from_slice(&[MEMORY_SECTION, 5, 1, 1, 17, 0x80, 2]);
+        // export section: function 1 as `process` and memory 0 as `memory`
+        module.extend_from_slice(&[EXPORT_SECTION, 20, 2, 7]);
+        module.extend_from_slice(b"process");
+        module.extend_from_slice(&[IMPORT_FUNC, 1, 6]);
+        module.extend_from_slice(b"memory");
+        module.extend_from_slice(&[IMPORT_MEMORY, 0]);
+        module.extend(rest);
+
+        let imports = imports(&module).unwrap();
+        assert_eq!(imports.len(), 1);
+        assert_eq!((imports[0].module.as_str(), imports[0].name.as_str()), ("env", "f"));
+        assert_eq!(imports[0].kind, ExternalKind::Func);
+
+        let exports = exports(&module).unwrap();
+        let names: Vec<(&str, ExternalKind)> = exports.iter().map(|e| (e.name.as_str(), e.kind)).collect();
+        assert_eq!(names, vec![("process", ExternalKind::Func), ("memory", ExternalKind::Memory)]);
+
+        let memories = memories(&module).unwrap();
+        assert_eq!(memories, vec![Limits { initial: 17, maximum: Some(256), shared: false, memory64: false }]);
+        assert_eq!(function_sizes(&module).unwrap()[0].0, "large");
+    }
+
+    #[test]
+    fn test_function_sizes() {
+        let sizes = function_sizes(&sample_module()).unwrap();
+        assert_eq!(sizes, vec![("large".to_string(), 6), ("small".to_string(), 3)]);
+
+        let stripped = strip_custom_sections(&sample_module(), |name| name == "name").unwrap();
+        assert_eq!(function_sizes(&stripped).unwrap()[0].0, "func[2]");
+    }
+}
@@ -0,0 +1,410 @@
+// Web Worker wrapper for browser builds. The exported classes and functions are
+// read from the wasm-bindgen glue, and two scripts are generated: a worker
+// entry that loads the module and runs requests, and a main-thread proxy with
+// the same API where every call is a postMessage resolved by request id.
+use crate::compiler::error::CompileError;
+use crate::compiler::target::Target;
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const WORKER_TEMPLATE: &str = include_str!("templates/worker.js");
+const PROXY_TEMPLATE: &str = include_str!("templates/proxy.js");
+
+// Glue methods that manage the wasm object itself rather than being part of its API
+const INTERNAL_METHODS: &[&str] = &["free", "__destroy_into_raw", "__wrap", "__unwrap"];
+
+// Glue functions that load the module; the worker calls them itself
+const INIT_FUNCTIONS: &[&str] = &["initSync", "__wbg_init"];
+
+pub fn worker_file(out_name: &str) -> String {
+    format!("{}.worker.js", out_name)
+}
+
+pub fn proxy_file(out_name: &str) -> String {
+    format!("{}.proxy.js", out_name)
+}
+
+pub fn proxy_types_file(out_name: &str) -> String {
+    format!("{}.proxy.d.ts", out_name)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GlueFunction {
+    pub name: String,
+    pub params: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GlueClass {
+    pub name: String,
+    // None when the class has no #[wasm_bindgen(constructor)]
+    pub constructor: Option<Vec<String>>,
+    pub methods: Vec<GlueFunction>,
+    pub static_methods: Vec<GlueFunction>,
+}
+
+// What the glue exports
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GlueApi {
+    pub classes: Vec<GlueClass>,
+    pub functions: Vec<GlueFunction>,
+}
+
+// The worker stage of a compile report
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkerReport {
+    pub files: Vec<PathBuf>,
+    pub classes: Vec<String>,
+    pub functions: Vec<String>,
+    // Classes JS cannot construct, which the proxy leaves out
+    pub skipped: Vec<String>,
+}
+
+impl fmt::Display for WorkerReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let files: Vec<String> = self
+            .files
+            .iter()
+            .map(|file| file.file_name().unwrap_or_default().to_string_lossy().into_owned())
+            .collect();
+        write!(f, "Worker: {}", files.join(", "))?;
+        let mut api = self.classes.clone();
+        api.extend(self.functions.iter().map(|name| format!("{}()", name)));
+        if !api.is_empty() {
+            write!(f, "\n  proxied: {}", api.join(", "))?;
+        }
+        if !self.skipped.is_empty() {
+            write!(f, "\n  skipped (no constructor): {}", self.skipped.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// `name(a, b) {` as wasm-bindgen writes functions and methods
+fn signature(line: &str) -> Option<(String, Vec<String>)> {
+    let line = line.trim().strip_suffix('{')?.trim_end();
+    let (name, rest) = line.split_once('(')?;
+    let params = rest.strip_suffix(')')?;
+    let is_identifier = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if !is_identifier(name) {
+        return None;
+    }
+    let params: Vec<String> = params.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect();
+    if !params.iter().all(|p| is_identifier(p)) {
+        return None;
+    }
+    Some((name.to_string(), params))
+}
+
+// Reads exported classes and functions from wasm-bindgen's glue. Class bodies
+// are indented four spaces; getters, setters and internal methods are skipped.
+pub fn parse_glue(js: &str) -> GlueApi {
+    let mut api = GlueApi::default();
+    let mut class: Option<GlueClass> = None;
+    for line in js.lines() {
+        if let Some(current) = &mut class {
+            if line.starts_with('}') {
+                api.classes.extend(class.take());
+                continue;
+            }
+            let member = match line.strip_prefix("    ") {
+                Some(member) if !member.starts_with(' ') => member,
+                _ => continue,
+            };
+            let (is_static, member) = match member.strip_prefix("static ") {
+                Some(member) => (true, member),
+                None => (false, member),
+            };
+            let (name, params) = match signature(member) {
+                Some(signature) => signature,
+                None => continue,
+            };
+            if INTERNAL_METHODS.contains(&name.as_str()) || name.starts_with("__") {
+                continue;
+            }
+            let function = GlueFunction { name, params };
+            if is_static {
+                current.static_methods.push(function);
+            } else if function.name == "constructor" {
+                current.constructor = Some(function.params);
+            } else {
+                current.methods.push(function);
+            }
+        } else if let Some(name) = line.strip_prefix("export class ").and_then(|rest| rest.strip_suffix(" {")) {
+            class = Some(GlueClass {
+                name: name.trim().to_string(),
+                constructor: None,
+                methods: Vec::new(),
+                static_methods: Vec::new(),
+            });
+        } else if let Some((name, params)) = line.strip_prefix("export function ").and_then(signature) {
+            if !INIT_FUNCTIONS.contains(&name.as_str()) && !name.starts_with("__") {
+                api.functions.push(GlueFunction { name, params });
+            }
+        }
+    }
+    api
+}
+
+pub fn worker_js(target: Target, out_name: &str, api: &GlueApi) -> String {
+    // Web glue is initialized explicitly; bundlers instantiate the module on import
+    let (import, init) = match target {
+        Target::Web => ("init, * as wasm", "init()"),
+        _ => ("* as wasm", "Promise.resolve()"),
+    };
+    let classes: Vec<String> = api
+        .classes
+        .iter()
+        .map(|class| format!("{name}: wasm.{name}", name = class.name))
+        .collect();
+    WORKER_TEMPLATE
+        .replace("{{out_name}}", out_name)
+        .replace("{{import}}", import)
+        .replace("{{init}}", init)
+        .replace("{{classes}}", &classes.join(", "))
+}
+
+pub fn proxy_js(out_name: &str, api: &GlueApi) -> String {
+    let mut exports = String::new();
+    for class in &api.classes {
+        let params = match &class.constructor {
+            Some(params) => params.join(", "),
+            None => continue,
+        };
+        exports.push_str(&format!("\nexport class {} {{\n  #remote;\n\n", class.name));
+        exports.push_str(&format!(
+            "  constructor({params}) {{\n    this.#remote = new Remote('{class}', [{params}]);\n  }}\n",
+            params = params,
+            class = class.name
+        ));
+        for method in &class.methods {
+            exports.push_str(&format!(
+                "\n  {name}({params}) {{\n    return this.#remote.call('{name}', [{params}]);\n  }}\n",
+                name = method.name,
+                params = method.params.join(", ")
+            ));
+        }
+        exports.push_str("\n  free() {\n    return this.#remote.free();\n  }\n");
+        for method in &class.static_methods {
+            exports.push_str(&format!(
+                "\n  static {name}({params}) {{\n    return request({{ kind: 'static', class: '{class}', method: '{name}', args: [{params}] }});\n  }}\n",
+                name = method.name,
+                params = method.params.join(", "),
+                class = class.name
+            ));
+        }
+        exports.push_str("}\n");
+    }
+    for function in &api.functions {
+        exports.push_str(&format!(
+            "\nexport function {name}({params}) {{\n  return request({{ kind: 'function', method: '{name}', args: [{params}] }});\n}}\n",
+            name = function.name,
+            params = function.params.join(", ")
+        ));
+    }
+    PROXY_TEMPLATE.replace("{{out_name}}", out_name).replace("{{exports}}", &exports)
+}
+
+// Arguments and results cross postMessage, so the proxy is typed loosely
+pub fn proxy_d_ts(api: &GlueApi) -> String {
+    let params = |params: &[String]| params.iter().map(|p| format!("{}: any", p)).collect::<Vec<_>>().join(", ");
+    let mut types = String::from(
+        "export function startWorker(url?: string | URL): Worker;\nexport function terminateWorker(): void;\n",
+    );
+    for class in &api.classes {
+        let constructor = match &class.constructor {
+            Some(constructor) => constructor,
+            None => continue,
+        };
+        types.push_str(&format!("\nexport class {} {{\n", class.name));
+        types.push_str(&format!("  constructor({});\n", params(constructor)));
+        for method in &class.methods {
+            types.push_str(&format!("  {}({}): Promise<any>;\n", method.name, params(&method.params)));
+        }
+        types.push_str("  free(): Promise<void>;\n");
+        for method in &class.static_methods {
+            types.push_str(&format!("  static {}({}): Promise<any>;\n", method.name, params(&method.params)));
+        }
+        types.push_str("}\n");
+    }
+    for function in &api.functions {
+        types.push_str(&format!("\nexport function {}({}): Promise<any>;\n", function.name, params(&function.params)));
+    }
+    types
+}
+
+// Module workers need ES module glue
+pub fn check_target(target: Target) -> Result<(), CompileError> {
+    if !matches!(target, Target::Web | Target::Bundler) {
+        return Err(CompileError::InvalidInput(format!(
+            "--emit-worker needs web or bundler output, not {}",
+            target
+        )));
+    }
+    Ok(())
+}
+
+// Writes the worker entry, the proxy and, with `typescript`, the proxy's types
+pub fn write_worker(
+    output_dir: &Path,
+    target: Target,
+    out_name: &str,
+    typescript: bool,
+) -> Result<WorkerReport, CompileError> {
+    // Bundler glue re-exports everything from the _bg.js module
+    let glue = match target {
+        Target::Bundler => format!("{}_bg.js", out_name),
+        _ => format!("{}.js", out_name),
+    };
+    let api = parse_glue(&fs::read_to_string(output_dir.join(&glue))?);
+    if api.classes.is_empty() && api.functions.is_empty() {
+        return Err(CompileError::PostProcess(format!("{} exports nothing to run in a worker", glue)));
+    }
+
+    let mut files = vec![output_dir.join(worker_file(out_name)), output_dir.join(proxy_file(out_name))];
+    fs::write(&files[0], worker_js(target, out_name, &api))?;
+    fs::write(&files[1], proxy_js(out_name, &api))?;
+    if typescript {
+        let types = output_dir.join(proxy_types_file(out_name));
+        fs::write(&types, proxy_d_ts(&api))?;
+        files.push(types);
+    }
+
+    let (classes, skipped) = api.classes.iter().partition::<Vec<_>, _>(|class| class.constructor.is_some());
+    Ok(WorkerReport {
+        files,
+        classes: classes.into_iter().map(|class| class.name.clone()).collect(),
+        functions: api.functions.iter().map(|function| function.name.clone()).collect(),
+        skipped: skipped.into_iter().map(|class| class.name.clone()).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Trimmed from wasm-bindgen's web output
+    const GLUE: &str = r#"let wasm;
+
+export function initialize_rig_wasm(config_json) {
+    const ptr0 = passStringToWasm0(config_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+}
+
+export class WasmAgent {
+
+    __destroy_into_raw() {
+        const ptr = this.__wbg_ptr;
+        return ptr;
+    }
+
+    free() {
+        const ptr = this.__destroy_into_raw();
+    }
+    /**
+     * @param {string} config_json
+     */
+    constructor(config_json) {
+        if (r2) {
+            throw takeObject(r1);
+        }
+    }
+    /**
+     * @param {string} input
+     * @returns {Promise<string>}
+     */
+    process(input) {
+        return takeObject(ret);
+    }
+    /**
+     * @param {string} context
+     */
+    addContext(context) {
+    }
+    clearContext() {
+    }
+}
+
+export class SearchHit {
+
+    static __wrap(ptr) {
+    }
+    get score() {
+        return ret;
+    }
+}
+
+export class WasmVectorStore {
+
+    constructor() {
+    }
+    /**
+     * @param {string} id
+     * @param {Float32Array} vector
+     * @param {any} metadata
+     */
+    add(id, vector, metadata) {
+    }
+    /**
+     * @param {Float32Array} query
+     * @param {number} k
+     * @returns {any}
+     */
+    search(query, k) {
+    }
+    static withCapacity(capacity) {
+    }
+}
+
+export function initSync(module) {
+}
+"#;
+
+    #[test]
+    fn test_parse_glue() {
+        let api = parse_glue(GLUE);
+        let names: Vec<&str> = api.classes.iter().map(|class| class.name.as_str()).collect();
+        assert_eq!(names, vec!["WasmAgent", "SearchHit", "WasmVectorStore"]);
+
+        let agent = &api.classes[0];
+        assert_eq!(agent.constructor, Some(vec!["config_json".to_string()]));
+        let methods: Vec<&str> = agent.methods.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(methods, vec!["process", "addContext", "clearContext"]);
+
+        assert_eq!(api.classes[1].constructor, None);
+        assert!(api.classes[1].methods.is_empty() && api.classes[1].static_methods.is_empty());
+
+        let store = &api.classes[2];
+        assert_eq!(store.constructor, Some(vec![]));
+        assert_eq!(store.methods[0].params, vec!["id", "vector", "metadata"]);
+        assert_eq!(store.static_methods[0].name, "withCapacity");
+
+        let functions: Vec<&str> = api.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(functions, vec!["initialize_rig_wasm"]);
+    }
+
+    #[test]
+    fn test_generated_scripts() {
+        let api = parse_glue(GLUE);
+
+        let worker = worker_js(Target::Web, "rig_wasm", &api);
+        assert!(worker.contains("import init, * as wasm from './rig_wasm.js';"));
+        assert!(worker.contains("const ready = init();"));
+        assert!(worker.contains("WasmAgent: wasm.WasmAgent, SearchHit: wasm.SearchHit"));
+        assert!(worker_js(Target::Bundler, "rig_wasm", &api).contains("const ready = Promise.resolve();"));
+
+        let proxy = proxy_js("rig_wasm", &api);
+        assert!(proxy.contains("new URL('./rig_wasm.worker.js', import.meta.url)"));
+        assert!(proxy.contains("this.#remote = new Remote('WasmAgent', [config_json]);"));
+        assert!(proxy.contains("  addContext(context) {\n    return this.#remote.call('addContext', [context]);"));
+        assert!(proxy.contains("  search(query, k) {\n    return this.#remote.call('search', [query, k]);"));
+        assert!(proxy.contains("method: 'withCapacity', args: [capacity]"));
+        assert!(proxy.contains("export function initialize_rig_wasm(config_json) {"));
+        assert!(!proxy.contains("class SearchHit"));
+
+        let types = proxy_d_ts(&api);
+        assert!(types.contains("  process(input: any): Promise<any>;"));
+        assert!(types.contains("  free(): Promise<void>;"));
+    }
+}
@@ -0,0 +1,64 @@
+// The rig:agent component for WASI hosts (see wit/world.wit). It mirrors
+// `WasmAgent`, with the provider configured from the environment and requests
+// sent through wasi:http.
+
+use crate::adapters::wasi_executor::block_on;
+use crate::adapters::DefaultHttpClient;
+use crate::config::ProviderConfig;
+use crate::wasm_bindings::agent::create_provider;
+use rig::agent::Agent;
+use rig::providers::Provider;
+use std::cell::RefCell;
+
+wit_bindgen::generate!({
+    world: "rig-agent",
+    path: "wit",
+});
+
+use exports::rig::agent::agent::Guest;
+
+thread_local! {
+    // Created on first use, so the host can set the environment before calling in
+    static AGENT: RefCell<Option<Agent<Box<dyn Provider>>>> = RefCell::new(None);
+}
+
+fn provider_config() -> ProviderConfig {
+    ProviderConfig {
+        api_key: std::env::var("RIG_API_KEY").unwrap_or_default(),
+        model: std::env::var("RIG_MODEL").unwrap_or_else(|_| "gpt-3.5-turbo".to_string()),
+        api_base_url: std::env::var("RIG_API_BASE_URL").ok(),
+        additional_params: None,
+    }
+}
+
+fn with_agent<T>(f: impl FnOnce(&mut Agent<Box<dyn Provider>>) -> T) -> Result<T, String> {
+    AGENT.with(|agent| {
+        let mut agent = agent.borrow_mut();
+        if agent.is_none() {
+            let provider = create_provider(provider_config(), DefaultHttpClient::new())
+                .map_err(|e| format!("Failed to create provider: {}", e))?;
+            *agent = Some(Agent::new(provider));
+        }
+        Ok(f(agent.as_mut().expect("agent was just created")))
+    })
+}
+
+struct Component;
+
+impl Guest for Component {
+    fn process(input: String) -> Result<String, String> {
+        with_agent(|agent| {
+            block_on(agent.process(&input)).map_err(|e| format!("Agent processing error: {}", e))
+        })?
+    }
+
+    fn add_context(context: String) {
+        let _ = with_agent(|agent| agent.add_context(&context));
+    }
+
+    fn clear_context() {
+        let _ = with_agent(|agent| agent.clear_context());
+    }
+}
+
+export!(Component);
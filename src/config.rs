@@ -1,6 +1,14 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
 
+// Config baked in by `rig-wasm-compiler --config`, if any
+pub const EMBEDDED_CONFIG: Option<&str> = option_env!("RIG_WASM_CONFIG");
+
+// Prefix of secret values left out of an embedded config, e.g.
+// `$secret:providers.openai.api_key`; they must be supplied at runtime
+pub const SECRET_PLACEHOLDER: &str = "$secret:";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WasmConfig {
     // Provider-specific configurations
@@ -23,6 +31,41 @@ pub struct ProviderConfig {
     pub additional_params: Option<serde_json::Value>,
 }
 
+// Endpoints the providers call when `api_base_url` is not set, by provider name
+const DEFAULT_API_BASE_URLS: &[(&str, &str)] = &[
+    ("openai", "https://api.openai.com/v1"),
+    ("anthropic", "https://api.anthropic.com"),
+    ("cohere", "https://api.cohere.ai"),
+    ("gemini", "https://generativelanguage.googleapis.com"),
+    ("xai", "https://api.x.ai"),
+    ("groq", "https://api.groq.com/openai/v1"),
+    ("perplexity", "https://api.perplexity.ai"),
+    ("deepseek", "https://api.deepseek.com"),
+    ("mistral", "https://api.mistral.ai"),
+    ("together", "https://api.together.xyz"),
+    ("openrouter", "https://openrouter.ai/api/v1"),
+    ("huggingface", "https://router.huggingface.co"),
+    ("ollama", "http://localhost:11434"),
+];
+
+// The default endpoints, as (provider, base URL) pairs
+pub fn default_api_base_urls() -> &'static [(&'static str, &'static str)] {
+    DEFAULT_API_BASE_URLS
+}
+
+impl ProviderConfig {
+    // Where the provider named `provider` sends requests: `api_base_url`, or the
+    // provider's default endpoint
+    pub fn base_url<'a>(&'a self, provider: &str) -> Option<&'a str> {
+        self.api_base_url.as_deref().filter(|url| !url.is_empty()).or_else(|| {
+            DEFAULT_API_BASE_URLS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(provider))
+                .map(|(_, url)| *url)
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VectorStoreConfig {
     pub store_type: String,
@@ -65,4 +108,54 @@ impl WasmConfig {
         self.request_timeout = Some(request_timeout);
         self
     }
-}
\ No newline at end of file
+
+    // The embedded config with `runtime_json` merged over it. Every secret
+    // placeholder has to be filled in by `runtime_json`, which may be empty
+    // when there are none.
+    pub fn from_embedded(embedded: &str, runtime_json: &str) -> Result<Self, String> {
+        let mut config: Value = serde_json::from_str(embedded)
+            .map_err(|e| format!("Embedded config is invalid: {}", e))?;
+        if !runtime_json.trim().is_empty() {
+            let runtime: Value = serde_json::from_str(runtime_json)
+                .map_err(|e| format!("Failed to parse config: {}", e))?;
+            merge(&mut config, runtime);
+        }
+
+        let mut missing = Vec::new();
+        placeholders(&config, &mut missing);
+        if !missing.is_empty() {
+            return Err(format!("Config values must be supplied at runtime: {}", missing.join(", ")));
+        }
+
+        serde_json::from_value(config).map_err(|e| format!("Failed to parse config: {}", e))
+    }
+}
+
+// Overlays `overlay` on `base`, object by object
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+// Fields still holding a secret placeholder
+fn placeholders(value: &Value, missing: &mut Vec<String>) {
+    match value {
+        Value::String(s) if s.starts_with(SECRET_PLACEHOLDER) => {
+            missing.push(s[SECRET_PLACEHOLDER.len()..].to_string());
+        }
+        Value::Object(object) => object.values().for_each(|value| placeholders(value, missing)),
+        Value::Array(array) => array.iter().for_each(|value| placeholders(value, missing)),
+        _ => {}
+    }
+}
@@ -0,0 +1,6 @@
+// The embedded wasmtime host needs the `run` feature, and is not available when
+// this crate itself is built for wasm32
+#[cfg(all(feature = "run", not(target_arch = "wasm32")))]
+pub mod run;
+pub mod server;
+pub mod watch;
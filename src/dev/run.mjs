@@ -0,0 +1,52 @@
+// Runner for `rig-wasm-compiler run`: loads the wasm-bindgen glue in Node,
+// calls the requested export and prints the result on stdout.
+import { readFileSync } from 'node:fs';
+import { createRequire } from 'node:module';
+import { pathToFileURL } from 'node:url';
+
+const { glue, wasm, target, export: exportName, args, newArgs, context } = JSON.parse(process.env.RIG_WASM_RUN);
+
+let mod;
+if (target === 'nodejs') {
+  mod = createRequire(import.meta.url)(glue);
+} else {
+  mod = await import(pathToFileURL(glue).href);
+  // web glue has to be initialized with the module bytes, as there is no fetch for file paths
+  if (typeof mod.default === 'function') {
+    await mod.default(readFileSync(wasm));
+  }
+}
+
+function lookup(name) {
+  if (!(name in mod)) {
+    const available = Object.keys(mod).filter((key) => key !== 'default' && !key.startsWith('__'));
+    throw new Error(`${name} is not exported; available: ${available.join(', ')}`);
+  }
+  return mod[name];
+}
+
+async function call() {
+  const [name, method] = exportName.split('.');
+  if (!method) {
+    return await lookup(name)(...args);
+  }
+  const Class = lookup(name);
+  const instance = new Class(...newArgs);
+  for (const doc of context) {
+    await instance.addContext(doc);
+  }
+  if (typeof instance[method] !== 'function') {
+    throw new Error(`${name} has no method ${method}`);
+  }
+  return await instance[method](...args);
+}
+
+try {
+  const result = await call();
+  if (result !== undefined) {
+    console.log(typeof result === 'string' ? result : JSON.stringify(result, null, 2));
+  }
+} catch (e) {
+  console.error(e instanceof Error ? e.message : String(e));
+  process.exit(1);
+}
@@ -0,0 +1,322 @@
+use crate::compiler::error::CompileError;
+use crate::compiler::target::Target;
+use crate::compiler::CompileOptions;
+use clap::ArgEnum;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use wasmtime::component::{Component, Linker, ResourceTable};
+use wasmtime::{Config, Engine, Store};
+use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiView};
+use wasmtime_wasi_http::{WasiHttpCtx, WasiHttpView};
+
+wasmtime::component::bindgen!({
+    world: "rig-agent",
+    path: "wit",
+});
+
+// Environment variable the provider base URL is passed in
+pub const BASE_URL_ENV: &str = "RIG_API_BASE_URL";
+
+// Provider settings forwarded from the caller's environment
+const FORWARDED_ENV: &[&str] = &["RIG_API_KEY", "RIG_MODEL", BASE_URL_ENV];
+
+// Loads the glue for a JS target, calls the export and prints the result
+const NODE_RUNNER: &str = include_str!("run.mjs");
+
+// Where a built module is run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ArgEnum)]
+pub enum Runtime {
+    // wasmtime for WASI targets, Node for everything else
+    #[default]
+    Auto,
+    // The embedded wasmtime (WASI targets only)
+    Wasmtime,
+    // `node` on PATH (nodejs, web and bundler targets)
+    Node,
+}
+
+// What to call in a built module
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    pub output_dir: PathBuf,
+    pub out_name: String,
+    pub target: Target,
+    pub runtime: Runtime,
+    // A function (`run_example`), a class method (`WasmAgent.process`), or a
+    // rig:agent component export (`process`)
+    pub export: String,
+    pub args: Vec<String>,
+    // Constructor arguments for class methods; defaults to a provider config JSON
+    pub new_args: Vec<String>,
+    // Added with add-context / addContext before the call
+    pub context: Vec<String>,
+    // Provider base URL, e.g. a local mock server
+    pub base_url: Option<String>,
+}
+
+impl RunOptions {
+    pub fn new(options: &CompileOptions, export: Option<String>) -> Self {
+        let export = export.unwrap_or_else(|| {
+            if options.target.is_wasi() { "process" } else { "WasmAgent.process" }.to_string()
+        });
+        RunOptions {
+            output_dir: options.output_dir.clone(),
+            out_name: options.out_name.clone(),
+            target: options.target,
+            runtime: Runtime::default(),
+            export,
+            args: Vec::new(),
+            new_args: Vec::new(),
+            context: Vec::new(),
+            base_url: None,
+        }
+    }
+
+    // Provider settings for the module: the caller's RIG_* variables, with the
+    // base URL given on the command line taking precedence
+    fn env(&self) -> Vec<(String, String)> {
+        let mut env: Vec<(String, String)> = FORWARDED_ENV
+            .iter()
+            .filter_map(|name| std::env::var(name).ok().map(|value| (name.to_string(), value)))
+            .collect();
+        if let Some(base_url) = &self.base_url {
+            env.retain(|(name, _)| name != BASE_URL_ENV);
+            env.push((BASE_URL_ENV.to_string(), base_url.clone()));
+        }
+        env
+    }
+
+    // The ProviderConfig JSON `WasmAgent`'s constructor takes
+    fn provider_config(&self) -> String {
+        let env = self.env();
+        let get = |name: &str| env.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone());
+        serde_json::json!({
+            "api_key": get("RIG_API_KEY").unwrap_or_default(),
+            "model": get("RIG_MODEL").unwrap_or_else(|| "gpt-3.5-turbo".to_string()),
+            "api_base_url": get(BASE_URL_ENV),
+            "additional_params": null,
+        })
+        .to_string()
+    }
+}
+
+struct Host {
+    wasi: WasiCtx,
+    http: WasiHttpCtx,
+    table: ResourceTable,
+}
+
+impl WasiView for Host {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.wasi
+    }
+}
+
+impl WasiHttpView for Host {
+    fn ctx(&mut self) -> &mut WasiHttpCtx {
+        &mut self.http
+    }
+
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+}
+
+fn runtime_error(e: impl std::fmt::Display) -> CompileError {
+    CompileError::RunFailed(e.to_string())
+}
+
+// Runs the built module and prints what the export returns on stdout
+pub fn run(options: &RunOptions) -> Result<(), CompileError> {
+    let wasm_file = options.output_dir.join(options.target.wasm_file(&options.out_name));
+    if !wasm_file.is_file() {
+        return Err(CompileError::InputNotFound(format!("{} (build before running)", wasm_file.display())));
+    }
+
+    let runtime = match options.runtime {
+        Runtime::Auto if options.target.is_wasi() => Runtime::Wasmtime,
+        Runtime::Auto => Runtime::Node,
+        runtime => runtime,
+    };
+    match (runtime, options.target) {
+        (Runtime::Wasmtime, Target::WasiP2) => run_component(options, &wasm_file),
+        (Runtime::Wasmtime, Target::WasiP1) => run_core_module(options, &wasm_file),
+        (Runtime::Node, Target::Nodejs | Target::Web | Target::Bundler) => run_node(options),
+        (runtime, target) => Err(CompileError::InvalidInput(format!(
+            "Cannot run {} output with {}; use wasmtime for wasip1/wasip2 and node for nodejs, web or bundler",
+            target,
+            if runtime == Runtime::Node { "node" } else { "wasmtime" }
+        ))),
+    }
+}
+
+fn wasi_context(options: &RunOptions) -> WasiCtx {
+    let mut builder = WasiCtxBuilder::new();
+    builder.inherit_stdout().inherit_stderr();
+    for (name, value) in options.env() {
+        builder.env(name, value);
+    }
+    builder.build()
+}
+
+// Calls a rig:agent export of a WASI preview 2 component
+fn run_component(options: &RunOptions, wasm_file: &Path) -> Result<(), CompileError> {
+    let engine = Engine::new(Config::new().wasm_component_model(true)).map_err(runtime_error)?;
+    let component = Component::from_file(&engine, wasm_file).map_err(runtime_error)?;
+
+    let mut linker = Linker::new(&engine);
+    wasmtime_wasi::add_to_linker_sync(&mut linker).map_err(runtime_error)?;
+    wasmtime_wasi_http::add_only_http_to_linker_sync(&mut linker).map_err(runtime_error)?;
+
+    let host = Host {
+        wasi: wasi_context(options),
+        http: WasiHttpCtx::new(),
+        table: ResourceTable::new(),
+    };
+    let mut store = Store::new(&engine, host);
+    let bindings = RigAgent::instantiate(&mut store, &component, &linker).map_err(runtime_error)?;
+    let agent = bindings.rig_agent_agent();
+
+    for context in &options.context {
+        agent.call_add_context(&mut store, context).map_err(runtime_error)?;
+    }
+
+    let input = options.args.join(" ");
+    // The JS spellings are accepted too, so the same --call works for both builds
+    match options.export.trim_start_matches("WasmAgent.") {
+        "process" => match agent.call_process(&mut store, &input).map_err(runtime_error)? {
+            Ok(reply) => println!("{}", reply),
+            Err(e) => return Err(CompileError::RunFailed(e)),
+        },
+        "add-context" | "addContext" => agent.call_add_context(&mut store, &input).map_err(runtime_error)?,
+        "clear-context" | "clearContext" => agent.call_clear_context(&mut store).map_err(runtime_error)?,
+        other => {
+            return Err(CompileError::InvalidInput(format!(
+                "The rig:agent world has no export {}; expected process, add-context or clear-context",
+                other
+            )))
+        }
+    }
+    Ok(())
+}
+
+// Calls a function without parameters in a WASI preview 1 core module. Imports
+// the module does not use at runtime (such as wasi:http) are stubbed out.
+fn run_core_module(options: &RunOptions, wasm_file: &Path) -> Result<(), CompileError> {
+    let engine = Engine::default();
+    let module = wasmtime::Module::from_file(&engine, wasm_file).map_err(runtime_error)?;
+
+    let mut linker: wasmtime::Linker<wasmtime_wasi::preview1::WasiP1Ctx> = wasmtime::Linker::new(&engine);
+    wasmtime_wasi::preview1::add_to_linker_sync(&mut linker, |ctx| ctx).map_err(runtime_error)?;
+    linker.define_unknown_imports_as_traps(&module).map_err(runtime_error)?;
+
+    let mut builder = WasiCtxBuilder::new();
+    builder.inherit_stdout().inherit_stderr();
+    for (name, value) in options.env() {
+        builder.env(name, value);
+    }
+    let mut store = Store::new(&engine, builder.build_p1());
+    let instance = linker.instantiate(&mut store, &module).map_err(runtime_error)?;
+
+    // Reactors export an initializer that has to run first
+    if let Ok(initialize) = instance.get_typed_func::<(), ()>(&mut store, "_initialize") {
+        initialize.call(&mut store, ()).map_err(runtime_error)?;
+    }
+
+    let func = instance
+        .get_func(&mut store, &options.export)
+        .ok_or_else(|| CompileError::InvalidInput(format!("{} has no export {}", wasm_file.display(), options.export)))?;
+    let ty = func.ty(&store);
+    if ty.params().len() > 0 {
+        return Err(CompileError::InvalidInput(format!(
+            "{} takes parameters; only parameterless exports of wasip1 modules can be called",
+            options.export
+        )));
+    }
+    let mut results: Vec<wasmtime::Val> = ty.results().map(|_| wasmtime::Val::I32(0)).collect();
+    func.call(&mut store, &[], &mut results).map_err(runtime_error)?;
+    for result in results {
+        println!("{:?}", result);
+    }
+    Ok(())
+}
+
+// Loads the JS glue in Node and calls the export
+fn run_node(options: &RunOptions) -> Result<(), CompileError> {
+    let output_dir = fs::canonicalize(&options.output_dir)?;
+    // One runner per process, so concurrent runs do not overwrite each other's
+    let runner = std::env::temp_dir()
+        .join("rig-wasm-compiler")
+        .join(format!("run-{}.mjs", std::process::id()));
+    fs::create_dir_all(runner.parent().unwrap_or(&output_dir))?;
+    fs::write(&runner, NODE_RUNNER)?;
+
+    let mut command = Command::new("node");
+    // Bundler glue imports the .wasm as an ES module
+    if options.target == Target::Bundler {
+        command.arg("--experimental-wasm-modules");
+    }
+    let new_args = if options.new_args.is_empty() {
+        vec![options.provider_config()]
+    } else {
+        options.new_args.clone()
+    };
+    command
+        .arg(&runner)
+        .env("RIG_WASM_RUN", serde_json::json!({
+            "glue": output_dir.join(format!("{}.js", options.out_name)),
+            "wasm": output_dir.join(options.target.wasm_file(&options.out_name)),
+            "target": options.target.as_str(),
+            "export": options.export,
+            "args": options.args,
+            "newArgs": new_args,
+            "context": options.context,
+        }).to_string())
+        .envs(options.env());
+
+    let status = command.status();
+    let _ = fs::remove_file(&runner);
+    let status = status.map_err(|e| CompileError::spawn("node", "nvm install --lts", e))?;
+    if !status.success() {
+        return Err(CompileError::RunFailed(format!("{} failed in node", options.export)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_export() {
+        let wasi = CompileOptions::new(".", "dist").with_target(Target::WasiP2);
+        assert_eq!(RunOptions::new(&wasi, None).export, "process");
+        let web = CompileOptions::new(".", "dist");
+        assert_eq!(RunOptions::new(&web, None).export, "WasmAgent.process");
+        assert_eq!(RunOptions::new(&web, Some("run_example".to_string())).export, "run_example");
+    }
+
+    #[test]
+    fn test_base_url_overrides_environment() {
+        let mut options = RunOptions::new(&CompileOptions::new(".", "dist"), None);
+        options.base_url = Some("http://127.0.0.1:8000/v1".to_string());
+        let env = options.env();
+        let base_urls: Vec<&String> = env.iter().filter(|(name, _)| name == BASE_URL_ENV).map(|(_, value)| value).collect();
+        assert_eq!(base_urls, vec!["http://127.0.0.1:8000/v1"]);
+
+        let config: serde_json::Value = serde_json::from_str(&options.provider_config()).unwrap();
+        assert_eq!(config["api_base_url"], "http://127.0.0.1:8000/v1");
+    }
+
+    #[test]
+    fn test_missing_output() {
+        let mut options = RunOptions::new(&CompileOptions::new(".", "does-not-exist"), None);
+        options.runtime = Runtime::Node;
+        assert!(matches!(run(&options), Err(CompileError::InputNotFound(_))));
+    }
+}
@@ -0,0 +1,236 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// Path the page subscribes to for live-reload notifications (server-sent events)
+pub const EVENTS_PATH: &str = "/__rig_wasm/events";
+
+// Injected into every served HTML page
+const LIVE_RELOAD_SCRIPT: &str = r#"<script>
+(() => {
+  const events = new EventSource("/__rig_wasm/events");
+  events.addEventListener("reload", () => location.reload());
+  events.addEventListener("build-error", (e) => console.error("[rig-wasm] build failed:\n" + JSON.parse(e.data)));
+})();
+</script>"#;
+
+pub fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("wasm") => "application/wasm",
+        Some("js") | Some("mjs") => "text/javascript; charset=utf-8",
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("json") | Some("map") => "application/json",
+        Some("ts") => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+// Default page for output directories without an index.html. It loads the
+// web-target glue and exposes the module as `window.rig` for the console.
+pub fn index_page(out_name: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset="utf-8">
+    <title>{out_name} - Rig WASM dev server</title>
+  </head>
+  <body>
+    <h1>{out_name}</h1>
+    <p>The module is available as <code>window.rig</code>. Open the developer console to try it.</p>
+    <script type="module">
+      import init, * as rig from "./{out_name}.js";
+      await init();
+      window.rig = rig;
+      console.log("[rig-wasm] loaded {out_name}", Object.keys(rig));
+    </script>
+  </body>
+</html>
+"#,
+        out_name = out_name
+    )
+}
+
+fn inject_live_reload(html: &str) -> String {
+    match html.rfind("</body>") {
+        Some(index) => format!("{}{}\n{}", &html[..index], LIVE_RELOAD_SCRIPT, &html[index..]),
+        None => format!("{}\n{}", html, LIVE_RELOAD_SCRIPT),
+    }
+}
+
+// Maps a request path onto the served directory, refusing `..` escapes
+fn resolve_path(root: &Path, request_path: &str) -> Option<PathBuf> {
+    let path = request_path.split(['?', '#']).next().unwrap_or("/");
+    let mut resolved = root.to_path_buf();
+    for component in Path::new(path.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(resolved)
+}
+
+// Static file server for an output directory, with live-reload notifications
+pub struct DevServer {
+    address: String,
+    clients: Arc<Mutex<Vec<TcpStream>>>,
+}
+
+impl DevServer {
+    pub fn start(root: PathBuf, address: &str, out_name: &str) -> io::Result<DevServer> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?.to_string();
+        let clients = Arc::new(Mutex::new(Vec::new()));
+
+        let server_clients = clients.clone();
+        let out_name = out_name.to_string();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let root = root.clone();
+                let clients = server_clients.clone();
+                let out_name = out_name.clone();
+                thread::spawn(move || {
+                    // A client hanging up mid-request is not worth reporting
+                    let _ = handle_connection(stream, &root, &out_name, &clients);
+                });
+            }
+        });
+
+        Ok(DevServer { address, clients })
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.address)
+    }
+
+    // Sends a server-sent event to every connected page, dropping closed connections
+    pub fn notify(&self, event: &str, data: &str) {
+        let message = format!(
+            "event: {}\ndata: {}\n\n",
+            event,
+            serde_json::to_string(data).unwrap_or_default()
+        );
+        let mut clients = self.clients.lock().unwrap();
+        clients.retain_mut(|client| client.write_all(message.as_bytes()).and_then(|_| client.flush()).is_ok());
+    }
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    root: &Path,
+    out_name: &str,
+    clients: &Mutex<Vec<TcpStream>>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Drain the headers; nothing in them changes the response
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let request_path = parts.next().unwrap_or("/");
+
+    if method != "GET" && method != "HEAD" {
+        return respond(&mut stream, "405 Method Not Allowed", "text/plain", b"Method not allowed", method == "HEAD");
+    }
+
+    if request_path == EVENTS_PATH {
+        stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n")?;
+        stream.flush()?;
+        clients.lock().unwrap().push(stream);
+        return Ok(());
+    }
+
+    let path = match resolve_path(root, request_path) {
+        Some(path) if path.is_dir() => path.join("index.html"),
+        Some(path) => path,
+        None => return respond(&mut stream, "403 Forbidden", "text/plain", b"Forbidden", method == "HEAD"),
+    };
+
+    let is_index = path == root.join("index.html");
+    match fs::read(&path) {
+        Ok(body) if content_type(&path).starts_with("text/html") => {
+            let html = inject_live_reload(&String::from_utf8_lossy(&body));
+            respond(&mut stream, "200 OK", content_type(&path), html.as_bytes(), method == "HEAD")
+        }
+        Ok(body) => respond(&mut stream, "200 OK", content_type(&path), &body, method == "HEAD"),
+        Err(_) if is_index => {
+            let html = inject_live_reload(&index_page(out_name));
+            respond(&mut stream, "200 OK", "text/html; charset=utf-8", html.as_bytes(), method == "HEAD")
+        }
+        Err(_) => respond(&mut stream, "404 Not Found", "text/plain", b"Not found", method == "HEAD"),
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8], head_only: bool) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    if !head_only {
+        stream.write_all(body)?;
+    }
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_content_type() {
+        assert_eq!(content_type(Path::new("rig_wasm_bg.wasm")), "application/wasm");
+        assert_eq!(content_type(Path::new("rig_wasm.js")), "text/javascript; charset=utf-8");
+    }
+
+    #[test]
+    fn test_resolve_path_rejects_parent() {
+        let root = Path::new("/srv/out");
+        assert_eq!(resolve_path(root, "/rig_wasm.js?v=1"), Some(root.join("rig_wasm.js")));
+        assert_eq!(resolve_path(root, "/../secret"), None);
+    }
+
+    #[test]
+    fn test_serves_wasm_and_index() {
+        let root = std::env::temp_dir().join("rig-wasm-compiler-server-test");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("rig_wasm_bg.wasm"), b"\0asm\x01\0\0\0").unwrap();
+        let server = DevServer::start(root.clone(), "127.0.0.1:0", "rig_wasm").unwrap();
+        let address = server.url().trim_start_matches("http://").trim_end_matches('/').to_string();
+
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(&address).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let wasm = get("/rig_wasm_bg.wasm");
+        assert!(wasm.starts_with("HTTP/1.1 200 OK"));
+        assert!(wasm.contains("Content-Type: application/wasm"));
+
+        let index = get("/");
+        assert!(index.contains("import init, * as rig from \"./rig_wasm.js\""));
+        assert!(index.contains(EVENTS_PATH));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+use crate::compiler::diagnostics::{emit_result, MessageFormat};
+use crate::compiler::error::CompileError;
+use crate::compiler::input::resolve_input;
+use crate::compiler::progress::ConsoleObserver;
+use crate::compiler::target::Target;
+use crate::compiler::{CompileOptions, Compiler};
+use crate::dev::server::DevServer;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+// Directories at the top of the watched crate that never contain sources
+// worth rebuilding for. Nested directories with these names are still watched.
+const IGNORED_DIRS: &[&str] = &["target", ".git", "node_modules", "pkg"];
+
+// Modification times of every file under the watched paths
+pub type Snapshot = HashMap<PathBuf, SystemTime>;
+
+pub fn snapshot(paths: &[PathBuf], ignore: &[PathBuf]) -> Snapshot {
+    let mut files = HashMap::new();
+    for path in paths {
+        collect(path, ignore, true, &mut files);
+    }
+    files
+}
+
+fn collect(path: &Path, ignore: &[PathBuf], root: bool, files: &mut Snapshot) {
+    if ignore.iter().any(|ignored| path.starts_with(ignored)) {
+        return;
+    }
+
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return,
+    };
+
+    if metadata.is_dir() {
+        if let Ok(entries) = fs::read_dir(path) {
+            for entry in entries.flatten() {
+                let name = entry.file_name();
+                if root && IGNORED_DIRS.iter().any(|ignored| name == *ignored) {
+                    continue;
+                }
+                collect(&entry.path(), ignore, false, files);
+            }
+        }
+    } else if let Ok(modified) = metadata.modified() {
+        files.insert(path.to_path_buf(), modified);
+    }
+}
+
+// Builds once, serves the output directory and rebuilds whenever a watched
+// file changes. Only returns on a setup error.
+pub fn watch(options: CompileOptions, address: &str, format: MessageFormat) -> Result<(), CompileError> {
+    let resolved = resolve_input(&options.input)?;
+    let watch_paths = match &resolved.source_file {
+        Some(source_file) => vec![source_file.clone()],
+        None => vec![resolved.crate_dir.clone()],
+    };
+
+    if options.target != Target::Web {
+        eprintln!(
+            "warning: the dev server page loads the web target; {} output is served as-is",
+            options.target
+        );
+    }
+
+    fs::create_dir_all(&options.output_dir)?;
+    let output_dir = fs::canonicalize(&options.output_dir)?;
+    let server = DevServer::start(output_dir.clone(), address, &options.out_name)?;
+    eprintln!("Serving {} at {}", output_dir.display(), server.url());
+
+    let ignore = vec![output_dir];
+    let mut last = snapshot(&watch_paths, &ignore);
+    build(&options, format, &server);
+
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        let current = snapshot(&watch_paths, &ignore);
+        if current == last {
+            continue;
+        }
+
+        // Editors often write a file in several steps; wait for things to settle
+        let mut settled = current;
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let next = snapshot(&watch_paths, &ignore);
+            if next == settled {
+                break;
+            }
+            settled = next;
+        }
+        last = settled;
+
+        eprintln!("Change detected, rebuilding...");
+        build(&options, format, &server);
+    }
+}
+
+fn build(options: &CompileOptions, format: MessageFormat, server: &DevServer) {
+    let result = Compiler::new(options.clone())
+        .with_observer(ConsoleObserver::new(format))
+        .compile();
+    emit_result(format, &result);
+
+    match &result {
+        Ok(_) => server.notify("reload", ""),
+        Err(e) => server.notify("build-error", &e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_detects_changes_and_skips_ignored() {
+        let root = std::env::temp_dir().join("rig-wasm-compiler-watch-test");
+        let output = root.join("dist");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::create_dir_all(&output).unwrap();
+        fs::write(root.join("src/lib.rs"), "pub fn a() {}").unwrap();
+        fs::write(root.join("target/ignored.rlib"), "").unwrap();
+        // Only the crate's own target directory is skipped
+        fs::create_dir_all(root.join("src/target")).unwrap();
+        fs::write(root.join("src/target/mod.rs"), "").unwrap();
+        fs::write(output.join("rig_wasm.js"), "").unwrap();
+
+        let paths = vec![root.clone()];
+        let ignore = vec![output.clone()];
+        let before = snapshot(&paths, &ignore);
+        assert_eq!(before.len(), 2);
+        assert!(before.contains_key(&root.join("src/lib.rs")));
+        assert!(before.contains_key(&root.join("src/target/mod.rs")));
+
+        fs::write(root.join("src/agent.rs"), "pub fn b() {}").unwrap();
+        assert_ne!(snapshot(&paths, &ignore), before);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,21 +1,37 @@
 use wasm_bindgen::prelude::*;
 use crate::config::WasmConfig;
-use std::path::Path;
-use std::fs;
+pub use crate::compiler::diagnostics::MessageFormat;
+pub use crate::compiler::error::CompileError;
+pub use crate::compiler::lint::LintLevel;
+pub use crate::compiler::optimize::OptLevel;
+pub use crate::compiler::progress::{CompileObserver, Phase};
+pub use crate::compiler::report::{Artifact, CompileReport};
+pub use crate::compiler::secrets::SecretPolicy;
+pub use crate::compiler::target::Target;
+pub use crate::compiler::{CompileOptions, Compiler, Profile};
 
 mod config;
 mod wasm_bindings;
 mod adapters;
 mod utils;
+#[cfg(target_os = "wasi")]
+mod component;
+pub mod compiler;
+pub mod dev;
+pub mod scaffold;
 
 #[wasm_bindgen]
 pub fn initialize_rig_wasm(config_json: &str) -> Result<(), JsValue> {
     // Set up the panic hook for better error messages
     utils::wasm_utils::set_panic_hook();
 
-    // Parse the config JSON
-    let config: WasmConfig = serde_json::from_str(config_json)
-        .map_err(|e| JsValue::from_str(&format!("Failed to parse config: {}", e)))?;
+    // Parse the config JSON. A config embedded at build time is the base, and
+    // `config_json` fills in its secrets and overrides the rest.
+    let config: WasmConfig = match config::EMBEDDED_CONFIG {
+        Some(embedded) => WasmConfig::from_embedded(embedded, config_json).map_err(|e| JsValue::from_str(&e))?,
+        None => serde_json::from_str(config_json)
+            .map_err(|e| JsValue::from_str(&format!("Failed to parse config: {}", e)))?,
+    };
 
     // Initialize Rig with WASM-specific configuration
     // This is where we would set up providers, vector stores, etc.
@@ -25,51 +41,31 @@ pub fn initialize_rig_wasm(config_json: &str) -> Result<(), JsValue> {
     Ok(())
 }
 
+// Compiles with the common settings and prints progress like the CLI does;
+// `Compiler` exposes every option
 pub fn compile_to_wasm(
-    input_file: &str,
+    input: &str,
     output_dir: &str,
+    target: Target,
     opt_level: u8,
     generate_typescript: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Check if input file exists
-    let input_path = Path::new(input_file);
-    if !input_path.exists() {
-        return Err(format!("Input file does not exist: {}", input_file).into());
-    }
-
-    // Create output directory if it doesn't exist
-    let output_path = Path::new(output_dir);
-    fs::create_dir_all(output_path)?;
-
-    // Run wasm-pack to compile the input file
-    let mut command = std::process::Command::new("wasm-pack");
-    command
-        .arg("build")
-        .arg("--target").arg("web")
-        .arg("--out-dir").arg(output_dir)
-        .arg("--out-name").arg("rig_wasm")
-        .arg("--").arg(input_file);
-
-    // Set optimization level
-    command.arg("-O").arg(opt_level.to_string());
-
-    if generate_typescript {
-        command.arg("--typescript");
-    }
-
-    let output = command.output()?;
-
-    if !output.status.success() {
-        return Err(format!(
-            "wasm-pack failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        ).into());
-    }
-
-    println!("WASM compilation successful");
-    println!("Output directory: {}", output_dir);
-
-    Ok(())
+    message_format: MessageFormat,
+) -> Result<(), CompileError> {
+    // Optimization level 0 builds with the dev profile, anything else in release
+    let profile = if opt_level == 0 { Profile::Dev } else { Profile::Release };
+
+    let options = CompileOptions::new(input, output_dir)
+        .with_target(target)
+        .with_profile(profile)
+        .with_opt_level(OptLevel::from_level(opt_level))
+        .with_typescript(generate_typescript);
+
+    let result = Compiler::new(options)
+        .with_observer(compiler::progress::ConsoleObserver::new(message_format))
+        .compile();
+    compiler::diagnostics::emit_result(message_format, &result);
+
+    result.map(|_| ())
 }
 
 #[cfg(test)]
@@ -1,39 +1,555 @@
-use clap::Parser;
-use rig_wasm_compiler::compile_to_wasm;
+use clap::{Parser, Subcommand};
+use rig_wasm_compiler::compiler::budget::{parse_size, SizeBudget};
+use rig_wasm_compiler::compiler::cache;
+use rig_wasm_compiler::compiler::diagnostics::{emit_result, emit_result_stderr};
+use rig_wasm_compiler::compiler::inspect::inspect;
+use rig_wasm_compiler::compiler::manifest::{load_manifest, project_dir, Entry, ProjectManifest};
+use rig_wasm_compiler::compiler::package::pack;
+use rig_wasm_compiler::compiler::preset::Preset;
+use rig_wasm_compiler::compiler::progress::ConsoleObserver;
+use rig_wasm_compiler::compiler::signing;
+use rig_wasm_compiler::compiler::toolchain;
+#[cfg(feature = "run")]
+use rig_wasm_compiler::dev::run::{run, RunOptions, Runtime};
+use rig_wasm_compiler::dev::watch::watch;
+use rig_wasm_compiler::scaffold::{init_project, Template};
+use rig_wasm_compiler::{
+    CompileError, CompileOptions, Compiler, LintLevel, MessageFormat, OptLevel, SecretPolicy, Target,
+};
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
+#[clap(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    #[clap(flatten)]
+    build: Args,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Rebuild on every change and serve the output with live reload
+    #[clap(alias = "serve")]
+    Watch {
+        #[clap(flatten)]
+        build: Args,
+
+        /// Address for the dev server
+        #[clap(long, default_value = "127.0.0.1:8080")]
+        address: String,
+    },
+
+    /// Create a new Rig WASM project
+    Init {
+        /// Crate name
+        name: String,
+
+        /// Project template
+        #[clap(long, arg_enum, default_value = "simple")]
+        template: Template,
+
+        /// Directory to create the project in (defaults to ./<name>)
+        #[clap(long, parse(from_os_str))]
+        path: Option<PathBuf>,
+
+        /// Overwrite existing files
+        #[clap(long)]
+        force: bool,
+    },
+
+    /// Write package.json and an npm-compatible tarball for the built package
+    Pack {
+        #[clap(flatten)]
+        build: Args,
+
+        /// Pack the existing output instead of building it first
+        #[clap(long)]
+        no_build: bool,
+
+        /// Directory to write the tarball to
+        #[clap(long, parse(from_os_str), default_value = ".")]
+        pack_destination: PathBuf,
+    },
+
+    /// Call an export of the built module and print what it returns
+    #[cfg(feature = "run")]
+    Run {
+        #[clap(flatten)]
+        build: Args,
+
+        /// Export to call: a function (run_example), a class method (WasmAgent.process),
+        /// or a rig:agent export for WASI targets (process) [default: WasmAgent.process, or process for WASI]
+        #[clap(long)]
+        export: Option<String>,
+
+        /// Arguments passed to the export
+        args: Vec<String>,
+
+        /// Constructor argument for class methods; repeat for several [default: a provider config JSON]
+        #[clap(long = "new-arg", multiple_occurrences = true)]
+        new_args: Vec<String>,
+
+        /// Context document to add before the call; repeat for several
+        #[clap(long, multiple_occurrences = true)]
+        context: Vec<String>,
+
+        /// Provider base URL, e.g. a local mock server (sets RIG_API_BASE_URL)
+        #[clap(long)]
+        base_url: Option<String>,
+
+        /// Where to run the module [default: wasmtime for WASI targets, node otherwise]
+        #[clap(long, arg_enum, default_value = "auto")]
+        runtime: Runtime,
+
+        /// Run the existing output instead of building it first
+        #[clap(long)]
+        no_build: bool,
+    },
+
+    /// List the exports, imports, memory limits and custom sections of a .wasm file
+    Inspect {
+        /// Module to inspect
+        #[clap(parse(from_os_str))]
+        file: PathBuf,
+
+        /// Print the report as a JSON object
+        #[clap(long)]
+        json: bool,
+    },
+
+    /// Check a signed output directory: the manifest signature, then every file it lists
+    Verify {
+        /// Output directory holding manifest.json and manifest.json.sig
+        #[clap(parse(from_os_str), default_value = "pkg")]
+        dir: PathBuf,
+
+        /// Trusted ed25519 public key: a PEM file or a file with 64 hex characters
+        #[clap(long, parse(from_os_str))]
+        public_key: PathBuf,
+
+        /// Print the report as text or as a JSON object
+        #[clap(long, arg_enum, default_value = "human")]
+        message_format: MessageFormat,
+    },
+
+    /// List or clear the build cache
+    Cache {
+        #[clap(subcommand)]
+        command: CacheCommand,
+    },
+
+    /// Check the installed toolchain without touching the network
+    Doctor {
+        /// Project directory whose Cargo.lock pins wasm-bindgen
+        #[clap(long, parse(from_os_str), default_value = ".")]
+        path: PathBuf,
+
+        /// Print the report as text or as a JSON object
+        #[clap(long, arg_enum, default_value = "human")]
+        message_format: MessageFormat,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheCommand {
+    /// List cached builds, newest first
+    Ls {
+        /// Print the listing as text or as a JSON object
+        #[clap(long, arg_enum, default_value = "human")]
+        message_format: MessageFormat,
+    },
+
+    /// Remove every cached build
+    Clean,
+}
+
+#[derive(clap::Args, Debug)]
 struct Args {
-    /// Input Rust file to compile
-    #[clap(short, long, parse(from_os_str))]
-    input: PathBuf,
+    /// Input crate directory, Cargo.toml, workspace member name or .rs file (defaults to the current crate).
+    /// Repeat to build several entries into one package
+    #[clap(short, long, parse(from_os_str), multiple_occurrences = true)]
+    input: Vec<PathBuf>,
 
-    /// Output directory for WASM files
+    /// Output directory for WASM files (defaults to pkg)
     #[clap(short, long, parse(from_os_str))]
-    output: PathBuf,
+    output: Option<PathBuf>,
+
+    /// Base name of the generated .js and .wasm files [default: rig_wasm]. Not allowed with several inputs,
+    /// which are named after their entries
+    #[clap(long)]
+    out_name: Option<String>,
+
+    /// Build settings file (defaults to rig-wasm.toml or [package.metadata.rig-wasm] next to the first input,
+    /// or in the current directory)
+    #[clap(long, parse(from_os_str))]
+    manifest: Option<PathBuf>,
+
+    /// wasm-bindgen output target [default: web]
+    #[clap(long, arg_enum)]
+    target: Option<Target>,
+
+    /// Build preset: cargo profile settings, wasm-opt level and panic hook tuned for size, speed or debugging
+    #[clap(long = "profile", arg_enum)]
+    preset: Option<Preset>,
+
+    /// wasm-opt optimization level (0-4, s or z); 0 also builds with the dev profile [default: 2]
+    #[clap(short = 'O', long)]
+    opt_level: Option<OptLevel>,
+
+    /// Strip the name section and DWARF from the module
+    #[clap(long, overrides_with = "no_strip_names")]
+    strip_names: bool,
+
+    /// Keep the name section, overriding the build settings
+    #[clap(long, overrides_with = "strip_names")]
+    no_strip_names: bool,
+
+    /// Keep DWARF and the name section and write <out-name>.symbols.json (on with --profile debug)
+    #[clap(long, conflicts_with = "strip_names", overrides_with = "no_debug_info")]
+    debug_info: bool,
+
+    /// Do not keep debug info or write a symbol map, overriding the build settings
+    #[clap(long, overrides_with = "debug_info")]
+    no_debug_info: bool,
+
+    /// Strip custom sections such as producers and target_features
+    #[clap(long, overrides_with = "no_strip_custom_sections")]
+    strip_custom_sections: bool,
+
+    /// Keep custom sections, overriding the build settings
+    #[clap(long, overrides_with = "strip_custom_sections")]
+    no_strip_custom_sections: bool,
+
+    /// Fail if the .wasm plus JS glue is larger than this (e.g. 800KiB)
+    #[clap(long, parse(try_from_str = parse_size))]
+    max_size: Option<u64>,
+
+    /// Fail if the gzip-compressed .wasm plus JS glue is larger than this
+    #[clap(long, parse(try_from_str = parse_size))]
+    max_gzip_size: Option<u64>,
 
-    /// Optimization level (0-3)
-    #[clap(short, long, default_value = "2")]
-    opt_level: u8,
+    /// Fail if the brotli-compressed .wasm plus JS glue is larger than this
+    #[clap(long, parse(try_from_str = parse_size))]
+    max_brotli_size: Option<u64>,
+
+    /// TOML file with per-file size budgets; the --max-* flags override its totals
+    #[clap(long, parse(from_os_str))]
+    budget: Option<PathBuf>,
+
+    /// Comma-separated list of cargo features to enable
+    #[clap(long, value_delimiter = ',')]
+    features: Vec<String>,
+
+    /// Do not enable the crate's default features
+    #[clap(long, overrides_with = "default_features")]
+    no_default_features: bool,
+
+    /// Enable the crate's default features, overriding the build settings
+    #[clap(long, overrides_with = "no_default_features")]
+    default_features: bool,
 
     /// Generate TypeScript definitions
-    #[clap(short, long)]
+    #[clap(short, long, overrides_with = "no_typescript")]
     typescript: bool,
+
+    /// Do not generate TypeScript definitions, overriding the build settings
+    #[clap(long, overrides_with = "typescript")]
+    no_typescript: bool,
+
+    /// WasmConfig file (JSON or TOML) to embed into the module
+    #[clap(long, parse(from_os_str))]
+    config: Option<PathBuf>,
+
+    /// What to do with API keys in the --config file: fail the build, or leave placeholders to fill in at runtime [default: reject]
+    #[clap(long, arg_enum)]
+    config_secrets: Option<SecretPolicy>,
+
+    /// How to treat wasm-incompatible APIs and crates found before the build [default: warn]
+    #[clap(long, arg_enum)]
+    lint: Option<LintLevel>,
+
+    /// Build a copy of the sources with tokio, #[tokio::main] and native HTTP clients rewritten for wasm32
+    #[clap(long, overrides_with = "no_shim")]
+    shim: bool,
+
+    /// Build the sources as they are, overriding the build settings
+    #[clap(long, overrides_with = "shim")]
+    no_shim: bool,
+
+    /// Write the shimmed sources to <output>/shimmed-src for review (implies --shim)
+    #[clap(long, overrides_with = "no_emit_shimmed_source")]
+    emit_shimmed_source: bool,
+
+    /// Do not write the shimmed sources, overriding the build settings
+    #[clap(long, overrides_with = "emit_shimmed_source")]
+    no_emit_shimmed_source: bool,
+
+    /// Always rebuild instead of reusing an identical earlier build from the cache
+    #[clap(long, overrides_with = "cache")]
+    no_cache: bool,
+
+    /// Reuse identical earlier builds, overriding the build settings
+    #[clap(long, overrides_with = "no_cache")]
+    cache: bool,
+
+    /// Remap build paths, set file times to $SOURCE_DATE_EPOCH and write manifest.json with SRI hashes
+    #[clap(long, overrides_with = "no_reproducible")]
+    reproducible: bool,
+
+    /// Build without the reproducibility steps, overriding the build settings
+    #[clap(long, overrides_with = "reproducible")]
+    no_reproducible: bool,
+
+    /// Write a Content-Security-Policy allowing the --config providers' origins as _headers and nginx-headers.conf
+    #[clap(long, overrides_with = "no_emit_headers")]
+    emit_headers: bool,
+
+    /// Do not write the Content-Security-Policy files, overriding the build settings
+    #[clap(long, overrides_with = "emit_headers")]
+    no_emit_headers: bool,
+
+    /// Write <out-name>.worker.js and <out-name>.proxy.js to run the module in a Web Worker
+    #[clap(long, overrides_with = "no_emit_worker")]
+    emit_worker: bool,
+
+    /// Do not write the Web Worker files, overriding the build settings
+    #[clap(long, overrides_with = "emit_worker")]
+    no_emit_worker: bool,
+
+    /// Sign manifest.json with this ed25519 key (PKCS#8 PEM or 64 hex characters), writing manifest.json.sig
+    #[clap(long, parse(from_os_str))]
+    sign_key: Option<PathBuf>,
+
+    /// Report compiler messages as rendered text or as JSON records on stdout
+    #[clap(long, arg_enum, default_value = "human")]
+    message_format: MessageFormat,
+}
+
+// A boolean setting and its --no- counterpart. Neither leaves the manifest alone;
+// clap keeps only the last of the two.
+fn flag(on: bool, off: bool) -> Option<bool> {
+    match (on, off) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+impl Args {
+    // The flags as a manifest layer, to be merged over the project's build settings
+    fn overrides(&self) -> Result<ProjectManifest, CompileError> {
+        let budget = match &self.budget {
+            Some(path) => Some(SizeBudget::from_file(path)?),
+            None => None,
+        };
+
+        let inputs: Vec<String> = self.input.iter().map(|input| input.to_string_lossy().into_owned()).collect();
+        let (input, entries) = match inputs.len() {
+            0 | 1 => (inputs.into_iter().next(), Vec::new()),
+            _ => (None, inputs.into_iter().map(Entry::new).collect()),
+        };
+
+        Ok(ProjectManifest {
+            input,
+            entries,
+            out_name: self.out_name.clone(),
+            output: self.output.clone(),
+            targets: self.target.into_iter().collect(),
+            features: self.features.clone(),
+            no_default_features: flag(self.no_default_features, self.default_features),
+            typescript: flag(self.typescript, self.no_typescript),
+            preset: self.preset,
+            opt_level: self.opt_level,
+            strip_names: flag(self.strip_names, self.no_strip_names),
+            debug_info: flag(self.debug_info, self.no_debug_info),
+            strip_custom_sections: flag(self.strip_custom_sections, self.no_strip_custom_sections),
+            budget,
+            config: self.config.clone(),
+            config_secrets: self.config_secrets,
+            lint: self.lint,
+            shim: flag(self.shim, self.no_shim),
+            emit_shimmed_source: flag(self.emit_shimmed_source, self.no_emit_shimmed_source),
+            cache: flag(self.cache, self.no_cache),
+            reproducible: flag(self.reproducible, self.no_reproducible),
+            sign_key: self.sign_key.clone(),
+            emit_headers: flag(self.emit_headers, self.no_emit_headers),
+            emit_worker: flag(self.emit_worker, self.no_emit_worker),
+            ..Default::default()
+        })
+    }
+
+    // The project manifest with the flags merged on top, found from the first input
+    fn manifest(&self) -> Result<ProjectManifest, CompileError> {
+        let input = self.input.first().map(|input| input.to_string_lossy().into_owned());
+        load_manifest(&project_dir(input.as_deref()), self.manifest.as_deref(), self.overrides()?)
+    }
+
+    // One set of options per target and entry
+    fn compile_options(&self) -> Result<Vec<CompileOptions>, CompileError> {
+        let mut options = self.manifest()?.compile_options();
+        for options in &mut options {
+            let total = &mut options.budget.total;
+            total.raw = self.max_size.or(total.raw);
+            total.gzip = self.max_gzip_size.or(total.gzip);
+            total.brotli = self.max_brotli_size.or(total.brotli);
+        }
+        Ok(options)
+    }
+}
+
+// Reports a setup error the same way a failed compilation is reported, then exits
+fn exit_with(format: MessageFormat, error: CompileError) -> ! {
+    let code = error.exit_code();
+    emit_result(format, &Err(error));
+    std::process::exit(code);
 }
 
 fn main() {
-    let args = Args::parse();
-
-    println!("Compiling {} to WASM...", args.input.display());
-
-    match compile_to_wasm(
-        args.input.to_str().unwrap(),
-        args.output.to_str().unwrap(),
-        args.opt_level,
-        args.typescript,
-    ) {
-        Ok(()) => println!("Successfully compiled to WASM"),
-        Err(e) => eprintln!("Compilation failed: {}", e),
+    let cli = Cli::parse();
+
+    match cli.command {
+        Some(Command::Watch { build, address }) => {
+            let format = build.message_format;
+            // The dev server serves a single output, so only the first target is watched
+            let result = build
+                .compile_options()
+                .and_then(|mut options| watch(options.remove(0), &address, format));
+            if let Err(e) = result {
+                exit_with(format, e);
+            }
+        }
+        Some(Command::Init { name, template, path, force }) => {
+            let dir = path.unwrap_or_else(|| PathBuf::from(&name));
+            match init_project(&dir, &name, template, force) {
+                Ok(files) => {
+                    for file in files {
+                        println!("  created {}", file.display());
+                    }
+                    println!("Created {} project {} in {}", template, name, dir.display());
+                }
+                Err(e) => exit_with(MessageFormat::Human, e),
+            }
+        }
+        Some(Command::Pack { build, no_build, pack_destination }) => {
+            let format = build.message_format;
+            let result = build.manifest().and_then(|manifest| {
+                let options = build.compile_options()?;
+                if !no_build {
+                    compile_all(&options, format, false);
+                }
+                pack(&options, manifest.npm.unwrap_or_default(), &pack_destination)
+            });
+            match result {
+                Ok(report) => match format {
+                    MessageFormat::Human => println!("{}", report),
+                    MessageFormat::Json => println!("{}", serde_json::to_string(&report).unwrap()),
+                },
+                Err(e) => exit_with(format, e),
+            }
+        }
+        #[cfg(feature = "run")]
+        Some(Command::Run { build, export, args, new_args, context, base_url, runtime, no_build }) => {
+            let format = build.message_format;
+            // Only the first target and entry is run
+            let result = build.compile_options().and_then(|mut options| {
+                options.truncate(1);
+                // stdout is for what the export returns
+                if !no_build {
+                    compile_all(&options, format, true);
+                }
+                let mut run_options = RunOptions::new(&options[0], export);
+                run_options.runtime = runtime;
+                run_options.args = args;
+                run_options.new_args = new_args;
+                run_options.context = context;
+                run_options.base_url = base_url;
+                run(&run_options)
+            });
+            if let Err(e) = result {
+                exit_with(format, e);
+            }
+        }
+        Some(Command::Inspect { file, json }) => {
+            let format = if json { MessageFormat::Json } else { MessageFormat::Human };
+            match inspect(&file) {
+                Ok(report) if json => println!("{}", serde_json::to_string(&report).unwrap()),
+                Ok(report) => println!("{}", report),
+                Err(e) => exit_with(format, e),
+            }
+        }
+        Some(Command::Verify { dir, public_key, message_format }) => {
+            let result = signing::read_verifying_key(&public_key).and_then(|key| signing::verify(&dir, &key));
+            match result {
+                Ok(report) => match message_format {
+                    MessageFormat::Human => println!("{}", report),
+                    MessageFormat::Json => println!("{}", serde_json::to_string(&report).unwrap()),
+                },
+                Err(e) => exit_with(message_format, e),
+            }
+        }
+        Some(Command::Cache { command }) => {
+            let dir = cache::cache_dir();
+            match command {
+                CacheCommand::Ls { message_format } => match cache::list(&dir) {
+                    Ok(listing) => match message_format {
+                        MessageFormat::Human => println!("{}", listing),
+                        MessageFormat::Json => println!("{}", serde_json::to_string(&listing).unwrap()),
+                    },
+                    Err(e) => exit_with(message_format, e),
+                },
+                CacheCommand::Clean => match cache::clean(&dir) {
+                    Ok(report) => println!("{}", report),
+                    Err(e) => exit_with(MessageFormat::Human, e),
+                },
+            }
+        }
+        Some(Command::Doctor { path, message_format }) => {
+            let report = toolchain::detect(&path);
+            match message_format {
+                MessageFormat::Human => print!("Toolchain:\n{}", report),
+                MessageFormat::Json => println!("{}", serde_json::to_string(&report).unwrap()),
+            }
+            if let Some(e) = report.missing_required() {
+                std::process::exit(e.exit_code());
+            }
+        }
+        None => build(cli.build),
     }
-}
\ No newline at end of file
+}
+
+fn build(args: Args) {
+    let format = args.message_format;
+    let options = args.compile_options().unwrap_or_else(|e| exit_with(format, e));
+    compile_all(&options, format, false);
+}
+
+// Builds every target and entry. Exits on the first failure. With `to_stderr`
+// the banner and report go to stderr, leaving stdout to the caller.
+fn compile_all(options: &[CompileOptions], format: MessageFormat, to_stderr: bool) {
+    for options in options {
+        if format == MessageFormat::Human {
+            let banner = format!("Compiling {} to WASM ({})...", options.input, options.target);
+            if to_stderr {
+                eprintln!("{}", banner);
+            } else {
+                println!("{}", banner);
+            }
+        }
+
+        let result = Compiler::new(options.clone())
+            .with_observer(ConsoleObserver::new(format).with_stderr(to_stderr))
+            .compile();
+
+        if to_stderr {
+            emit_result_stderr(format, &result);
+        } else {
+            emit_result(format, &result);
+        }
+        if let Err(e) = result {
+            std::process::exit(e.exit_code());
+        }
+    }
+}
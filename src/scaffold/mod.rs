@@ -0,0 +1,179 @@
+use crate::compiler::error::CompileError;
+use crate::compiler::input::ENTRY_DEPENDENCIES;
+use clap::ArgEnum;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Starting points for `rig-wasm-compiler init`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum Template {
+    // A single agent with process/addContext/clearContext
+    Simple,
+    // An agent that retrieves context from an in-memory vector store
+    Rag,
+    // The simple agent running in a Web Worker, driven over postMessage
+    Worker,
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Template::Simple => "simple",
+            Template::Rag => "rag",
+            Template::Worker => "worker",
+        })
+    }
+}
+
+const CARGO_TOML: &str = include_str!("templates/common/Cargo.toml");
+const GITIGNORE: &str = include_str!("templates/common/gitignore");
+const README: &str = include_str!("templates/common/README.md");
+// The crate's own browser adapter, so new projects get every HttpClient method
+const HTTP_CLIENT: &str = include_str!("../adapters/http_client.rs");
+const INDEX_HTML: &str = include_str!("templates/common/index.html");
+const SIMPLE_LIB: &str = include_str!("templates/simple/lib.rs");
+const SIMPLE_INDEX_JS: &str = include_str!("templates/simple/index.js");
+const SIMPLE_TEST: &str = include_str!("templates/simple/web.rs");
+const RAG_LIB: &str = include_str!("templates/rag/lib.rs");
+const RAG_INDEX_JS: &str = include_str!("templates/rag/index.js");
+const RAG_TEST: &str = include_str!("templates/rag/web.rs");
+const WORKER_JS: &str = include_str!("templates/worker/worker.js");
+const WORKER_INDEX_JS: &str = include_str!("templates/worker/index.js");
+
+impl Template {
+    // Relative path and contents of every file the template writes
+    fn files(&self) -> Vec<(&'static str, &'static str)> {
+        let mut files = vec![
+            ("Cargo.toml", CARGO_TOML),
+            (".gitignore", GITIGNORE),
+            ("README.md", README),
+            ("src/http_client.rs", HTTP_CLIENT),
+            ("www/index.html", INDEX_HTML),
+        ];
+
+        match self {
+            Template::Simple => files.extend([
+                ("src/lib.rs", SIMPLE_LIB),
+                ("www/index.js", SIMPLE_INDEX_JS),
+                ("tests/web.rs", SIMPLE_TEST),
+            ]),
+            Template::Rag => files.extend([
+                ("src/lib.rs", RAG_LIB),
+                ("www/index.js", RAG_INDEX_JS),
+                ("tests/web.rs", RAG_TEST),
+            ]),
+            Template::Worker => files.extend([
+                ("src/lib.rs", SIMPLE_LIB),
+                ("www/index.js", WORKER_INDEX_JS),
+                ("www/worker.js", WORKER_JS),
+                ("tests/web.rs", SIMPLE_TEST),
+            ]),
+        }
+
+        files
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), CompileError> {
+    let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(CompileError::InvalidInput(format!(
+            "{} is not a valid crate name; use letters, digits, '-' and '_', starting with a letter",
+            name
+        )));
+    }
+    Ok(())
+}
+
+// The adapter without its browser tests, importing rig-core by its library name
+fn http_client() -> String {
+    let source = HTTP_CLIENT.split("#[cfg(test)]").next().unwrap_or_default();
+    source.replace("rig_core::", "rig::")
+}
+
+fn render(path: &str, template: &str, name: &str) -> String {
+    let template = if path == "src/http_client.rs" { http_client() } else { template.to_string() };
+    template
+        .replace("{{name}}", name)
+        .replace("{{crate_name}}", &name.replace('-', "_"))
+        .replace("{{dependencies}}", ENTRY_DEPENDENCIES)
+}
+
+// Writes a new project for `template` into `dir`. Existing files are only
+// overwritten when `force` is set. Returns the files written.
+pub fn init_project(dir: &Path, name: &str, template: Template, force: bool) -> Result<Vec<PathBuf>, CompileError> {
+    validate_name(name)?;
+
+    let files = template.files();
+    if !force {
+        let existing: Vec<String> = files
+            .iter()
+            .map(|(path, _)| dir.join(path))
+            .filter(|path| path.exists())
+            .map(|path| path.display().to_string())
+            .collect();
+        if !existing.is_empty() {
+            return Err(CompileError::InvalidInput(format!(
+                "Refusing to overwrite existing files (pass --force to replace them): {}",
+                existing.join(", ")
+            )));
+        }
+    }
+
+    let mut written = Vec::new();
+    for (relative, contents) in files {
+        let path = dir.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, render(relative, contents, name))?;
+        written.push(path);
+    }
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_project() {
+        let dir = std::env::temp_dir().join("rig-wasm-compiler-init-test");
+        let _ = fs::remove_dir_all(&dir);
+
+        let written = init_project(&dir, "my-agent", Template::Rag, false).unwrap();
+        assert!(written.contains(&dir.join("src/lib.rs")));
+
+        let manifest = fs::read_to_string(dir.join("Cargo.toml")).unwrap();
+        assert!(manifest.contains("name = \"my-agent\""));
+        assert!(manifest.contains("rig-core"));
+        assert!(manifest.contains("crate-type = [\"cdylib\", \"rlib\"]"));
+
+        let test = fs::read_to_string(dir.join("tests/web.rs")).unwrap();
+        assert!(test.contains("use my_agent::RagAgent;"));
+        let client = fs::read_to_string(dir.join("src/http_client.rs")).unwrap();
+        assert!(client.contains("async fn post_json") && client.contains("async fn post_form"));
+        assert!(client.contains("use rig::http_client::HttpClient;"));
+        assert!(!client.contains("wasm_bindgen_test"));
+        for path in &written {
+            assert!(!fs::read_to_string(path).unwrap().contains("{{"), "{} was not rendered", path.display());
+        }
+
+        let err = init_project(&dir, "my-agent", Template::Simple, false).unwrap_err();
+        assert_eq!(err.kind(), "invalid-input");
+        assert!(init_project(&dir, "my-agent", Template::Worker, true).is_ok());
+        assert!(dir.join("www/worker.js").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("rag_agent").is_ok());
+        assert!(validate_name("1agent").is_err());
+        assert!(validate_name("my agent").is_err());
+    }
+}
@@ -0,0 +1,25 @@
+import init, { RagAgent } from "./rig_wasm.js";
+
+await init();
+
+const DOCUMENTS = [
+    "The capital of France is Paris.",
+    "The Eiffel Tower is located in Paris.",
+    "Paris is known as the City of Light.",
+];
+
+const output = document.getElementById("output");
+let agent;
+
+document.getElementById("send").addEventListener("click", async () => {
+    if (!agent) {
+        agent = new RagAgent(document.getElementById("api-key").value, "gpt-3.5-turbo");
+        DOCUMENTS.forEach((doc) => agent.addDocument(doc));
+    }
+    output.textContent = "Thinking...";
+    try {
+        output.textContent = await agent.process(document.getElementById("input").value);
+    } catch (e) {
+        output.textContent = `Error: ${e}`;
+    }
+});
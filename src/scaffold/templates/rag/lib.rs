@@ -0,0 +1,81 @@
+mod http_client;
+
+use http_client::WasmHttpClient;
+use rig::agent::Agent;
+use rig::providers::openai::OpenAIProvider;
+use wasm_bindgen::prelude::*;
+
+const DIMENSIONS: usize = 256;
+
+// Hashes words into a fixed-size vector. Swap in a real embedding model once
+// the pipeline works end to end.
+fn embed(text: &str) -> Vec<f32> {
+    let mut vector = vec![0.0; DIMENSIONS];
+    for word in text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()) {
+        let hash = word
+            .to_lowercase()
+            .bytes()
+            .fold(2166136261u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(16777619));
+        vector[hash as usize % DIMENSIONS] += 1.0;
+    }
+    vector
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+#[wasm_bindgen]
+pub struct RagAgent {
+    agent: Agent<OpenAIProvider<WasmHttpClient>>,
+    documents: Vec<(String, Vec<f32>)>,
+}
+
+#[wasm_bindgen]
+impl RagAgent {
+    #[wasm_bindgen(constructor)]
+    pub fn new(api_key: &str, model: &str) -> RagAgent {
+        let provider = OpenAIProvider::new(WasmHttpClient, api_key.to_string(), model.to_string());
+        RagAgent {
+            agent: Agent::new(provider),
+            documents: Vec::new(),
+        }
+    }
+
+    #[wasm_bindgen(js_name = addDocument)]
+    pub fn add_document(&mut self, content: &str) {
+        self.documents.push((content.to_string(), embed(content)));
+    }
+
+    // The `k` documents most similar to `query`
+    pub fn search(&self, query: &str, k: usize) -> Vec<JsValue> {
+        let query = embed(query);
+        let mut scored: Vec<(f32, &String)> = self.documents
+            .iter()
+            .map(|(content, vector)| (cosine_similarity(&query, vector), content))
+            .collect();
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        scored.into_iter().take(k).map(|(_, content)| JsValue::from_str(content)).collect()
+    }
+
+    pub async fn process(&mut self, input: &str) -> Result<String, JsValue> {
+        let context: Vec<String> = self.search(input, 3)
+            .into_iter()
+            .filter_map(|document| document.as_string())
+            .collect();
+
+        self.agent.clear_context();
+        self.agent.add_context(&format!("Relevant information:\n{}", context.join("\n\n")));
+
+        self.agent.process(input)
+            .await
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
@@ -0,0 +1,15 @@
+use wasm_bindgen_test::*;
+use {{crate_name}}::RagAgent;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+fn test_search_ranks_relevant_documents_first() {
+    let mut agent = RagAgent::new("test-key", "gpt-3.5-turbo");
+    agent.add_document("The Eiffel Tower is located in Paris.");
+    agent.add_document("Rust compiles to WebAssembly.");
+
+    let results = agent.search("Where is the Eiffel Tower?", 1);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].as_string().unwrap(), "The Eiffel Tower is located in Paris.");
+}
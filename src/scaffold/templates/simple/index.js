@@ -0,0 +1,17 @@
+import init, { WasmAgent } from "./rig_wasm.js";
+
+await init();
+
+const output = document.getElementById("output");
+let agent;
+
+document.getElementById("send").addEventListener("click", async () => {
+    const apiKey = document.getElementById("api-key").value;
+    agent ??= new WasmAgent(apiKey, "gpt-3.5-turbo");
+    output.textContent = "Thinking...";
+    try {
+        output.textContent = await agent.process(document.getElementById("input").value);
+    } catch (e) {
+        output.textContent = `Error: ${e}`;
+    }
+});
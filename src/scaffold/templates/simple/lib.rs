@@ -0,0 +1,36 @@
+mod http_client;
+
+use http_client::WasmHttpClient;
+use rig::agent::Agent;
+use rig::providers::openai::OpenAIProvider;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct WasmAgent {
+    agent: Agent<OpenAIProvider<WasmHttpClient>>,
+}
+
+#[wasm_bindgen]
+impl WasmAgent {
+    #[wasm_bindgen(constructor)]
+    pub fn new(api_key: &str, model: &str) -> WasmAgent {
+        let provider = OpenAIProvider::new(WasmHttpClient, api_key.to_string(), model.to_string());
+        WasmAgent { agent: Agent::new(provider) }
+    }
+
+    pub async fn process(&self, input: &str) -> Result<String, JsValue> {
+        self.agent.process(input)
+            .await
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    #[wasm_bindgen(js_name = addContext)]
+    pub fn add_context(&mut self, context: &str) {
+        self.agent.add_context(context);
+    }
+
+    #[wasm_bindgen(js_name = clearContext)]
+    pub fn clear_context(&mut self) {
+        self.agent.clear_context();
+    }
+}
@@ -0,0 +1,11 @@
+use wasm_bindgen_test::*;
+use {{crate_name}}::WasmAgent;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+fn test_context_can_be_changed() {
+    let mut agent = WasmAgent::new("test-key", "gpt-3.5-turbo");
+    agent.add_context("You are a helpful assistant.");
+    agent.clear_context();
+}
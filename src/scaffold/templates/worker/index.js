@@ -0,0 +1,33 @@
+const worker = new Worker(new URL("./worker.js", import.meta.url), { type: "module" });
+const pending = new Map();
+let nextId = 0;
+
+worker.onmessage = ({ data: { id, result, error } }) => {
+    const { resolve, reject } = pending.get(id);
+    pending.delete(id);
+    error === undefined ? resolve(result) : reject(error);
+};
+
+function call(method, ...args) {
+    return new Promise((resolve, reject) => {
+        const id = nextId++;
+        pending.set(id, { resolve, reject });
+        worker.postMessage({ id, method, args });
+    });
+}
+
+const output = document.getElementById("output");
+let created = false;
+
+document.getElementById("send").addEventListener("click", async () => {
+    if (!created) {
+        await call("create", document.getElementById("api-key").value, "gpt-3.5-turbo");
+        created = true;
+    }
+    output.textContent = "Thinking...";
+    try {
+        output.textContent = await call("process", document.getElementById("input").value);
+    } catch (e) {
+        output.textContent = `Error: ${e}`;
+    }
+});
@@ -0,0 +1,31 @@
+// Runs the agent off the main thread so long requests don't block the page
+import init, { WasmAgent } from "./rig_wasm.js";
+
+const ready = init();
+let agent;
+
+self.onmessage = async ({ data: { id, method, args } }) => {
+    await ready;
+    try {
+        let result;
+        switch (method) {
+            case "create":
+                agent = new WasmAgent(...args);
+                break;
+            case "process":
+                result = await agent.process(...args);
+                break;
+            case "addContext":
+                agent.addContext(...args);
+                break;
+            case "clearContext":
+                agent.clearContext();
+                break;
+            default:
+                throw new Error(`Unknown method ${method}`);
+        }
+        self.postMessage({ id, result });
+    } catch (e) {
+        self.postMessage({ id, error: String(e) });
+    }
+};
@@ -0,0 +1,181 @@
+// The hook is only installed with the console_error_panic_hook feature
+#![cfg_attr(not(feature = "console_error_panic_hook"), allow(dead_code, unused_imports))]
+
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::panic;
+use std::sync::Once;
+use wasm_bindgen::prelude::*;
+
+// Frames of the panic machinery itself, left out of backtraces
+const PANIC_FRAMES: &[&str] = &[
+    "std::panicking",
+    "std::panic",
+    "core::panicking",
+    "core::panic",
+    "rust_begin_unwind",
+    "__rust_start_panic",
+    "rust_panic",
+    "console_error_panic_hook",
+    "rig_wasm_compiler::utils::backtrace",
+];
+
+thread_local! {
+    // Function names by index, from the symbol map a debug build writes
+    static SYMBOLS: RefCell<HashMap<u32, String>> = RefCell::new(HashMap::new());
+}
+
+#[wasm_bindgen]
+extern "C" {
+    type Error;
+
+    #[wasm_bindgen(constructor)]
+    fn new() -> Error;
+
+    #[wasm_bindgen(structural, method, getter)]
+    fn stack(error: &Error) -> String;
+}
+
+#[derive(Deserialize)]
+struct SymbolMap {
+    functions: Vec<FunctionSymbol>,
+}
+
+#[derive(Deserialize)]
+struct FunctionSymbol {
+    index: u32,
+    name: String,
+}
+
+// Loads the `<out-name>.symbols.json` of a debug build, so backtraces show
+// function names even where the browser does not read the name section
+#[wasm_bindgen(js_name = setSymbolMap)]
+pub fn set_symbol_map(json: &str) -> Result<(), JsValue> {
+    let map: SymbolMap =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&format!("Invalid symbol map: {}", e)))?;
+    SYMBOLS.with(|symbols| {
+        *symbols.borrow_mut() = map.functions.into_iter().map(|f| (f.index, f.name)).collect();
+    });
+    Ok(())
+}
+
+// Drops the `::h0123456789abcdef` hash legacy-mangled Rust names end with
+fn strip_hash(name: &str) -> &str {
+    match name.rfind("::h") {
+        Some(pos) if name.len() - pos == 19 && name[pos + 3..].chars().all(|c| c.is_ascii_hexdigit()) => &name[..pos],
+        _ => name,
+    }
+}
+
+// The function name the browser printed for a frame: Chrome writes
+// `at module.wasm.name (url:wasm-function[1]:0x2a)`, Firefox `name@url:wasm-function[1]:0x2a`
+fn frame_name(line: &str) -> Option<&str> {
+    let name = if let Some(rest) = line.strip_prefix("at ") {
+        rest.split(" (").next()?
+    } else {
+        line.split('@').next()?
+    };
+    let name = match name.find(".wasm.") {
+        Some(pos) => &name[pos + ".wasm.".len()..],
+        None => name,
+    };
+    if name.is_empty() || name.contains("wasm-function[") || name.starts_with("wasm://") {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+// Turns a JS stack trace into Rust frames: wasm frames only, named from
+// `symbols` or the browser's own output, without the panic machinery
+pub(crate) fn symbolize(stack: &str, symbols: &HashMap<u32, String>) -> Vec<String> {
+    let mut frames = Vec::new();
+    for line in stack.lines().map(str::trim) {
+        let start = match line.find("wasm-function[") {
+            Some(pos) => pos + "wasm-function[".len(),
+            None => continue,
+        };
+        let end = match line[start..].find(']') {
+            Some(len) => start + len,
+            None => continue,
+        };
+        let index: u32 = match line[start..end].parse() {
+            Ok(index) => index,
+            Err(_) => continue,
+        };
+        let offset = line[end + 1..].trim_start_matches(':').trim_end_matches(')');
+
+        let name = symbols
+            .get(&index)
+            .map(String::as_str)
+            .or_else(|| frame_name(line))
+            .map(strip_hash)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("wasm-function[{}]", index));
+        if PANIC_FRAMES.iter().any(|frame| name.starts_with(frame)) {
+            continue;
+        }
+        frames.push(if offset.is_empty() { name } else { format!("{} at {}", name, offset) });
+    }
+    frames
+}
+
+fn report(message: String) {
+    let stack = Error::new().stack();
+    let frames = SYMBOLS.with(|symbols| symbolize(&stack, &symbols.borrow()));
+
+    let mut report = message;
+    if !frames.is_empty() {
+        report.push_str("\n\nRust backtrace:");
+        for (i, frame) in frames.iter().enumerate() {
+            report.push_str(&format!("\n  {:>3}: {}", i, frame));
+        }
+    }
+    web_sys::console::error_1(&JsValue::from_str(&report));
+}
+
+// Installs a panic hook that logs the panic message and a symbolized backtrace
+// with console.error. Until a symbol map is loaded, console_error_panic_hook
+// reports the panic with the browser's own stack trace.
+#[cfg(feature = "console_error_panic_hook")]
+pub fn install() {
+    static SET: Once = Once::new();
+    SET.call_once(|| {
+        panic::set_hook(Box::new(|info| {
+            if SYMBOLS.with(|symbols| symbols.borrow().is_empty()) {
+                console_error_panic_hook::hook(info);
+            } else {
+                report(info.to_string());
+            }
+        }))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbolize() {
+        let stack = "Error\n\
+            at imports.wbg.__wbg_new_abd6 (http://localhost/rig_wasm.js:310:21)\n\
+            at rig_wasm_bg.wasm.std::panicking::rust_panic_with_hook::h0123456789abcdef (http://localhost/rig_wasm_bg.wasm:wasm-function[80]:0x1a2b)\n\
+            at rig_wasm_bg.wasm.rig_wasm::agent::process::h0123456789abcdef (http://localhost/rig_wasm_bg.wasm:wasm-function[42]:0x3c4d)\n\
+            at http://localhost/rig_wasm_bg.wasm:wasm-function[7]:0x99\n\
+            at wasm://wasm/0012abcd:wasm-function[9]:0x10\n\
+            rig_wasm::lib::run@http://localhost/rig_wasm_bg.wasm:wasm-function[5]:0x20";
+        let mut symbols = HashMap::new();
+        symbols.insert(7, "rig_wasm::WasmAgent::process".to_string());
+
+        assert_eq!(
+            symbolize(stack, &symbols),
+            vec![
+                "rig_wasm::agent::process at 0x3c4d",
+                "rig_wasm::WasmAgent::process at 0x99",
+                "wasm-function[9] at 0x10",
+                "rig_wasm::lib::run at 0x20",
+            ]
+        );
+    }
+}
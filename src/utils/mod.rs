@@ -1 +1,3 @@
+pub mod backtrace;
+pub mod verify;
 pub mod wasm_utils;
@@ -0,0 +1,55 @@
+use crate::compiler::integrity::MANIFEST_FILE;
+use crate::compiler::signing::{self, SIGNATURE_FILE};
+use gloo_net::http::Request;
+use wasm_bindgen::prelude::*;
+
+// Checks a downloaded file against a signed manifest before it is used:
+// the manifest.json.sig signature over `manifest` with the trusted
+// `public_key` (hex or PEM), then the size and SHA-384 hash of `bytes`
+// against the entry for `path`
+#[wasm_bindgen(js_name = verifyModule)]
+pub fn verify_module(
+    manifest: &str,
+    signature: &str,
+    public_key: &str,
+    path: &str,
+    bytes: &[u8],
+) -> Result<(), JsValue> {
+    let key = signing::parse_verifying_key(public_key).map_err(|e| JsValue::from_str(&e))?;
+    let manifest = signing::verify_manifest(manifest.as_bytes(), signature, &key).map_err(|e| JsValue::from_str(&e))?;
+    signing::check_file(&manifest, path, bytes).map_err(|e| JsValue::from_str(&e))
+}
+
+async fn fetch(url: &str) -> Result<Vec<u8>, JsValue> {
+    let response = Request::get(url)
+        .send()
+        .await
+        .map_err(|e| JsValue::from_str(&format!("Failed to fetch {}: {}", url, e)))?;
+    if !response.ok() {
+        return Err(JsValue::from_str(&format!("Failed to fetch {}: HTTP {}", url, response.status())));
+    }
+    response
+        .binary()
+        .await
+        .map_err(|e| JsValue::from_str(&format!("Failed to read {}: {}", url, e)))
+}
+
+// Downloads `path` from a signed output directory at `base_url` together with
+// its manifest and signature, and returns its bytes only if they verify.
+// Pass them to `init()` or `WebAssembly.instantiate`.
+#[wasm_bindgen(js_name = fetchVerified)]
+pub async fn fetch_verified(base_url: &str, path: &str, public_key: &str) -> Result<js_sys::Uint8Array, JsValue> {
+    let base_url = base_url.trim_end_matches('/');
+    let manifest = fetch(&format!("{}/{}", base_url, MANIFEST_FILE)).await?;
+    let signature = fetch(&format!("{}/{}", base_url, SIGNATURE_FILE)).await?;
+    let bytes = fetch(&format!("{}/{}", base_url, path)).await?;
+
+    verify_module(
+        &String::from_utf8_lossy(&manifest),
+        &String::from_utf8_lossy(&signature),
+        public_key,
+        path,
+        &bytes,
+    )?;
+    Ok(js_sys::Uint8Array::from(bytes.as_slice()))
+}
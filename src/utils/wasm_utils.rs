@@ -1,10 +1,11 @@
 use wasm_bindgen::prelude::*;
 use web_sys::console;
 
-// Set up a panic hook for better error messages in WASM
+// Set up a panic hook for better error messages in WASM: the panic message
+// followed by a Rust backtrace, symbolized with the map from setSymbolMap
 pub fn set_panic_hook() {
     #[cfg(feature = "console_error_panic_hook")]
-    console_error_panic_hook::set_once();
+    crate::utils::backtrace::install();
 }
 
 // Log a message to the browser console
@@ -1,7 +1,7 @@
 use wasm_bindgen::prelude::*;
 use rig::agent::Agent;
 use rig::providers::Provider;
-use crate::adapters::http_client::WasmHttpClient;
+use crate::adapters::DefaultHttpClient;
 use crate::config::ProviderConfig;
 
 #[wasm_bindgen]
@@ -16,7 +16,7 @@ impl WasmAgent {
         let config: ProviderConfig = serde_json::from_str(config_json)
             .map_err(|e| JsValue::from_str(&format!("Failed to parse config: {}", e)))?;
 
-        let http_client = WasmHttpClient::new();
+        let http_client = DefaultHttpClient::new();
         let provider = create_provider(config, http_client)
             .map_err(|e| JsValue::from_str(&format!("Failed to create provider: {}", e)))?;
 
@@ -42,7 +42,7 @@ impl WasmAgent {
     }
 }
 
-fn create_provider(config: ProviderConfig, http_client: WasmHttpClient) -> Result<Box<dyn Provider>, Box<dyn std::error::Error>> {
+pub(crate) fn create_provider(config: ProviderConfig, http_client: DefaultHttpClient) -> Result<Box<dyn Provider>, Box<dyn std::error::Error>> {
     // This function would create the appropriate provider based on the configuration
     // For now, we'll just create a dummy provider
     
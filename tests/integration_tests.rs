@@ -40,4 +40,161 @@ fn test_compile_rag_agent() {
 
     // Clean up
     fs::remove_dir_all(output_dir).unwrap();
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_compile_each_target() {
+    let targets: &[(&str, &[&str])] = &[
+        ("web", &["rig_wasm.js", "rig_wasm_bg.wasm"]),
+        ("bundler", &["rig_wasm.js", "rig_wasm_bg.js", "rig_wasm_bg.wasm"]),
+        ("nodejs", &["rig_wasm.js", "rig_wasm_bg.wasm"]),
+        ("no-modules", &["rig_wasm.js", "rig_wasm_bg.wasm"]),
+        ("deno", &["rig_wasm.js", "rig_wasm_bg.wasm"]),
+    ];
+
+    for (target, files) in targets {
+        let output_dir = format!("test_output/target_{}", target);
+        let result = Command::new("cargo")
+            .args(&["run", "--",
+                    "--input", "examples/simple_agent.rs",
+                    "--output", &output_dir,
+                    "--target", target])
+            .output()
+            .expect("Failed to execute rig-wasm-compiler");
+
+        assert!(result.status.success(), "Compilation for {} failed: {:?}", target, String::from_utf8_lossy(&result.stderr));
+        for file in *files {
+            assert!(Path::new(&format!("{}/{}", output_dir, file)).exists(), "{} output is missing {}", target, file);
+        }
+
+        // Only the bundler target splits the bindings into a separate _bg.js
+        if *target != "bundler" {
+            assert!(!Path::new(&format!("{}/rig_wasm_bg.js", output_dir)).exists());
+        }
+
+        // Clean up
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+}
+
+#[test]
+fn test_missing_input_exit_code() {
+    let result = Command::new("cargo")
+        .args(&["run", "--",
+                "--input", "examples/does_not_exist.rs",
+                "--output", "test_output/missing",
+                "--message-format", "json"])
+        .output()
+        .expect("Failed to execute rig-wasm-compiler");
+
+    assert_eq!(result.status.code(), Some(2));
+
+    let stdout = String::from_utf8_lossy(&result.stdout);
+    let record: serde_json::Value = serde_json::from_str(stdout.lines().last().unwrap()).unwrap();
+    assert_eq!(record["reason"], "compile-error");
+    assert_eq!(record["kind"], "input-not-found");
+    assert_eq!(record["exit_code"], 2);
+}
+
+#[test]
+fn test_scaffolded_project_checks() {
+    let project_dir = "test_output/scaffold_check";
+    let _ = fs::remove_dir_all(project_dir);
+    let result = Command::new("cargo")
+        .args(&["run", "--",
+                "init", "scaffold-check",
+                "--template", "simple",
+                "--path", project_dir])
+        .output()
+        .expect("Failed to execute rig-wasm-compiler");
+
+    assert!(result.status.success(), "Init failed: {:?}", String::from_utf8_lossy(&result.stderr));
+
+    let manifest = format!("{}/Cargo.toml", project_dir);
+    let result = Command::new("cargo")
+        .args(&["check",
+                "--manifest-path", &manifest,
+                "--target", "wasm32-unknown-unknown"])
+        .output()
+        .expect("Failed to execute cargo check");
+
+    assert!(result.status.success(), "Scaffolded project does not compile: {:?}", String::from_utf8_lossy(&result.stderr));
+
+    // Clean up
+    fs::remove_dir_all(project_dir).unwrap();
+}
+
+#[test]
+fn test_config_is_embedded() {
+    let output_dir = "test_output/embedded_config";
+    fs::create_dir_all("test_output").unwrap();
+    let config = "test_output/embedded_config.json";
+    fs::write(config, r#"{
+        "providers": {
+            "openai": { "api_key": "", "model": "rig-embedded-config-test", "api_base_url": null, "additional_params": null }
+        },
+        "max_tokens": null, "temperature": null, "request_timeout": null, "vector_store": null
+    }"#).unwrap();
+
+    let result = Command::new("cargo")
+        .args(&["run", "--",
+                "--input", "examples/simple_agent.rs",
+                "--output", output_dir,
+                "--config", config])
+        .output()
+        .expect("Failed to execute rig-wasm-compiler");
+
+    assert!(result.status.success(), "Compilation failed: {:?}", String::from_utf8_lossy(&result.stderr));
+
+    // The config ends up in the module users ship, not only in this crate's build
+    let wasm = fs::read(format!("{}/rig_wasm_bg.wasm", output_dir)).unwrap();
+    let needle = br#""model":"rig-embedded-config-test""#;
+    assert!(wasm.windows(needle.len()).any(|window| window == needle));
+    let glue = fs::read_to_string(format!("{}/rig_wasm.js", output_dir)).unwrap();
+    assert!(glue.contains("rigWasmConfig"));
+
+    // Clean up
+    fs::remove_dir_all(output_dir).unwrap();
+    fs::remove_file(config).unwrap();
+}
+
+#[cfg(feature = "run")]
+#[test]
+fn test_run_json_keeps_stdout_for_the_result() {
+    if Command::new("node").arg("--version").output().is_err() {
+        eprintln!("skipping: node is not installed");
+        return;
+    }
+
+    let output_dir = "test_output/run_stdout";
+    fs::create_dir_all("test_output").unwrap();
+    let input = "test_output/run_stdout.rs";
+    fs::write(input, r#"
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub fn greet() -> String {
+    "hello from wasm".to_string()
+}
+"#).unwrap();
+
+    let result = Command::new("cargo")
+        .args(&["run", "--features", "run", "--",
+                "run",
+                "--input", input,
+                "--output", output_dir,
+                "--target", "nodejs",
+                "--message-format", "json",
+                "--export", "greet"])
+        .output()
+        .expect("Failed to execute rig-wasm-compiler");
+
+    assert!(result.status.success(), "Run failed: {:?}", String::from_utf8_lossy(&result.stderr));
+    // Phase, warning, diagnostic and report records all go to stderr
+    assert_eq!(String::from_utf8_lossy(&result.stdout).trim(), "hello from wasm");
+    assert!(String::from_utf8_lossy(&result.stderr).contains("\"reason\":\"compile-finished\""));
+
+    // Clean up
+    fs::remove_dir_all(output_dir).unwrap();
+    fs::remove_file(input).unwrap();
+}
@@ -0,0 +1,118 @@
+// Builds this crate as a wasm32-wasip2 component and calls it from an
+// embedded wasmtime host, the way a server-side sandbox would
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use wasmtime::component::{Component, Linker, ResourceTable};
+use wasmtime::{Config, Engine, Store};
+use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiView};
+use wasmtime_wasi_http::{WasiHttpCtx, WasiHttpView};
+
+wasmtime::component::bindgen!({
+    world: "rig-agent",
+    path: "wit",
+});
+
+struct Host {
+    wasi: WasiCtx,
+    http: WasiHttpCtx,
+    table: ResourceTable,
+}
+
+impl WasiView for Host {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.wasi
+    }
+}
+
+impl WasiHttpView for Host {
+    fn ctx(&mut self) -> &mut WasiHttpCtx {
+        &mut self.http
+    }
+
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+}
+
+fn target_installed(triple: &str) -> bool {
+    let output = Command::new("rustc").args(["--print", "sysroot"]).output().expect("Failed to run rustc");
+    let sysroot = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Path::new(&sysroot).join("lib/rustlib").join(triple).is_dir()
+}
+
+fn build_component(output_dir: &str, target: &str) {
+    let result = Command::new("cargo")
+        .args(&["run", "--",
+                "--input", ".",
+                "--output", output_dir,
+                "--target", target])
+        .output()
+        .expect("Failed to execute rig-wasm-compiler");
+
+    assert!(result.status.success(), "Compilation failed: {:?}", String::from_utf8_lossy(&result.stderr));
+}
+
+#[test]
+fn test_compile_wasip2_component() {
+    if !target_installed("wasm32-wasip2") {
+        eprintln!("skipping: rustup target add wasm32-wasip2 to run this test");
+        return;
+    }
+
+    let output_dir = "test_output/wasip2";
+    build_component(output_dir, "wasip2");
+    assert!(Path::new(&format!("{}/rig_wasm.wasm", output_dir)).exists());
+    assert!(Path::new(&format!("{}/rig_wasm.wit", output_dir)).exists());
+    // No JS glue for WASI targets
+    assert!(!Path::new(&format!("{}/rig_wasm.js", output_dir)).exists());
+
+    let engine = Engine::new(Config::new().wasm_component_model(true)).unwrap();
+    let component = Component::from_file(&engine, format!("{}/rig_wasm.wasm", output_dir)).unwrap();
+
+    let mut linker = Linker::new(&engine);
+    wasmtime_wasi::add_to_linker_sync(&mut linker).unwrap();
+    wasmtime_wasi_http::add_only_http_to_linker_sync(&mut linker).unwrap();
+
+    let wasi = WasiCtxBuilder::new()
+        .env("RIG_MODEL", "test-model")
+        .env("RIG_API_BASE_URL", "http://127.0.0.1:9")
+        .build();
+    let mut store = Store::new(&engine, Host { wasi, http: WasiHttpCtx::new(), table: ResourceTable::new() });
+
+    let bindings = RigAgent::instantiate(&mut store, &component, &linker).unwrap();
+    let agent = bindings.rig_agent_agent();
+
+    agent.call_add_context(&mut store, "You are a helpful assistant.").unwrap();
+    let reply = agent.call_process(&mut store, "Hello, Rig!").unwrap();
+    assert_eq!(reply, Ok("Processed: Hello, Rig!".to_string()));
+    agent.call_clear_context(&mut store).unwrap();
+
+    // Clean up
+    fs::remove_dir_all(output_dir).unwrap();
+}
+
+#[test]
+fn test_compile_wasip1_module() {
+    if !target_installed("wasm32-wasip1") {
+        eprintln!("skipping: rustup target add wasm32-wasip1 to run this test");
+        return;
+    }
+
+    let output_dir = "test_output/wasip1";
+    build_component(output_dir, "wasip1");
+
+    // A core module, not a component
+    let engine = Engine::default();
+    let module = wasmtime::Module::from_file(&engine, format!("{}/rig_wasm.wasm", output_dir)).unwrap();
+    assert!(module.exports().any(|export| export.name().contains("rig:agent/agent")));
+
+    // Clean up
+    fs::remove_dir_all(output_dir).unwrap();
+}